@@ -0,0 +1,256 @@
+// ============================================================
+// srvpru-bench
+// ------------------------------------------------------------
+//! Load generator for a running srvpru.
+//!
+//! Spawns simulated clients, which join rooms in groups, then
+//! play a script of ctos messages, each waiting for a stoc
+//! reply. Latency of each step and the join itself are
+//! reported as percentiles, with throughput, per message type.
+//!
+//! Meant to run against srvpru with `mock_ygopro` as ygopro
+//! binary, whose lobby replies `Chat` and `HsReady` by default.
+//!
+//! ```text
+//! srvpru-bench --addr 127.0.0.1:7911 --clients 200 --host-info M --host-info T,LP4000 --rounds 20
+//! ```
+// ============================================================
+
+#[macro_use] extern crate log;
+#[macro_use] extern crate anyhow;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+use srvpru::ygopro::client::YgoClient;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::generate::wrap_mapped_struct;
+use srvpru::ygopro::message::stoc;
+use srvpru::ygopro::message::string::cast_to_c_array;
+use srvpru::ygopro::message::string::cast_to_string;
+
+/// Length of `pass` in `JoinGame`, with the ending 0.
+const PASS_LENGTH: usize = 20;
+
+const USAGE: &str = "Usage: srvpru-bench [options]
+    --addr <addr>           srvpru address, default 127.0.0.1:7911
+    --clients <n>           simulated clients, default 100
+    --host-info <string>    host info part of room password, like `M` or `T,LP4000`.
+                            Repeat to rotate between rooms. Default no host info.
+    --rounds <n>            times to play the script for each client, default 10
+    --script <path>         json list of steps, default chat and ready
+    --timeout <secs>        wait for each reply, default 5";
+
+// ============================================================
+//  Step
+// ------------------------------------------------------------
+/// One message exchange in script.
+// ============================================================
+#[derive(Deserialize, Debug, Clone)]
+struct Step {
+    /// Name in report, type of `send` if not given.
+    #[serde(default)]
+    name: Option<String>,
+    /// Complete ctos frames, with length and type header.
+    send: Vec<u8>,
+    /// Type byte of stoc message, which completes this step.
+    expect: u8
+}
+
+impl Step {
+    fn new<T: srvpru::ygopro::message::Struct + srvpru::ygopro::message::MappedStruct + serde::Serialize>(obj: &T, expect: stoc::MessageType) -> Step {
+        Step { name: None, send: wrap_mapped_struct(obj), expect: expect.into() }
+    }
+
+    fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => match self.send.get(2).and_then(|&type_byte| ctos::MessageType::try_from(type_byte).ok()) {
+                Some(message_type) => format!("{:?}", message_type),
+                None => format!("CTOS {:?}", self.send.get(2))
+            }
+        }
+    }
+}
+
+fn default_script() -> Vec<Step> {
+    vec![
+        Step::new(&ctos::Chat { msg: cast_to_c_array("srvpru-bench") }, stoc::MessageType::Chat),
+        Step::new(&ctos::HsReady, stoc::MessageType::HsPlayerChange),
+        Step::new(&ctos::HsNotReady, stoc::MessageType::HsPlayerChange),
+    ]
+}
+
+struct Options {
+    addr: SocketAddr,
+    clients: usize,
+    host_infos: Vec<String>,
+    rounds: usize,
+    script: Vec<Step>,
+    timeout: Duration
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> anyhow::Result<Options> {
+        let mut options = Options {
+            addr: "127.0.0.1:7911".parse()?,
+            clients: 100,
+            host_infos: Vec::new(),
+            rounds: 10,
+            script: default_script(),
+            timeout: Duration::from_secs(5)
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| anyhow!("Missing value of {}", arg));
+            match arg.as_str() {
+                "--addr" => options.addr = value()?.parse()?,
+                "--clients" => options.clients = value()?.parse()?,
+                "--host-info" => options.host_infos.push(value()?),
+                "--rounds" => options.rounds = value()?.parse()?,
+                "--script" => options.script = serde_json::from_reader(std::fs::File::open(value()?)?)?,
+                "--timeout" => options.timeout = Duration::from_secs(value()?.parse()?),
+                _ => Err(anyhow!("Unknown option {}", arg))?
+            }
+        }
+        if options.host_infos.is_empty() { options.host_infos.push(String::new()); }
+        Ok(options)
+    }
+}
+
+/// Players to fill a room with this host info.
+fn room_size(host_info: &str) -> usize {
+    if host_info.split(',').any(|controller| matches!(controller.trim(), "T" | "TAG")) { 4 } else { 2 }
+}
+
+// ============================================================
+//  Report
+// ------------------------------------------------------------
+/// Latencies and failures of each step, by label.
+// ============================================================
+#[derive(Default)]
+struct Report {
+    latencies: BTreeMap<String, Vec<Duration>>,
+    failures: BTreeMap<String, usize>
+}
+
+impl Report {
+    fn record(&mut self, label: &str, latency: Option<Duration>) {
+        match latency {
+            Some(latency) => self.latencies.entry(label.to_string()).or_default().push(latency),
+            None => *self.failures.entry(label.to_string()).or_default() += 1
+        }
+    }
+
+    fn print(&mut self, elapsed: Duration) {
+        println!("{:<16}{:>8}{:>8}{:>10}{:>10}{:>10}{:>10}{:>12}", "message", "ok", "failed", "p50(ms)", "p90(ms)", "p99(ms)", "max(ms)", "msg/s");
+        let labels: BTreeSet<String> = self.latencies.keys().chain(self.failures.keys()).cloned().collect();
+        for label in labels {
+            let latencies = self.latencies.entry(label.clone()).or_default();
+            latencies.sort();
+            let failures = self.failures.get(&label).copied().unwrap_or_default();
+            let milliseconds = |latency: Option<Duration>| latency.map(|latency| latency.as_secs_f64() * 1000.0).unwrap_or(f64::NAN);
+            println!("{:<16}{:>8}{:>8}{:>10.2}{:>10.2}{:>10.2}{:>10.2}{:>12.1}", label, latencies.len(), failures,
+                milliseconds(percentile(latencies, 50)), milliseconds(percentile(latencies, 90)),
+                milliseconds(percentile(latencies, 99)), milliseconds(latencies.last().copied()),
+                latencies.len() as f64 / elapsed.as_secs_f64());
+        }
+        println!("elapsed {:.2}s", elapsed.as_secs_f64());
+    }
+}
+
+/// Nearest-rank percentile of sorted `latencies`.
+fn percentile(latencies: &[Duration], percent: usize) -> Option<Duration> {
+    if latencies.is_empty() { return None; }
+    let rank = ((latencies.len() * percent + 99) / 100).max(1);
+    latencies.get(rank - 1).copied()
+}
+
+/// Receive until a stoc of `type_byte`, `None` if connection closed.
+async fn wait_for(client: &mut YgoClient, type_byte: u8) -> Option<()> {
+    loop {
+        let message = client.recv().await?;
+        if message.raw.get(2) == Some(&type_byte) { return Some(()); }
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  simulate
+// ----------------------------------------------------------------------------------------------------
+/// Join `pass` as `name`, then play script for rounds.
+///
+/// Stops at the first failed step, since later replies can't be told apart.
+// ----------------------------------------------------------------------------------------------------
+async fn simulate(options: Arc<Options>, report: Arc<Mutex<Report>>, name: String, pass: String) {
+    let start = Instant::now();
+    let mut client = match YgoClient::connect(options.addr).player_info(&name).join(&pass).await {
+        Ok(client) => client,
+        Err(e) => { warn!("{} failed to connect: {}", name, e); report.lock().record("JoinGame", None); return; }
+    };
+    let joined = tokio::time::timeout(options.timeout, client.expect::<stoc::HsPlayerEnter, _>(|enter| cast_to_string(&enter.name).as_deref() == Some(name.as_str()))).await;
+    let joined = matches!(joined, Ok(Some(_)));
+    report.lock().record("JoinGame", joined.then(|| start.elapsed()));
+    if !joined { return; }
+
+    for _ in 0..options.rounds {
+        for step in options.script.iter() {
+            while client.try_recv().is_some() {}
+            let start = Instant::now();
+            let replied = match client.send_raw(&step.send).await {
+                Ok(_) => matches!(tokio::time::timeout(options.timeout, wait_for(&mut client, step.expect)).await, Ok(Some(_))),
+                Err(_) => false
+            };
+            report.lock().record(&step.label(), replied.then(|| start.elapsed()));
+            if !replied { return; }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let options = Arc::new(Options::parse(args.into_iter()).map_err(|e| anyhow!("{}\n{}", e, USAGE))?);
+    let report = Arc::new(Mutex::new(Report::default()));
+
+    let start = Instant::now();
+    let mut tasks = Vec::new();
+    let (mut room, mut seat) = (0, 0);
+    for index in 0..options.clients {
+        let host_info = &options.host_infos[room % options.host_infos.len()];
+        // Process id keeps rooms apart from former runs, which may not be destroyed yet.
+        let room_name = format!("b{}_{}", std::process::id() % 10000, room);
+        let pass = if host_info.is_empty() { room_name } else { format!("{}#{}", host_info, room_name) };
+        if pass.encode_utf16().count() >= PASS_LENGTH { bail!("Room password {} is too long, shorten host info.", pass); }
+        tasks.push(tokio::spawn(simulate(options.clone(), report.clone(), format!("bench{}", index), pass)));
+        seat += 1;
+        if seat >= room_size(host_info) { room += 1; seat = 0; }
+    }
+    for task in tasks { task.await?; }
+    report.lock().print(start.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentile() {
+        let latencies: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&latencies, 50), Some(Duration::from_millis(5)));
+        assert_eq!(percentile(&latencies, 99), Some(Duration::from_millis(10)));
+        assert_eq!(percentile(&latencies[..1], 0), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&[], 50), None);
+    }
+}
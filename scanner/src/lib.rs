@@ -10,6 +10,8 @@ use proc_macro::TokenStream;
 /// generate code for each file under target directory.    
 /// - use `#name` for file name.   
 /// - use `#fullname` for generated class name.
+/// - use `#dependencies` for `#fullname::DEPENDENCIES` if file calls `depend_on!`, or `&[]`.
+/// - use `#children` for `Some(#fullname::plugins)` if a directory of same name exists, or `None`.
 /// 
 /// A changed version for [automod](https://crates.io/crates/automod).
 // ----------------------------------------------------------------------------------------------------
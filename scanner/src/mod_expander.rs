@@ -38,10 +38,17 @@ pub fn execute_for_each_under_dir(input: TokenStream) -> TokenStream {
     println!("Searching directory {:?}...", &dir);
     println!("Generate logical path is {:}", &logical_path);
 
-    let expanded = match scan_directory(dir) {
+    let expanded = match scan_directory(&dir) {
         Ok(names) => names.into_iter().map(|name| {
             let fullname = logical_path.clone() + &name;
-            let actual_execution = execution.replace("# name", &name)
+            let declared = declares_dependencies(&dir.join(format!("{}.rs", name))) || declares_dependencies(&dir.join(&name).join("mod.rs"));
+            let dependencies = if declared { format!("{}::DEPENDENCIES", fullname) } else { "&[]".to_string() };
+            let children = if dir.join(&name).is_dir() { format!("Some({}::plugins)", fullname) } else { "None".to_string() };
+            let actual_execution = execution.replace("# dependencies", &dependencies)
+                                                   .replace("#dependencies", &dependencies)
+                                                   .replace("# children", &children)
+                                                   .replace("#children", &children)
+                                                   .replace("# name", &name)
                                                    .replace("#name", &name)
                                                    .replace("# fullname", &fullname)
                                                    .replace("#fullname", &fullname);
@@ -74,3 +81,10 @@ fn scan_directory<P: AsRef<Path>>(dir: P) -> Result<Vec<String>, Box<dyn std::er
     names.sort();
     Ok(names)
 }
+
+/// If a `depend_on!` is called at top level of file, so it exports `DEPENDENCIES`.
+fn declares_dependencies(file: &Path) -> bool {
+    let content = match fs::read_to_string(file) { Ok(content) => content, Err(_) => return false };
+    let file = match syn::parse_file(&content) { Ok(file) => file, Err(_) => return false };
+    file.items.iter().any(|item| matches!(item, syn::Item::Macro(item) if item.mac.path.is_ident("depend_on")))
+}
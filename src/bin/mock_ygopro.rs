@@ -0,0 +1,231 @@
+// ============================================================
+// mock_ygopro
+// ------------------------------------------------------------
+//! A stand-in of ygopro server, for integration tests and
+//! benchmarks which can't afford the real engine.
+//!
+//! Follow the contract of `Room::spawn`:
+//! - take host info as process arguments.
+//! - print listening port as the first line of stdout.
+//! - exit when all players leave, so the room is destroyed.
+//!
+//! Only lobby is simulated: join, ready, chat, leave, and
+//! `DuelStart` for `HsStart`. Anything else can be scripted by
+//! a json file at `MOCK_YGOPRO_SCRIPT`, as a list of [Rule].
+// ============================================================
+
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+use srvpru::srvpru::MessageFramer;
+use srvpru::ygopro::Mode;
+use srvpru::ygopro::Netplayer;
+use srvpru::ygopro::PlayerChange;
+use srvpru::ygopro::message::HostInfo;
+use srvpru::ygopro::message::MappedStruct;
+use srvpru::ygopro::message::Struct;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::generate::wrap_mapped_struct;
+use srvpru::ygopro::message::stoc;
+
+/// Close the process if nobody joins in that seconds.
+const IDLE_TIMEOUT: u64 = 60;
+
+/// Extra replies to a ctos message, after lobby behaviours.
+#[derive(Deserialize, Debug)]
+struct Rule {
+    /// Type byte of ctos message.
+    ctos: u8,
+    /// Send to all players, or just the sender.
+    #[serde(default)]
+    broadcast: bool,
+    /// Complete stoc frames, with length and type header.
+    stoc: Vec<Vec<u8>>
+}
+
+struct Client {
+    id: usize,
+    name: [u16; 20],
+    position: Netplayer,
+    sender: mpsc::UnboundedSender<Vec<u8>>
+}
+
+#[derive(Default)]
+struct Lobby {
+    host_info: HostInfo,
+    rules: Vec<Rule>,
+    clients: Vec<Client>,
+    ever_joined: bool
+}
+
+impl Lobby {
+    fn duelist_count(&self) -> u8 {
+        if self.host_info.mode == Mode::Tag { 4 } else { 2 }
+    }
+
+    fn free_position(&self) -> Netplayer {
+        (0..self.duelist_count())
+            .find(|position| !self.clients.iter().any(|client| client.position as u8 == *position))
+            .and_then(|position| Netplayer::try_from(position).ok())
+            .unwrap_or(Netplayer::Observer)
+    }
+
+    fn broadcast(&self, data: &[u8]) {
+        for client in self.clients.iter() {
+            client.sender.send(data.to_vec()).ok();
+        }
+    }
+}
+
+fn parse_host_info(args: &[String]) -> HostInfo {
+    let mut host_info = HostInfo::default();
+    let arg = |index: usize| args.get(index).map(|arg| arg.as_str()).unwrap_or_default();
+    if let Ok(lflist) = arg(1).parse() { host_info.lflist = lflist; }
+    if let Ok(rule) = arg(2).parse() { host_info.rule = rule; }
+    if let Some(mode) = arg(3).parse::<u8>().ok().and_then(|mode| Mode::try_from(mode).ok()) { host_info.mode = mode; }
+    if let Ok(duel_rule) = arg(4).parse() { host_info.duel_rule = duel_rule; }
+    host_info.no_check_deck = arg(5) == "T";
+    host_info.no_shuffle_deck = arg(6) == "T";
+    if let Ok(start_lp) = arg(7).parse() { host_info.start_lp = start_lp; }
+    if let Ok(start_hand) = arg(8).parse() { host_info.start_hand = start_hand; }
+    if let Ok(draw_count) = arg(9).parse() { host_info.draw_count = draw_count; }
+    if let Ok(time_limit) = arg(10).parse() { host_info.time_limit = time_limit; }
+    host_info
+}
+
+fn load_rules() -> anyhow::Result<Vec<Rule>> {
+    match std::env::var("MOCK_YGOPRO_SCRIPT") {
+        Ok(path) => Ok(serde_json::from_reader(std::fs::File::open(path)?)?),
+        Err(_) => Ok(Vec::new())
+    }
+}
+
+fn body<T: for<'de> Deserialize<'de>>(data: &[u8]) -> Option<T> {
+    bincode::deserialize(data).ok()
+}
+
+fn frame<T: Struct + MappedStruct + serde::Serialize>(obj: &T) -> Vec<u8> {
+    wrap_mapped_struct(obj)
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  process
+// ----------------------------------------------------------------------------------------------------
+/// Act as ygopro lobby for one ctos message.
+///
+/// #### Arguments
+/// * `name`: name from `PlayerInfo`, kept until `JoinGame`.
+/// * `position`: position after `JoinGame`.
+// ----------------------------------------------------------------------------------------------------
+fn process(lobby: &Mutex<Lobby>, id: usize, sender: &mpsc::UnboundedSender<Vec<u8>>, name: &mut [u16; 20], position: &mut Option<Netplayer>, message_type: u8, data: &[u8]) {
+    let mut lobby = lobby.lock();
+    match ctos::MessageType::try_from(message_type) {
+        Ok(ctos::MessageType::PlayerInfo) => if let Some(info) = body::<ctos::PlayerInfo>(data) { *name = info.name; },
+        Ok(ctos::MessageType::JoinGame) if position.is_none() => {
+            let new_position = lobby.free_position();
+            let host = if new_position == Netplayer::Player1 { 0x10 } else { 0 };
+            sender.send(frame(&stoc::JoinGame { info: lobby.host_info.clone() })).ok();
+            sender.send(frame(&stoc::TypeChange { _type: new_position as u8 | host })).ok();
+            for client in lobby.clients.iter().filter(|client| client.position != Netplayer::Observer) {
+                sender.send(frame(&stoc::HsPlayerEnter { name: client.name, pos: client.position })).ok();
+            }
+            lobby.clients.push(Client { id, name: *name, position: new_position, sender: sender.clone() });
+            lobby.ever_joined = true;
+            if new_position != Netplayer::Observer {
+                lobby.broadcast(&frame(&stoc::HsPlayerEnter { name: *name, pos: new_position }));
+            }
+            *position = Some(new_position);
+        },
+        Ok(ctos::MessageType::HsReady) => if let Some(position) = position {
+            lobby.broadcast(&frame(&stoc::HsPlayerChange { status: PlayerChange::Ready(*position) }));
+        },
+        Ok(ctos::MessageType::HsNotReady) => if let Some(position) = position {
+            lobby.broadcast(&frame(&stoc::HsPlayerChange { status: PlayerChange::Notready(*position) }));
+        },
+        Ok(ctos::MessageType::Chat) => if let (Some(position), Some(chat)) = (*position, body::<ctos::Chat>(data)) {
+            lobby.broadcast(&frame(&stoc::Chat { name: position as u16, msg: chat.msg }));
+        },
+        Ok(ctos::MessageType::HsStart) => lobby.broadcast(&frame(&stoc::DuelStart)),
+        Ok(ctos::MessageType::LeaveGame) => if let Some(position) = position.take() {
+            leave(&mut lobby, id, position);
+        },
+        _ => {}
+    }
+    for rule in lobby.rules.iter().filter(|rule| rule.ctos == message_type) {
+        for data in rule.stoc.iter() {
+            if rule.broadcast { lobby.broadcast(data); }
+            else { sender.send(data.clone()).ok(); }
+        }
+    }
+}
+
+fn leave(lobby: &mut Lobby, id: usize, position: Netplayer) {
+    lobby.clients.retain(|client| client.id != id);
+    if position != Netplayer::Observer {
+        lobby.broadcast(&frame(&stoc::HsPlayerChange { status: PlayerChange::Leave(position) }));
+    }
+}
+
+async fn serve(lobby: Arc<Mutex<Lobby>>, id: usize, stream: TcpStream) {
+    let (mut reader, mut writer) = stream.into_split();
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<u8>>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(data) = receiver.recv().await {
+            if writer.write_all(&data).await.is_err() { break; }
+        }
+    });
+    let mut buf = [0; 10240];
+    let mut framer = MessageFramer::new();
+    let mut name = [0u16; 20];
+    let mut position = None;
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n
+        };
+        let frames = match framer.feed(&buf[0..n]) {
+            Ok(Some(frames)) => frames,
+            Ok(None) => continue,
+            Err(_) => break
+        };
+        let mut rest = &frames[..];
+        while rest.len() >= 3 {
+            let length = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            process(&lobby, id, &sender, &mut name, &mut position, rest[2], &rest[3..2 + length]);
+            rest = &rest[2 + length..];
+        }
+    }
+    let mut _lobby = lobby.lock();
+    if let Some(position) = position { leave(&mut _lobby, id, position); }
+    // Like ygopro, the server ends with its last player.
+    if _lobby.ever_joined && _lobby.clients.is_empty() { std::process::exit(0); }
+    drop(_lobby);
+    writer_task.abort();
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let lobby = Arc::new(Mutex::new(Lobby { host_info: parse_host_info(&args), rules: load_rules()?, ..Default::default() }));
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    println!("{}", listener.local_addr()?.port());
+
+    let idle_lobby = lobby.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(tokio::time::Duration::from_secs(IDLE_TIMEOUT)).await;
+        if !idle_lobby.lock().ever_joined { std::process::exit(0); }
+    });
+
+    for id in 0.. {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(serve(lobby.clone(), id, stream));
+    }
+    Ok(())
+}
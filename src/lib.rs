@@ -0,0 +1,50 @@
+#[macro_use] extern crate downcast_rs;
+#[macro_use] extern crate erased_serde;
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate thiserror;
+#[macro_use] extern crate bitflags;
+#[macro_use] extern crate anyhow;
+#[macro_use] extern crate scanner;
+#[macro_use] extern crate log;
+extern crate pretty_env_logger;
+
+#[macro_use] pub mod ygopro;
+#[macro_use] pub mod srvpru;
+pub mod srvpro;
+
+use crate::srvpru::Player;
+use crate::srvpru::Room;
+use crate::srvpru::Server;
+use crate::srvpru::get_server;
+use crate::ygopro::data::Card;
+
+/// Load configurations from `SRVPRU_CONFIG_PATH`, and cards.
+pub async fn init() {
+    srvpro::generate_srvpru_configuration().await;
+    crate::srvpru::load_configuration().expect("Failed to load srvpru configuration.");
+    match Card::load_all_cards().await {
+        Ok(_) => info!("Loaded {} cards.", crate::ygopro::data::CARDS.read().len()),
+        Err(e) => warn!("Failed to load cards, card names and deck validation won't work: {}", e)
+    }
+}
+
+/// Load i18n and plugins, then build the socket server.
+pub fn register() {
+    crate::srvpru::i18n::init().expect("Failed to load i18n");
+    Player::init().expect("Failed to load base plugin: player");
+    Room::init().expect("Failed to load plugin: room");
+    crate::srvpru::plugins::init().expect("Init plugins failed");
+    Server::init().expect("Failed to init socket server");
+}
+
+/// Run socket server until it terminates, or shut it down gracefully on SIGTERM/SIGINT.
+pub async fn start() {
+    let server = get_server();
+    tokio::select! {
+        result = server.start() => {
+            result.expect("Failed to start socket server");
+            error!("Terminated server. Srvpru is going to down.");
+        },
+        _ = async { crate::srvpru::wait_for_shutdown_signal().await; server.shutdown().await } => {}
+    }
+}
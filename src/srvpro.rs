@@ -36,6 +36,13 @@ pub async fn generate_srvpru_configuration() {
         Err(_) => return,
     };
     info!("Generating configuration from srvpro.");
+    write_to_file("version_checker", &plugins::version_checker::Configuration { version: configuration.version });
+    let (srvpru_configuration, srvpru_plugin_configurations) = migrate(configuration).await;
+    write_configs(srvpru_configuration, srvpru_plugin_configurations);
+}
+
+/// Translate srvpro configuration to srvpru one, and configurations of plugins by name.
+async fn migrate(configuration: SrvproConfiguration) -> (Configuration, HashMap<String, Value>) {
     let mut srvpru_configuration = Configuration::default();
     // At this stage, dependecy hasn't been registered.
     // So just take all of them in.
@@ -44,10 +51,11 @@ pub async fn generate_srvpru_configuration() {
     srvpru_configuration.plugins.push("stage_recorder".to_string());
     srvpru_configuration.plugins.push("chat_command".to_string());
     srvpru_configuration.plugins.push("api".to_string());
+    // Configured by srvpro version.
+    srvpru_configuration.plugins.push("version_checker".to_string());
     let mut srvpru_plugin_configurations: HashMap<String, Value> = HashMap::new();
 
     srvpru_configuration.ygopro.host_info = configuration.hostinfo;
-    write_to_file("version_checker", &plugins::version_checker::Configuration { version: configuration.version });
 
     for (name, module_config) in configuration.modules {
         let config = &mut srvpru_configuration;
@@ -97,7 +105,7 @@ pub async fn generate_srvpru_configuration() {
         }
     }
     
-    write_configs(srvpru_configuration, srvpru_plugin_configurations);
+    (srvpru_configuration, srvpru_plugin_configurations)
 }
 
 fn load_configuration<T: serde::de::DeserializeOwned>(name: &str) -> anyhow::Result<T> {
@@ -316,7 +324,7 @@ struct CloudReplayConfig {
 #[async_trait]
 impl SrvproModuleConfiguration for CloudReplayConfig {
     async fn fit_srvpru(self, config: &mut Configuration, _: &mut HashMap<String, Value>) {
-        if self.enabled { warn!("Srvpru don't support cloud replay.") }
+        if self.enabled { config.plugins.push("replay_archive".to_string()); }
         if self.enabled_halfway_watch == Some(true) {
             config.plugins.push("telescreen".to_string());
         }
@@ -576,4 +584,26 @@ impl<'de> serde::de::Visitor<'de> for SrvproValueVisitor {
 
 fn deserialize_srvpro_value<'de, D>(deserializer: D) -> Result<Option<String>, D::Error> where D: serde::de::Deserializer<'de> {
     deserializer.deserialize_any(SrvproValueVisitor)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn migrated_plugins_have_dependencies() {
+        let srvpro: SrvproConfiguration = serde_json::from_value(serde_json::json!({
+            "file": "./config/config.user.json",
+            "port": 7911,
+            "version": 4946,
+            "hostinfo": {},
+            "modules": { "cloud_replay": { "enabled": true, "enabled_halfway_watch": true } },
+            "ban": {}
+        })).unwrap();
+        let (configuration, _) = migrate(srvpro).await;
+        assert!(configuration.plugins.iter().any(|plugin| plugin == "telescreen"));
+        let all_plugins = plugins::plugins();
+        assert!(all_plugins.iter().any(|plugin| plugin.name == "telescreen" && plugin.dependencies.contains(&"version_checker")));
+        let (_, missing) = plugins::resolve_dependencies(&all_plugins, configuration.plugins, &[], false);
+        assert_eq!(missing, []);
+    }
+}
@@ -3,16 +3,25 @@
 #[macro_use] mod utils;
 mod processor;
 mod server;
+mod framer;
+mod sink;
+mod selector;
 
 #[macro_use] pub mod plugins;
 #[macro_use] pub mod message;
 pub mod i18n;
+pub mod metrics;
+pub mod proxy_protocol;
+pub mod tls;
 
 pub use processor::*;
 pub use server::*;
 pub use room::*;
 pub use player::*;
 pub use utils::*;
+pub use framer::*;
+pub use sink::*;
+pub use selector::*;
 
 #[doc(hidden)] fn default_ygopro_cwd() -> String{ "./ygopro".to_string() }
 #[doc(hidden)] fn default_ygopro_address() -> String { "127.0.0.1".to_string() }
@@ -32,6 +41,10 @@ pub struct YgoproConfiguration {
     /// Cards.cdb position. Don't needed if you don't 
     #[serde(default = "default_ygopro_database")]
     pub database: String,
+    /// Extra cdb files, or directories of cdb files, relative to `cwd`. \
+    /// Cards in them override `database`, and later ones override former ones.
+    #[serde(default = "default_empty_vec_owned")]
+    pub expansions: Vec<String>,
     /// Ygopro server path.
     #[serde(default = "default_ygopro_binary")]
     binary: String,
@@ -62,13 +75,44 @@ set_configuration! {
     /// After that milliseconds without any message, socket will close.
     #[serde(default = "default_timeout")]
     timeout: u64,
+    /// Expect a PROXY protocol (v1 or v2) header on each connection, and take the client address in it. \
+    /// Enable only behind a balancer sending it, connections without the header are refused.
+    #[serde(default)]
+    proxy_protocol: bool,
+    /// On SIGTERM or SIGINT, wait for rooms to end for at most that seconds, before killing them. \
+    /// Set to `0` to kill immediately.
+    #[serde(default)]
+    drain_timeout: u64,
+    /// Deadline of each handler in milliseconds, unless set in `handler_policies`. \
+    /// Set to `0` for no deadline.
+    #[serde(default)]
+    handler_timeout: u64,
+    /// Deadline and failure policy by handler name, e.g. for handlers calling http apis:
+    /// ```yaml
+    /// handler_policies:
+    ///   mycard_login: { timeout: 3000, on_failure: closed }
+    ///   newspeak_chat: { timeout: 1000, on_failure: open }
+    /// ```
+    #[serde(default)]
+    handler_policies: std::collections::HashMap<String, HandlerPolicy>,
+    /// Tls listeners beside plain ones, see [TlsConfiguration](tls::TlsConfiguration).
+    #[serde(default)]
+    tls: Option<tls::TlsConfiguration>,
     /// Ygopro server configuration.
     #[serde(default)]
     ygopro: YgoproConfiguration,
     /// Enabled plugins. \
-    /// Srvpru will compile and load all plugins when start, and load plugins enabled in this configuration.
+    /// Srvpru will compile and load all plugins when start, and load plugins enabled in this configuration. \
+    /// Reread on [Reload](message::Reload), or changed by [Server::set_plugins] at runtime. \
+    /// A plugin can be limited to some rooms by `rooms` in its own configuration, see [load_room_selector](plugins::load_room_selector).
     #[serde(default = "default_plugins")]
     plugins: Vec<String>,
+    /// Enable dependencies declared by [depend_on!] of enabled plugins, if they are not listed in `plugins`.
+    #[serde(default)]
+    auto_enable_dependencies: bool,
+    /// Start even if dependencies of enabled plugins are not enabled, with only warnings.
+    #[serde(default)]
+    allow_missing_dependencies: bool,
     /// Additional handlers on `stoc` after plugins loaded.
     #[serde(default = "default_empty_vec_owned")]
     stoc_handlers: Vec<String>,
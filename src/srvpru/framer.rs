@@ -0,0 +1,155 @@
+// ============================================================
+// framer
+// ------------------------------------------------------------
+//! Reassemble ygopro packets from a tcp stream.
+//!
+//! A single `read` on a socket may contain half a packet, or
+//! several packets with a partial one in the tail.
+//! [MessageFramer] keeps the residual bytes per connection,
+//! and only hand out complete `[length:u16][type:u8][body]` frames.
+// ============================================================
+
+use crate::srvpru::ProcessorError;
+
+/// Size of the length header in front of each packet.
+const LENGTH_HEADER_SIZE: usize = 2;
+
+/// Longest frame a client may send, which fits in one read of srvpru.
+pub const MAX_CLIENT_FRAME_LENGTH: usize = 10240 - LENGTH_HEADER_SIZE;
+
+// ============================================================
+//  MessageFramer
+// ------------------------------------------------------------
+/// Per-connection buffer for splitting a byte stream into packets.
+// ============================================================
+#[derive(Default, Debug)]
+pub struct MessageFramer {
+    /// Bytes received but not yet formed a complete frame.
+    buffer: Vec<u8>,
+    /// Longest length a frame may declare, any `u16` if not set.
+    max_length: Option<usize>
+}
+
+impl MessageFramer {
+    pub fn new() -> MessageFramer {
+        MessageFramer::default()
+    }
+
+    /// A framer refusing frames declaring a length over `max_length`, 
+    /// so that a client can't make srvpru buffer a large frame for it.
+    pub fn with_max_length(max_length: usize) -> MessageFramer {
+        MessageFramer { buffer: Vec::new(), max_length: Some(max_length) }
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  feed
+    // ----------------------------------------------------------------------------------------------------
+    /// Append data read from socket, and take out all complete frames.
+    ///
+    /// #### Arguments
+    /// * `data`: bytes just read from socket.
+    ///
+    /// #### Return
+    /// * `Ok(Some(frames))`: one or more complete frames, concatenated, ready for
+    ///   [process_multiple_messages](crate::srvpru::Processor#method.process_multiple_messages).
+    /// * `Ok(None)`: data is not enough for a frame yet, wait for next read.
+    /// * `Err(ProcessorError::ProtoLength)`: a frame declares a zero length, which can't even hold a type.
+    ///   The stream can't be trusted any more; residual data is dropped.
+    /// * `Err(ProcessorError::Oversize)`: a frame declares a length over the max length. Residual data is dropped too.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn feed(&mut self, data: &[u8]) -> Result<Option<Vec<u8>>, ProcessorError> {
+        self.buffer.extend_from_slice(data);
+        let mut complete = 0;
+        while self.buffer.len() - complete >= LENGTH_HEADER_SIZE {
+            let length = u16::from_le_bytes([self.buffer[complete], self.buffer[complete + 1]]) as usize;
+            if length == 0 {
+                self.buffer.clear();
+                return Err(ProcessorError::ProtoLength);
+            }
+            if matches!(self.max_length, Some(max_length) if length > max_length) {
+                self.buffer.clear();
+                return Err(ProcessorError::Oversize);
+            }
+            if self.buffer.len() - complete < LENGTH_HEADER_SIZE + length { break; }
+            complete += LENGTH_HEADER_SIZE + length;
+        }
+        if complete == 0 { return Ok(None); }
+        let rest = self.buffer.split_off(complete);
+        Ok(Some(std::mem::replace(&mut self.buffer, rest)))
+    }
+
+    /// Count of bytes waiting for the rest of their frame.
+    #[allow(dead_code)]
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Drop all residual bytes.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(message_type: u8, body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 1) as u16).to_le_bytes().to_vec();
+        data.push(message_type);
+        data.extend_from_slice(body);
+        data
+    }
+
+    #[test]
+    fn reassemble_split_frames() {
+        let mut framer = MessageFramer::new();
+        let data = frame(0x10, &[1, 2, 3, 4]);
+        assert_eq!(framer.feed(&data[..1]).unwrap(), None);
+        assert_eq!(framer.feed(&data[1..4]).unwrap(), None);
+        assert_eq!(framer.pending(), 4);
+        assert_eq!(framer.feed(&data[4..]).unwrap(), Some(data));
+        assert_eq!(framer.pending(), 0);
+    }
+
+    #[test]
+    fn hand_out_several_frames_of_one_read() {
+        let mut framer = MessageFramer::new();
+        let first = frame(0x10, &[1, 2]);
+        let second = frame(0x11, &[]);
+        let third = frame(0x12, &[3, 4, 5]);
+        let mut data = [first.clone(), second.clone(), third.clone()].concat();
+        data.truncate(data.len() - 2);
+        assert_eq!(framer.feed(&data).unwrap(), Some([first, second].concat()));
+        assert_eq!(framer.pending(), third.len() - 2);
+        assert_eq!(framer.feed(&third[third.len() - 2..]).unwrap(), Some(third));
+    }
+
+    #[test]
+    fn refuse_zero_length() {
+        let mut framer = MessageFramer::new();
+        let data = [frame(0x10, &[1]), vec![0, 0, 0x11]].concat();
+        assert!(matches!(framer.feed(&data), Err(ProcessorError::ProtoLength)));
+        assert_eq!(framer.pending(), 0);
+    }
+
+    #[test]
+    fn refuse_oversized_length() {
+        let mut framer = MessageFramer::with_max_length(16);
+        assert_eq!(framer.feed(&frame(0x10, &[0; 15])).unwrap(), Some(frame(0x10, &[0; 15])));
+        // Refused by header, before body arrives.
+        assert!(matches!(framer.feed(&frame(0x10, &[0; 16])[..3]), Err(ProcessorError::Oversize)));
+        assert_eq!(framer.pending(), 0);
+    }
+
+    #[test]
+    fn wait_for_frame_longer_than_reads() {
+        let mut framer = MessageFramer::new();
+        let data = frame(0x10, &vec![7; u16::MAX as usize - 1]);
+        for chunk in data[..data.len() - 1].chunks(10240) {
+            assert_eq!(framer.feed(chunk).unwrap(), None);
+        }
+        assert_eq!(framer.pending(), data.len() - 1);
+        assert_eq!(framer.feed(&data[data.len() - 1..]).unwrap(), Some(data));
+    }
+}
@@ -46,6 +46,7 @@ pub enum MessageType {
     StructSequence,    
     ServerStart,
     Reload,
+    Shutdown,
 
     RoomCreated,
     PlayerDestroy,
@@ -66,6 +67,10 @@ pub struct ServerStart;
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct Reload;
 
+/// Srvpru is going to exit, all rooms are closed. Flush anything need to be kept.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Shutdown;
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 // #[srvpru]
 pub struct RoomCreated {
@@ -0,0 +1,83 @@
+// ============================================================
+// metrics
+// ------------------------------------------------------------
+//! Prometheus metrics collected by core.
+//!
+//! Collecting is off until [enable] is called, which is done
+//! by `metrics` plugin. The plugin also exports [REGISTRY]
+//! on `/metrics`.
+// ============================================================
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use prometheus::Histogram;
+use prometheus::HistogramOpts;
+use prometheus::HistogramVec;
+use prometheus::IntCounter;
+use prometheus::IntCounterVec;
+use prometheus::Opts;
+use prometheus::Registry;
+use prometheus::core::Collector;
+
+use crate::srvpru::ListenError;
+use crate::srvpru::ProcessorError;
+use crate::ygopro::message::Direction;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    /// All srvpru metrics, including those registered by plugins.
+    pub static ref REGISTRY: Registry = Registry::new_custom(Some("srvpru".to_string()), None).unwrap();
+    pub static ref HANDLER_CALLS: IntCounterVec = register(IntCounterVec::new(Opts::new("handler_calls_total", "Handler invocations."), &["handler"]).unwrap());
+    pub static ref HANDLER_SECONDS: HistogramVec = register(HistogramVec::new(HistogramOpts::new("handler_duration_seconds", "Time spent in handler."), &["handler"]).unwrap());
+    pub static ref PROCESSOR_ERRORS: IntCounterVec = register(IntCounterVec::new(Opts::new("processor_errors_total", "Processor errors by variant."), &["direction", "error"]).unwrap());
+    pub static ref LISTEN_ERRORS: IntCounterVec = register(IntCounterVec::new(Opts::new("listen_errors_total", "Socket listen errors by variant."), &["direction", "error"]).unwrap());
+    pub static ref SPAWN_FAILURES: IntCounter = register(IntCounter::new("ygopro_spawn_failures_total", "Ygopro processes failed to spawn.").unwrap());
+}
+
+/// Add `collector` to [REGISTRY], and give it back.
+pub fn register<T: Collector + Clone + 'static>(collector: T) -> T {
+    REGISTRY.register(Box::new(collector.clone())).expect("Metric registered twice");
+    collector
+}
+
+/// Start collecting.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn direction_label(direction: Direction) -> &'static str {
+    match direction {
+        Direction::STOC => "stoc",
+        Direction::CTOS => "ctos",
+        Direction::SRVPRU => "srvpru"
+    }
+}
+
+/// Timer of one handler invocation, observed on drop. `None` if not enabled.
+pub fn start_handler_timer(handler: &str) -> Option<prometheus::HistogramTimer> {
+    if !enabled() { return None; }
+    HANDLER_CALLS.with_label_values(&[handler]).inc();
+    let histogram: Histogram = HANDLER_SECONDS.with_label_values(&[handler]);
+    Some(histogram.start_timer())
+}
+
+pub fn record_processor_error(direction: Direction, error: &ProcessorError) {
+    if !enabled() { return; }
+    PROCESSOR_ERRORS.with_label_values(&[direction_label(direction), error.variant()]).inc();
+}
+
+pub fn record_listen_error(direction: Direction, error: &ListenError) {
+    if !enabled() { return; }
+    LISTEN_ERRORS.with_label_values(&[direction_label(direction), error.variant()]).inc();
+}
+
+pub fn record_spawn_failure() {
+    if !enabled() { return; }
+    SPAWN_FAILURES.inc();
+}
@@ -11,10 +11,9 @@ use std::collections::HashMap;
 
 use tokio::task::JoinHandle;
 use tokio::net::TcpStream;
+use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWriteExt;
-use tokio::net::tcp::OwnedReadHalf;
-use tokio::net::tcp::OwnedWriteHalf;
 
 use parking_lot::RwLock;
 use parking_lot::Mutex;
@@ -23,7 +22,11 @@ use crate::ygopro::message;
 use crate::ygopro::message::ctos;
 use crate::ygopro::message::srvpru;
 
+use crate::srvpru::BoxedPacketSink;
 use crate::srvpru::ListenError;
+use crate::srvpru::MessageFramer;
+use crate::srvpru::metrics;
+use crate::ygopro::message::Direction;
 use crate::srvpru::Handler;
 use crate::srvpru::HandlerCondition;
 use crate::srvpru::HandlerOccasion;
@@ -95,8 +98,8 @@ pub struct Player {
     pub origin_name: Option<String>,
     pub name: String,
     pub client_addr: SocketAddr,
-    pub client_stream_writer: Option<OwnedWriteHalf>,
-    pub server_stream_writer: Option<OwnedWriteHalf>,
+    pub client_stream_writer: Option<BoxedPacketSink>,
+    pub server_stream_writer: Option<BoxedPacketSink>,
     pub reader_handler: JoinHandle<()>,
 
     // These fields are not core.
@@ -113,7 +116,7 @@ impl Player {
         Ok(())
     }
 
-    pub async fn new(room: &Arc<Mutex<Room>>, client_addr: SocketAddr, client_stream_writer: OwnedWriteHalf) -> anyhow::Result<Arc<Mutex<Player>>> {
+    pub async fn new(room: &Arc<Mutex<Room>>, client_addr: SocketAddr, client_stream_writer: BoxedPacketSink) -> anyhow::Result<Arc<Mutex<Player>>> {
         let room = room.clone();
         let server_addr = room.lock().server_addr.clone().ok_or(anyhow!("Room don't have a server addr"))?;
         let stream = TcpStream::connect(server_addr).await?;
@@ -123,23 +126,25 @@ impl Player {
             server_stream_writer.write_all(&data).await?;
         }
         player.client_stream_writer = Some(client_stream_writer);
-        player.server_stream_writer = Some(server_stream_writer);
+        player.server_stream_writer = Some(Box::new(server_stream_writer));
         let player = Arc::new(Mutex::new(player));
         player.lock().reader_handler = Player::follow_socket(&player, client_addr, server_stream_reader); 
         PLAYERS.write().insert(client_addr, player.clone());
         Ok(player)
     }
 
-    fn follow_socket(this: &Arc<Mutex<Player>>, client_addr: SocketAddr, mut server_stream_reader: OwnedReadHalf) -> JoinHandle<()> {
+    fn follow_socket<R: AsyncRead + Send + Unpin + 'static>(this: &Arc<Mutex<Player>>, client_addr: SocketAddr, mut server_stream_reader: R) -> JoinHandle<()> {
         let this = this.clone();
         let configuration = crate::srvpru::get_configuration();
         let timeout = tokio::time::Duration::from_secs(configuration.timeout);
         tokio::spawn(async move {
             let mut buf = [0; 10240];
+            let mut framer = MessageFramer::new();
             loop {
                 let data = match tokio::time::timeout(timeout, server_stream_reader.read(&mut buf)).await {
                     Ok(data) => data,
                     Err(_) => {
+                        metrics::record_listen_error(Direction::STOC, &ListenError::Timeout);
                         if server::trigger_internal(client_addr, srvpru::StocListenError { error: ListenError::Timeout }).await.map_or(true, |block_message| !block_message) { 
                             break
                         } else { continue; }
@@ -149,21 +154,35 @@ impl Player {
                     Ok(n) if n == 0 => break,
                     Ok(n) => n,
                     Err(e) => {
-                        if server::trigger_internal(client_addr, srvpru::StocListenError { error: ListenError::Drop(anyhow::Error::new(e)) }).await.map_or(true, |block_message| !block_message) {
+                        let error = ListenError::Drop(anyhow::Error::new(e));
+                        metrics::record_listen_error(Direction::STOC, &error);
+                        if server::trigger_internal(client_addr, srvpru::StocListenError { error }).await.map_or(true, |block_message| !block_message) {
                             break
                         } else { continue; }
                     }
                 };
                 if n > 10240 { 
+                    metrics::record_listen_error(Direction::STOC, &ListenError::Oversize);
                     if server::trigger_internal(client_addr, srvpru::StocListenError { error: ListenError::Oversize }).await.map_or(true, |block_message| !block_message) {
                         break;
                     } else { continue; }
                 }
+                let frames = match framer.feed(&buf[0..n]) {
+                    Ok(Some(frames)) => frames,
+                    Ok(None) => continue,
+                    Err(error) => {
+                        metrics::record_processor_error(Direction::STOC, &error);
+                        if server::trigger_internal(client_addr, srvpru::STOCProcessError { error }).await.map_or(true, |block_message| !block_message) {
+                            break;
+                        } else { continue; }
+                    }
+                };
                 let mut socket = this.lock().client_stream_writer.take();
                 let addr = this.lock().client_addr;
-                let result = crate::srvpru::get_server().stoc_processor.process_multiple_messages(&mut socket, addr, &buf[0..n]).await;
+                let result = crate::srvpru::get_server().stoc_processor().process_multiple_messages(&mut socket, addr, &frames).await;
                 if let Some(socket) = socket { this.lock().client_stream_writer.replace(socket); }
-                if result.is_err() {
+                if let Err(error) = result {
+                    metrics::record_processor_error(Direction::STOC, &error);
                     this.lock().expel();
                 } 
             }
@@ -20,10 +20,16 @@
 //! * other tool functions for server
 // ====================================================================================================
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::io::Read;
 
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+
+use crate::srvpru::RoomSelector;
+
 // ----------------------------------------------------------------------------------------------------
 //  load_configuration
 // ----------------------------------------------------------------------------------------------------
@@ -65,6 +71,37 @@ pub fn load_configuration<T: serde::de::DeserializeOwned>(name: &str) -> anyhow:
     serde_json::from_str("{}").map_err(|e| anyhow!(format!("Cannot find configuration file for mod {}: {}", name, e)))
 }
 
+/// Fields any plugin configuration can have, besides its own.
+#[derive(serde::Deserialize, Debug, Default)]
+struct CommonConfiguration {
+    #[serde(default)]
+    rooms: Option<RoomSelector>
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  load_room_selector
+// ----------------------------------------------------------------------------------------------------
+/// Read `rooms` in configuration of a plugin, which limits its handlers to matched rooms.
+/// 
+/// ```yaml
+/// # tip.yaml
+/// rooms: "casual || mode == Single"
+/// ```
+/// See [RoomSelector] for syntax.
+/// 
+/// #### Return
+/// `None` if not set, handlers of this plugin run in all rooms.
+// ----------------------------------------------------------------------------------------------------
+pub fn load_room_selector(plugin_name: &str) -> Option<RoomSelector> {
+    match load_configuration::<CommonConfiguration>(plugin_name) {
+        Ok(configuration) => configuration.rooms,
+        Err(e) => {
+            debug!("Can't read rooms of plugin {}, it runs in all rooms: {}", plugin_name, e);
+            None
+        }
+    }
+}
+
 #[doc(hidden)]
 pub fn process_plugin_result(name: &str, result: anyhow::Result<()>) {
     match result {
@@ -189,6 +226,9 @@ macro_rules! set_reloadable_configuration {
 /// Register dependency for current plugin mod. \
 /// Need to call `register_dependencies()?` in `init()`.
 /// 
+/// Dependencies are also exported as `DEPENDENCIES`, so they are [init](init_plugins) before this plugin,
+/// and srvpru refuses to start if they are not enabled.
+/// 
 /// #### Example
 /// ```
 /// depend_on! [
@@ -205,6 +245,9 @@ macro_rules! set_reloadable_configuration {
 #[macro_export]
 macro_rules! depend_on {
     ($($field: literal),*) => {
+        /// Plugins this plugin depends on.
+        pub const DEPENDENCIES: &[&str] = &[$($field),*];
+
         #[doc(hidden)]
         fn register_dependency() -> anyhow::Result<()> {
             let os_module_name = std::path::Path::new(file!()).file_stem().ok_or(anyhow!("Can not determain module name."))?;
@@ -218,14 +261,18 @@ macro_rules! depend_on {
 // ----------------------------------------------------------------------------------------------------
 //  expand_plugins_undeer_dir!
 // ----------------------------------------------------------------------------------------------------
-/// Generate following two codes for each file under given directory:
+/// Generate following codes for each file under given directory:
 /// ```
 /// mod [handler_name];
 /// ```
 /// and
 /// ```
+/// pub fn plugins() -> Vec<Plugin> {
+///     // [handler_name], or plugins() of it if a directory of same name exists.
+/// }
+/// 
 /// pub fn init() -> anyhow::Result {
-///     [handler_name]::init();
+///     init_plugins(plugins())
 /// }
 /// ```
 // ----------------------------------------------------------------------------------------------------
@@ -233,21 +280,197 @@ macro_rules! depend_on {
 macro_rules! expand_plugins_under_dir {
     ($directory: literal) => {  
         execute_for_each_under_dir!($directory, pub mod #name);
-        /// Init all plugins under this mod.
+        /// All plugins under this mod, including ones in sub mods.
+        pub fn plugins() -> Vec<crate::srvpru::plugins::Plugin> {
+            let mut plugins = Vec::new();
+            execute_for_each_under_dir!($directory, crate::srvpru::plugins::Plugin::collect(&mut plugins, "#name", #dependencies, #name::init, #children));
+            plugins
+        }
+
+        /// Init all plugins under this mod, see [init_plugins](crate::srvpru::plugins::init_plugins).
         pub fn init() -> anyhow::Result<()> {
-            execute_for_each_under_dir!($directory, crate::srvpru::plugins::process_plugin_result("#name", #name::init()));
-            Ok(())
+            crate::srvpru::plugins::init_plugins(plugins())
         }
     };
 }
 
+// ============================================================
+//  Plugin
+// ------------------------------------------------------------
+/// A plugin mod, listed by [expand_plugins_under_dir!].
+// ============================================================
+#[derive(Debug, Clone, Copy)]
+pub struct Plugin {
+    pub name: &'static str,
+    /// Declared by [depend_on!].
+    pub dependencies: &'static [&'static str],
+    pub init: fn() -> anyhow::Result<()>
+}
+
+impl Plugin {
+    #[doc(hidden)]
+    pub fn collect(plugins: &mut Vec<Plugin>, name: &'static str, dependencies: &'static [&'static str], init: fn() -> anyhow::Result<()>, children: Option<fn() -> Vec<Plugin>>) {
+        match children {
+            // Mods like `base` only hold other plugins.
+            Some(children) => plugins.extend(children()),
+            None => plugins.push(Plugin { name, dependencies, init })
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  init_plugins
+// ----------------------------------------------------------------------------------------------------
+/// Init `plugins`, each one after its dependencies. 
+/// 
+/// Before that, dependencies of enabled plugins are checked, and enabled if 
+/// `auto_enable_dependencies` is set in [srvpru configuration](crate::srvpru::Configuration).
+/// 
+/// #### If error happens
+/// * Plugins depend on each other: [PluginError::Cycle].
+/// * Dependency of an enabled plugin is not enabled: [PluginError::MissingDependency], 
+///   or only a warning if `allow_missing_dependencies` is set.
+// ----------------------------------------------------------------------------------------------------
+pub fn init_plugins(plugins: Vec<Plugin>) -> anyhow::Result<()> {
+    let configuration = crate::srvpru::get_configuration();
+    let order = load_order(&plugins)?;
+    let configured = enabled_plugins();
+    let (enabled, missing) = resolve_dependencies(&plugins, configured.clone(), &configured_handlers(), configuration.auto_enable_dependencies);
+    check_missing_dependencies(missing, configuration.allow_missing_dependencies)?;
+    for plugin in enabled.iter().filter(|plugin| !configured.contains(plugin)) {
+        info!("Plugin {} is enabled as a dependency.", plugin);
+    }
+    *enabled_plugins_lock().write() = enabled;
+    for plugin in order {
+        process_plugin_result(plugin.name, (plugin.init)());
+    }
+    Ok(())
+}
+
+/// Sort `plugins` so that dependencies come first, otherwise keep their order.
+fn load_order(plugins: &[Plugin]) -> Result<Vec<Plugin>, PluginError> {
+    let mut remaining = plugins.to_vec();
+    let mut order = Vec::new();
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|plugin| plugin.dependencies.iter().all(|dependency| !remaining.iter().any(|other| other.name == *dependency)));
+        match ready {
+            Some(index) => order.push(remaining.remove(index)),
+            None => {
+                // Drop plugins only depending on a cycle, leaving the cycle.
+                while let Some(index) = remaining.iter().position(|plugin| !remaining.iter().any(|other| other.dependencies.contains(&plugin.name))) {
+                    remaining.remove(index);
+                }
+                return Err(PluginError::Cycle(remaining.iter().map(|plugin| plugin.name.to_string()).collect()));
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// Add dependencies of `enabled` plugins to it, if `auto_enable`.
+/// 
+/// A dependency listed in `handlers` is also satisfied, see [configured_handlers].
+/// 
+/// #### Return
+/// Enabled plugins, and `(plugin, dependency)` of dependencies still not enabled.
+pub(crate) fn resolve_dependencies(plugins: &[Plugin], mut enabled: Vec<String>, handlers: &[String], auto_enable: bool) -> (Vec<String>, Vec<(String, String)>) {
+    let dependencies: HashMap<&str, &[&str]> = plugins.iter().map(|plugin| (plugin.name, plugin.dependencies)).collect();
+    let mut missing = Vec::new();
+    let mut index = 0;
+    // Enabled dependencies are checked in turn, as they are pushed.
+    while index < enabled.len() {
+        let plugin = enabled[index].clone();
+        for dependency in dependencies.get(plugin.as_str()).copied().unwrap_or_default() {
+            if REQUIRED_PLUGINS.contains(dependency) || enabled.iter().chain(handlers).any(|enabled_plugin| enabled_plugin == dependency) { continue; }
+            if auto_enable && dependencies.contains_key(dependency) { enabled.push(dependency.to_string()); }
+            else { missing.push((plugin.clone(), dependency.to_string())); }
+        }
+        index += 1;
+    }
+    (enabled, missing)
+}
+
+/// Handlers in `ctos_handlers`, `stoc_handlers` and `internal_handlers` of [srvpru configuration](crate::srvpru::Configuration),
+/// which satisfy dependencies of same names, as they always did.
+fn configured_handlers() -> Vec<String> {
+    let configuration = crate::srvpru::get_configuration();
+    configuration.ctos_handlers.iter().chain(&configuration.stoc_handlers).chain(&configuration.internal_handlers).cloned().collect()
+}
+
+fn check_missing_dependencies(missing: Vec<(String, String)>, allowed: bool) -> Result<(), PluginError> {
+    for (plugin, dependency) in missing {
+        let error = PluginError::MissingDependency { plugin, dependency };
+        if !allowed { return Err(error); }
+        warn!("{}", error);
+    }
+    Ok(())
+}
+
 // ----------------------------------------------------------------------------------------------------
 //  plugin_enabled
 // ----------------------------------------------------------------------------------------------------
-/// Check if a plugin is listed in [srvpru configuration](crate::srvpru::Configuration#plguins).
+/// Check if a plugin is enabled, which is listed in [srvpru configuration](crate::srvpru::Configuration#plguins)
+/// when start, and can be changed by [Server::set_plugins](crate::srvpru::Server::set_plugins).
 // ----------------------------------------------------------------------------------------------------
 pub fn plugin_enabled(plugin_name: &str) -> bool {
-    crate::srvpru::get_configuration().plugins.contains(&plugin_name.to_string())
+    enabled_plugins_lock().read().iter().any(|plugin| plugin == plugin_name)
+}
+
+/// Plugins always enabled, which others are built on.
+pub const REQUIRED_PLUGINS: [&str; 2] = ["player", "room"];
+
+static ENABLED_PLUGINS: OnceCell<RwLock<Vec<String>>> = OnceCell::new();
+
+fn enabled_plugins_lock() -> &'static RwLock<Vec<String>> {
+    ENABLED_PLUGINS.get_or_init(|| RwLock::new(crate::srvpru::get_configuration().plugins.clone()))
+}
+
+/// Currently enabled plugins, in order of configuration.
+pub fn enabled_plugins() -> Vec<String> {
+    enabled_plugins_lock().read().clone()
+}
+
+/// Plugins which have handlers, so they can be enabled at runtime.
+pub fn available_plugins() -> Vec<String> {
+    let mut plugins: Vec<String> = crate::srvpru::HANDLER_LIBRARY_BY_PLUGIN.read().keys().cloned().collect();
+    plugins.sort();
+    plugins
+}
+
+/// Errors happen on changing enabled plugins.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PluginError {
+    #[error("No plugin named {0} has handlers.")]
+    Unknown(String),
+    #[error("Plugin {0} can't be disabled.")]
+    Required(String),
+    /// Plugins without handlers only work as they are when srvpru starts,
+    /// like serving api.
+    #[error("Plugin {0} has no handlers, it can't be disabled at runtime.")]
+    Static(String),
+    #[error("Plugin {plugin} depends on {dependency}, which is not enabled.")]
+    MissingDependency { plugin: String, dependency: String },
+    #[error("Plugins depend on each other: {}.", .0.join(", "))]
+    Cycle(Vec<String>)
+}
+
+#[doc(hidden)]
+pub(crate) fn set_enabled_plugins(plugins: Vec<String>) -> Result<(), PluginError> {
+    if let Some(plugin) = REQUIRED_PLUGINS.iter().find(|required| !plugins.iter().any(|plugin| plugin == *required)) {
+        return Err(PluginError::Required(plugin.to_string()));
+    }
+    let available = available_plugins();
+    if let Some(plugin) = plugins.iter().find(|plugin| !available.contains(plugin) && !plugin_enabled(plugin)) {
+        return Err(PluginError::Unknown(plugin.clone()));
+    }
+    if let Some(plugin) = enabled_plugins().into_iter().find(|plugin| !available.contains(plugin) && !plugins.contains(plugin)) {
+        return Err(PluginError::Static(plugin));
+    }
+    let configuration = crate::srvpru::get_configuration();
+    let (plugins, missing) = resolve_dependencies(&self::plugins(), plugins, &configured_handlers(), configuration.auto_enable_dependencies);
+    check_missing_dependencies(missing, configuration.allow_missing_dependencies)?;
+    *enabled_plugins_lock().write() = plugins;
+    Ok(())
 }
 
 // ----------------------------------------------------------------------------------------------------
@@ -269,4 +492,73 @@ macro_rules! use_http_client {
     }
 }
 
-expand_plugins_under_dir!("src/srvpru/plugins");
\ No newline at end of file
+expand_plugins_under_dir!("src/srvpru/plugins");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn plugin(name: &'static str, dependencies: &'static [&'static str]) -> Plugin {
+        Plugin { name, dependencies, init: || Ok(()) }
+    }
+
+    fn names(plugins: &[Plugin]) -> Vec<&'static str> {
+        plugins.iter().map(|plugin| plugin.name).collect()
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn load_dependencies_first() {
+        let plugins = [
+            plugin("api", &[]),
+            plugin("lp_recorder", &["position_recorder"]),
+            plugin("tournament", &["api", "lp_recorder"]),
+            plugin("position_recorder", &[]),
+            plugin("welcome", &[])
+        ];
+        let order = load_order(&plugins).unwrap();
+        assert_eq!(names(&order), ["api", "position_recorder", "lp_recorder", "tournament", "welcome"]);
+    }
+
+    #[test]
+    fn report_only_plugins_in_cycle() {
+        let plugins = [
+            plugin("a", &["b"]),
+            plugin("b", &["c"]),
+            plugin("c", &["a"]),
+            plugin("d", &["a"]),
+            plugin("e", &[])
+        ];
+        assert_eq!(load_order(&plugins).unwrap_err(), PluginError::Cycle(strings(&["a", "b", "c"])));
+    }
+
+    #[test]
+    fn resolve_missing_dependencies() {
+        let plugins = [
+            plugin("player", &[]),
+            plugin("position_recorder", &["player"]),
+            plugin("lp_recorder", &["position_recorder"]),
+            plugin("windbot", &["chat_command"]),
+        ];
+        let enabled = strings(&["player", "room", "lp_recorder", "windbot"]);
+
+        let (resolved, missing) = resolve_dependencies(&plugins, enabled.clone(), &[], false);
+        assert_eq!(resolved, enabled);
+        assert_eq!(missing, [("lp_recorder".to_string(), "position_recorder".to_string()), ("windbot".to_string(), "chat_command".to_string())]);
+
+        // Handlers listed in configuration satisfy dependencies too.
+        let (_, missing) = resolve_dependencies(&plugins, enabled.clone(), &strings(&["chat_command"]), false);
+        assert_eq!(missing, [("lp_recorder".to_string(), "position_recorder".to_string())]);
+
+        // Unknown plugins can't be enabled.
+        let (resolved, missing) = resolve_dependencies(&plugins, enabled, &[], true);
+        assert_eq!(resolved, strings(&["player", "room", "lp_recorder", "windbot", "position_recorder"]));
+        assert_eq!(missing, [("windbot".to_string(), "chat_command".to_string())]);
+
+        assert!(check_missing_dependencies(missing.clone(), true).is_ok());
+        assert_eq!(check_missing_dependencies(missing, false), Err(PluginError::MissingDependency { plugin: "windbot".to_string(), dependency: "chat_command".to_string() }));
+    }
+}
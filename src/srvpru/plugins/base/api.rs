@@ -1,9 +1,16 @@
+use std::net::SocketAddr;
+
 use axum::Router;
+use hyper::server::conn::AddrStream;
+use tokio::net::TcpListener;
 use parking_lot::Mutex;
 use once_cell::sync::OnceCell;
 
 use crate::srvpru::Handler;
 use crate::srvpru::message::ServerStart;
+use crate::srvpru::tls::TlsConfiguration;
+use crate::srvpru::tls::TlsConnection;
+use crate::srvpru::tls::accept_connections;
 
 set_configuration! {
     #[serde(default = "default_port")]
@@ -42,10 +49,32 @@ fn start_server() {
         }
     };
     let app = router_wrapper.lock().take().expect("Router has been taken. It seems server already start.");
+    if let Some(tls) = crate::srvpru::get_configuration().tls.as_ref() {
+        if let Some(port) = tls.api_port { start_tls_server(app.clone(), tls, port); }
+    }
     tokio::spawn(async move {
         axum::Server::bind(&format!("0.0.0.0:{}", configuration.port).parse().unwrap())
-        .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr, _>())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr, &AddrStream>())
         .await
         .unwrap();
     });
-}
\ No newline at end of file
+}
+
+fn start_tls_server(app: Router, tls: &TlsConfiguration, port: u16) {
+    let acceptor = match crate::srvpru::tls::load_acceptor(tls) {
+        Ok(acceptor) => acceptor,
+        Err(e) => {
+            error!("Failed to load tls certificate, tls api on port {} won't start: {}", port, e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await.unwrap();
+        // Same handshake timeout as game port.
+        let handshake_timeout = tokio::time::Duration::from_secs(crate::srvpru::get_configuration().timeout);
+        axum::Server::builder(accept_connections(listener, acceptor, handshake_timeout))
+        .serve(app.into_make_service_with_connect_info::<SocketAddr, &TlsConnection>())
+        .await
+        .unwrap();
+    });
+}
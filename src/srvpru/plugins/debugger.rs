@@ -2,9 +2,13 @@
 // debugger
 // ------------------------------------------------------------
 //! Offer a debugger handler to print what message is sent.
+//! 
+//! Answers of duelists are printed as [ResponseDecision](crate::ygopro::message::ctos::ResponseDecision)
+//! if [response_recorder](super::recorder::response_recorder) is enabled too.
 // ============================================================
 
 use crate::ygopro::message;
+use crate::ygopro::message::ctos;
 use crate::ygopro::message::srvpru;
 use crate::srvpru::Handler;
 use crate::srvpru::Context;
@@ -19,6 +23,12 @@ pub fn init() -> anyhow::Result<()> {
 pub fn register_handlers() {
     Handler::new(0, "ctos_debugger", HandlerOccasion::Before, HandlerCondition::Always, |context| Box::pin(async move {
         debug!("CTOS  Message {:} -> {:} {:}", player_name(context), room_name(context), message_type_name(context.message_type));
+        if context.message_type == Some(message::MessageType::CTOS(ctos::MessageType::Response)) {
+            context.deserialize_message();
+            if let Some(response) = context.cast_message_to_type::<ctos::Response>() {
+                debug!("CTOS  Response - {:?}", context.interpret_response(response));
+            }
+        }
         Ok(false)
     })).register();
 
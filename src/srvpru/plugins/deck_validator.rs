@@ -0,0 +1,79 @@
+// ============================================================
+// deck_validator
+// ------------------------------------------------------------
+//! Check decks by srvpru itself before they reach ygopro.
+//!
+//! A deck failing [Deck::validate](crate::ygopro::data::Deck::validate)
+//! is blocked, and player can't get ready until a legal deck is sent.
+//! Rooms created with `no_check_deck` are not checked, unless
+//! `ignore_no_check_deck` is set, so a modified client can't bring
+//! illegal decks into tournament rooms. Nothing is checked if no card
+//! is loaded, as every deck would be refused.
+// ============================================================
+
+use crate::srvpru::CommonError;
+use crate::srvpru::Handler;
+use crate::srvpru::generate_chat;
+use crate::ygopro::Colors;
+use crate::ygopro::data::CARDS;
+use crate::ygopro::data::DeckError;
+use crate::ygopro::message::Direction;
+use crate::ygopro::message::ctos;
+use crate::ygopro::message::stoc;
+
+set_configuration! {
+    /// Still check deck if room is created with `no_check_deck`.
+    #[serde(default)]
+    ignore_no_check_deck: bool
+}
+
+player_attach! {
+    deck_error: Option<DeckError>
+}
+
+pub fn init() -> anyhow::Result<()> {
+    load_configuration()?;
+    register_handlers();
+    Ok(())
+}
+
+fn register_handlers() {
+    Handler::before_message::<ctos::UpdateDeck, _>(50, "deck_validator", |context, message| Box::pin(async move {
+        let host_info = context.get_room().ok_or(CommonError::RoomNotExist)?.lock().host_info.clone();
+        if host_info.no_check_deck && !get_configuration().ignore_no_check_deck { return Ok(false); }
+        if CARDS.read().is_empty() {
+            warn!("No card loaded, deck of {} is not checked.", context.addr);
+            return Ok(false);
+        }
+        let result = message.deck.validate(&host_info);
+        get_player_attachment_sure(context).deck_error = result.err();
+        match result {
+            Ok(_) => Ok(false),
+            Err(error) => {
+                context.send_back(&generate_chat(&format!("{{deck_invalid}} {}", error), Colors::Red, context.get_region())).await.ok();
+                refuse_deck(context, error).await;
+                context.block_message()
+            }
+        }
+    })).register();
+
+    Handler::before_message::<ctos::HsReady, _>(50, "deck_validator_ready_blocker", |context, _| Box::pin(async move {
+        let deck_error = get_player_attachment(context).and_then(|attachment| attachment.deck_error);
+        match deck_error {
+            Some(error) => {
+                refuse_deck(context, error).await;
+                context.block_message()
+            }
+            None => Ok(false)
+        }
+    })).register();
+
+    register_player_attachment_dropper();
+    register_player_attachment_mover();
+    Handler::register_handlers("deck_validator", Direction::CTOS, vec!["deck_validator", "deck_validator_ready_blocker"]);
+}
+
+async fn refuse_deck(context: &mut crate::srvpru::Context<'_>, error: DeckError) {
+    let message = stoc::ErrorMessage { msg: crate::ygopro::ErrorMessage::Deckerror, align: [0; 3], code: error.error_code() };
+    context.send_back(&message).await.ok();
+}
@@ -3,6 +3,9 @@
 // ------------------------------------------------------------
 //! Stop replay event after each game.  
 //! Send all the replays when a duel is end instead.
+//!
+//! Data of each replay is a standalone `.yrp` file. They can be
+//! saved under `save_directory`, or read by `get_replays` as [Replay].
 // ============================================================
 
 use std::io::Cursor;
@@ -13,14 +16,23 @@ use crate::ygopro::message;
 use crate::ygopro::message::stoc;
 use crate::ygopro::Colors;
 use crate::ygopro::data::Replay;
+use crate::ygopro::data::ReplayHeader;
 
 use crate::srvpru::processor::Handler;
 use crate::srvpru::generate_chat;
 
+set_configuration! {
+    /// Save each replay of matches as `{start_time}_{seed}.yrp` here, if set.
+    #[serde(default)]
+    save_directory: Option<String>
+}
+
 player_attach! {
     replays: Vec<Vec<u8>>
 }
 
+export_player_attach_as!(get_replays, Vec<Replay>, transformer);
+
 fn register_handlers() {
     srvpru_handler!(stoc::MessageType::Replay, get_player_attachment_sure, |context| {
         let room = context.get_room().ok_or(anyhow!("Cannot get toom"))?;
@@ -28,11 +40,11 @@ fn register_handlers() {
             return Ok(false);
         }
         attachment.replays.push(context.message_buffer.to_vec());
-        let vec = context.message_buffer.to_vec();
-        let mut cursor = Cursor::new(vec);
-        cursor.set_position(3);
-        if let Err(e) = Replay::from_reader(&mut cursor) {
-            error!("{:?}", e)
+        if let Some(directory) = get_configuration().save_directory.as_ref() {
+            // Skip length and type, what left is a whole yrp file.
+            if let Err(e) = save_replay(directory, &context.message_buffer[3..]).await {
+                warn!("Cannot save replay from {}: {:?}", context.addr, e);
+            }
         }
         context.block_message()
     }).register_as("replay_interceptor");
@@ -49,6 +61,26 @@ fn register_handlers() {
 }
 
 pub fn init() -> anyhow::Result<()> {
+    load_configuration()?;
     register_handlers();
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Write a `.yrp` file, named by its header. Both duelists get the same replay, so it's only written once.
+async fn save_replay(directory: &str, data: &[u8]) -> anyhow::Result<()> {
+    let header = ReplayHeader::from_reader(&mut Cursor::new(data))?;
+    let path = std::path::Path::new(directory).join(format!("{}_{}.yrp", header.start_time, header.seed));
+    if tokio::fs::metadata(&path).await.is_ok() { return Ok(()); }
+    tokio::fs::create_dir_all(directory).await?;
+    tokio::fs::write(path, data).await?;
+    Ok(())
+}
+
+/// Replays are only parsed when asked for.
+fn transformer<'b>(attachment: Option<parking_lot::MappedRwLockWriteGuard<'b, PlayerAttachment>>) -> Vec<Replay> {
+    let packets = attachment.map(|attachment| attachment.replays.clone()).unwrap_or_default();
+    packets.iter().filter_map(|packet| match Replay::from_bytes(&packet[3..]) {
+        Ok(replay) => Some(replay),
+        Err(e) => { warn!("Cannot parse replay: {:?}", e); None }
+    }).collect()
+}
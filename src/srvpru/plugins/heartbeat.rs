@@ -101,7 +101,7 @@ fn register_handlers() {
 
 fn register_exempts() {
     Handler::before_message::<gm::ConfirmCards, _>(100, "heartbeat_exempt_confirm", |context, message| Box::pin(async move {
-        if message.cards.iter().any(|card| matches!(card.location, Location::Limbo | Location::Deck | Location::Extra)) {
+        if message.cards.iter().any(|card| matches!(Location::try_from(card.location), Ok(Location::Limbo | Location::Deck | Location::Extra))) {
             get_player_attachment_sure(context).exempt = true;
         }
         Ok(false)
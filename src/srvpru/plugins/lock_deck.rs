@@ -1,19 +1,145 @@
-use crate::srvpru::RoomSelector;
-
-set_configuration! {
-    get_deck: String,
-    selector: RoomSelector
-}
-
-enum LockLevel {
-    OfferDeck,
-    SkipWait
-}
-
-pub fn init() -> anyhow::Result<()> {
-    Ok(())
-}
-
-fn register_handlers() {
-
-}
\ No newline at end of file
+// ============================================================
+// lock_deck
+// ------------------------------------------------------------
+//! Force players to use their registered deck, in rooms
+//! matched by `rooms`.
+//!
+//! Registered deck is fetched from `get_deck` endpoint, or
+//! `{deck_directory}/{player name}.ydk`.
+//! - [Check](LockLevel::Check): every `UpdateDeck` must match it.
+//! - [OfferDeck](LockLevel::OfferDeck): first `UpdateDeck` is replaced by it.
+//!
+//! Runs before `deck_validator`, so the deck it checks is the
+//! offered one.
+//!
+//! After first duel, side decking is allowed as long as all
+//! cards are the same.
+// ============================================================
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::srvpru::CommonError;
+use crate::srvpru::Context;
+use crate::srvpru::Handler;
+use crate::srvpru::generate_chat;
+use crate::ygopro::Colors;
+use crate::ygopro::data::Deck;
+use crate::ygopro::message::Direction;
+use crate::ygopro::message::ctos;
+use crate::ygopro::message::stoc;
+
+set_configuration! {
+    /// Http endpoint returning ydk of a player, called with query `username`.
+    #[serde(default)]
+    get_deck: String,
+    /// Directory of `{player name}.ydk`, used if `get_deck` is empty.
+    #[serde(default)]
+    deck_directory: String,
+    #[serde(default)]
+    lock_level: LockLevel
+}
+
+player_attach! {
+    registered_deck: Option<Deck>,
+    // A deck is accepted once, following ones are side decking.
+    accepted: bool,
+    refused: bool
+}
+
+/// How registered deck is enforced.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LockLevel {
+    /// Refuse any deck different from the registered one.
+    #[default]
+    Check,
+    /// Player's deck is ignored, registered deck is used instead.
+    OfferDeck
+}
+
+use_http_client!();
+
+pub fn init() -> anyhow::Result<()> {
+    load_configuration()?;
+    register_handlers();
+    Ok(())
+}
+
+fn register_handlers() {
+    Handler::before_message::<ctos::UpdateDeck, _>(40, "lock_deck", |context, message| Box::pin(async move {
+        let name = context.get_player().ok_or(CommonError::PlayerNotExist)?.lock().name.clone();
+        let registered_deck = get_player_attachment(context).and_then(|attachment| attachment.registered_deck.clone());
+        let registered_deck = match registered_deck {
+            Some(deck) => deck,
+            None => match fetch_deck(&name).await {
+                Ok(Some(deck)) => {
+                    get_player_attachment_sure(context).registered_deck = Some(deck.clone());
+                    deck
+                },
+                Ok(None) => return refuse(context, &format!("{}{{deck_not_found}}", name)).await,
+                Err(e) => {
+                    warn!("Failed to fetch registered deck of {}: {:?}", name, e);
+                    return refuse(context, &format!("{}{{deck_not_found}}", name)).await;
+                }
+            }
+        };
+
+        let accepted = get_player_attachment_sure(context).accepted;
+        if !accepted && get_configuration().lock_level == LockLevel::OfferDeck {
+            message.deck = registered_deck;
+            context.reserialize = true;
+        }
+        else if !registered_deck.same_cards(&message.deck, accepted) {
+            return refuse(context, &format!("{{deck_incorrect_part1}} {} {{deck_incorrect_part2}}", name)).await;
+        }
+        let mut attachment = get_player_attachment_sure(context);
+        attachment.accepted = true;
+        attachment.refused = false;
+        Ok(false)
+    })).register();
+
+    Handler::before_message::<ctos::HsReady, _>(60, "lock_deck_ready_blocker", |context, _| Box::pin(async move {
+        if get_player_attachment(context).is_some_and(|attachment| attachment.refused) {
+            return context.block_message();
+        }
+        Ok(false)
+    })).register();
+
+    register_player_attachment_dropper();
+    register_player_attachment_mover();
+    Handler::register_handlers("lock_deck", Direction::CTOS, vec!["lock_deck", "lock_deck_ready_blocker"]);
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  fetch_deck
+// ----------------------------------------------------------------------------------------------------
+/// Get registered deck of a player.
+///
+/// #### Return
+/// `None` if the player has no registration.
+// ----------------------------------------------------------------------------------------------------
+async fn fetch_deck(name: &str) -> anyhow::Result<Option<Deck>> {
+    let configuration = get_configuration();
+    if !configuration.get_deck.is_empty() {
+        let response = get_http_client().get(&configuration.get_deck).query(&[("username", name)]).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND { return Ok(None); }
+        let ydk = response.error_for_status()?.text().await?;
+        return Ok(Some(Deck::from_ydk(&ydk)));
+    }
+    // Player name may contain anything, never let it escape the directory.
+    if configuration.deck_directory.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') { return Ok(None); }
+    let path = std::path::Path::new(&configuration.deck_directory).join(format!("{}.ydk", name));
+    match tokio::fs::read_to_string(path).await {
+        Ok(ydk) => Ok(Some(Deck::from_ydk(&ydk))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into())
+    }
+}
+
+async fn refuse(context: &mut Context<'_>, reason: &str) -> anyhow::Result<bool> {
+    get_player_attachment_sure(context).refused = true;
+    context.send_back(&generate_chat(reason, Colors::Red, context.get_region())).await.ok();
+    context.send_back(&stoc::ErrorMessage { msg: crate::ygopro::ErrorMessage::Deckerror, align: [0; 3], code: 0 }).await.ok();
+    context.block_message()
+}
@@ -15,6 +15,10 @@ use crate::ygopro::message::Direction;
 use crate::ygopro::message::stoc;
 use crate::ygopro::message::gm;
 
+depend_on! {
+    "position_recorder"
+}
+
 player_attach! {
     lp: i32
 }
@@ -22,6 +26,7 @@ player_attach! {
 export_player_attach_as!(get_lp, i32, transformer);
 
 pub fn init() -> anyhow::Result<()> {
+    register_dependency()?;
     register_handlers();
     Ok(())    
 }
@@ -0,0 +1,79 @@
+// ============================================================
+// metrics
+// ------------------------------------------------------------
+//! Export prometheus metrics on `GET /metrics` of api.
+//!
+//! Besides metrics collected by [core](crate::srvpru::metrics),
+//! current rooms (by mode and duel stage), players and player
+//! precursors are counted when scraping.
+//!
+//! Duel stage is always `Void` without `stage_recorder`.
+//!
+//! Dependency:
+//! - [api](super::base::api)
+// ============================================================
+
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use axum::http::header;
+use axum::response::Headers;
+use axum::routing;
+use prometheus::Encoder;
+use prometheus::IntGauge;
+use prometheus::IntGaugeVec;
+use prometheus::Opts;
+use prometheus::TextEncoder;
+
+use crate::srvpru::ROOMS;
+use crate::srvpru::metrics;
+use crate::srvpru::metrics::REGISTRY;
+use crate::srvpru::player::PLAYERS;
+use crate::srvpru::player::PLAYER_PRECURSORS;
+use crate::srvpru::plugins::base::api::register_api;
+use crate::srvpru::plugins::plugin_enabled;
+
+depend_on! {
+    "api"
+}
+
+lazy_static! {
+    static ref ROOMS_GAUGE: IntGaugeVec = metrics::register(IntGaugeVec::new(Opts::new("rooms", "Active rooms."), &["mode", "stage"]).unwrap());
+    static ref PLAYERS_GAUGE: IntGauge = metrics::register(IntGauge::new("players", "Players joined a room.").unwrap());
+    static ref PRECURSORS_GAUGE: IntGauge = metrics::register(IntGauge::new("player_precursors", "Connections not joined a room yet.").unwrap());
+}
+
+pub fn init() -> anyhow::Result<()> {
+    register_dependency()?;
+    if !plugin_enabled("metrics") { return Ok(()); }
+    metrics::enable();
+    register_api(|router| router.route("/metrics", routing::get(get_metrics)));
+    Ok(())
+}
+
+fn collect_gauges() {
+    let mut rooms: HashMap<(String, String), i64> = HashMap::new();
+    for room in ROOMS.read().values() {
+        let room = room.lock();
+        let key = (format!("{:?}", room.host_info.mode), format!("{:?}", room.get_duel_stage()));
+        *rooms.entry(key).or_default() += 1;
+    }
+    // Rooms gone since last scrape shouldn't be kept.
+    ROOMS_GAUGE.reset();
+    for ((mode, stage), count) in rooms {
+        ROOMS_GAUGE.with_label_values(&[&mode, &stage]).set(count);
+    }
+    PLAYERS_GAUGE.set(PLAYERS.read().len() as i64);
+    PRECURSORS_GAUGE.set(PLAYER_PRECURSORS.read().len() as i64);
+}
+
+async fn get_metrics() -> Result<(Headers<Vec<(header::HeaderName, String)>>, Vec<u8>), StatusCode> {
+    collect_gauges();
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    encoder.encode(&REGISTRY.gather(), &mut buffer).map_err(|e| {
+        error!("Failed to encode metrics: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((Headers(vec![(header::CONTENT_TYPE, encoder.format_type().to_string())]), buffer))
+}
@@ -2,8 +2,410 @@
 // miniluv (Ministry of Love)
 // ------------------------------------------------------------
 //! Banish or Silent user.
+//!
+//! Bans and mutes are persisted in sqlite `database`, each one
+//! aims at a player name, an ip, or both (either matches).
+//! - Banned players are refused at `PlayerInfo` and `JoinGame`.
+//! - Muted players can't chat, but still can use chat commands.
+//!
+//! Admins, whose origin name (`name$password` by virtual_password)
+//! is listed in `admins`, can use chat commands:
+//! - `/ban <name> [minutes] [reason]`
+//! - `/mute <name> [minutes] [reason]`
+//! - `/kick <name>`
+//!
+//! Same operations are exposed by api, with `?key={access_key}`:
+//! - `GET /miniluv/penalties`
+//! - `POST /miniluv/penalties`, body as [PenaltyRequest].
+//! - `DELETE /miniluv/penalties/:id`
+//! - `POST /miniluv/kick`, body as [KickRequest].
+//!
+//! Dependency:
+//! - [api](super::base::api)
+//! - [chat_command](super::base::chat_command)
 // ============================================================
 
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use axum::Json;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::routing;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::Row;
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteRow;
+use tokio::sync::OnceCell;
+
+use crate::srvpru::CommonError;
+use crate::srvpru::Context;
+use crate::srvpru::Handler;
+use crate::srvpru::generate_chat;
+use crate::srvpru::message::Shutdown;
+use crate::srvpru::player::PLAYERS;
+use crate::srvpru::player::PLAYER_PRECURSORS;
+use crate::srvpru::plugins::base::api::register_api;
+use crate::srvpru::plugins::base::chat_command;
+use crate::srvpru::plugins::plugin_enabled;
+use crate::ygopro::Colors;
+use crate::ygopro::message::ctos;
+
+set_configuration! {
+    #[serde(default = "default_database")]
+    database: String,
+    /// Origin names allowed to use chat commands.
+    #[serde(default)]
+    admins: Vec<String>,
+    /// Api is refused if it's empty.
+    #[serde(default)]
+    access_key: String
+}
+
+fn default_database() -> String { "./miniluv.db".to_string() }
+
+depend_on! {
+    "api",
+    "chat_command"
+}
+
+/// What a penalty forbids.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PenaltyKind {
+    /// Can't join any room.
+    Ban,
+    /// Can't chat.
+    Mute
+}
+
+impl PenaltyKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PenaltyKind::Ban => "ban",
+            PenaltyKind::Mute => "mute"
+        }
+    }
+
+    fn from_str(kind: &str) -> Option<PenaltyKind> {
+        match kind {
+            "ban" => Some(PenaltyKind::Ban),
+            "mute" => Some(PenaltyKind::Mute),
+            _ => None
+        }
+    }
+}
+
+/// A stored ban or mute.
+#[derive(Serialize, Debug, Clone)]
+pub struct Penalty {
+    pub id: i64,
+    pub kind: PenaltyKind,
+    pub name: Option<String>,
+    pub ip: Option<String>,
+    pub reason: String,
+    pub created_at: i64,
+    /// Unix timestamp, `None` for permanent.
+    pub expire_at: Option<i64>
+}
+
+impl Penalty {
+    fn from_row(row: &SqliteRow) -> anyhow::Result<Penalty> {
+        let kind: String = row.try_get("kind")?;
+        Ok(Penalty {
+            id: row.try_get("id")?,
+            kind: PenaltyKind::from_str(&kind).ok_or_else(|| anyhow!("Unknown penalty kind {}", kind))?,
+            name: row.try_get("name")?,
+            ip: row.try_get("ip")?,
+            reason: row.try_get("reason")?,
+            created_at: row.try_get("created_at")?,
+            expire_at: row.try_get("expire_at")?
+        })
+    }
+
+    /// If this penalty aims at given player.
+    pub fn matches(&self, name: &str, ip: &IpAddr) -> bool {
+        self.name.as_deref() == Some(name) || self.ip.as_deref() == Some(ip.to_string().as_str())
+    }
+}
+
+/// Body of `POST /miniluv/penalties`.
+#[derive(Deserialize, Debug)]
+pub struct PenaltyRequest {
+    pub kind: PenaltyKind,
+    pub name: Option<String>,
+    pub ip: Option<String>,
+    /// Seconds, permanent if absent.
+    pub duration: Option<i64>,
+    #[serde(default)]
+    pub reason: String
+}
+
+/// Body of `POST /miniluv/kick`.
+#[derive(Deserialize, Debug)]
+pub struct KickRequest {
+    pub name: Option<String>,
+    pub ip: Option<String>
+}
+
 pub fn init() -> anyhow::Result<()> {
-    Ok(()) 
-}
\ No newline at end of file
+    load_configuration()?;
+    register_dependency()?;
+    register_handlers();
+    register_apis();
+    Ok(())
+}
+
+fn register_handlers() {
+    // After virtual_password, so real name is used.
+    Handler::before_message::<ctos::PlayerInfo, _>(12, "miniluv_ban_checker", |context, message| Box::pin(async move {
+        let name = context.get_string(&message.name, "name")?.clone();
+        check_ban(context, &name).await
+    })).register_for_plugin("miniluv");
+
+    // Penalty may be added after PlayerInfo.
+    Handler::before_message::<ctos::JoinGame, _>(5, "miniluv_join_checker", |context, _| Box::pin(async move {
+        let name = match PLAYER_PRECURSORS.read().get(&context.addr) {
+            Some(precursor) => precursor.name.clone(),
+            None => return Ok(false)
+        };
+        check_ban(context, &name).await
+    })).register_for_plugin("miniluv");
+
+    // Before chat_command, but let commands pass.
+    Handler::before_message::<ctos::Chat, _>(10, "miniluv_mute_checker", |context, message| Box::pin(async move {
+        if context.get_string(&message.msg, "message")?.starts_with('/') { return Ok(false); }
+        let name = context.get_player().ok_or(CommonError::PlayerNotExist)?.lock().name.clone();
+        match find_penalty(PenaltyKind::Mute, &name, &context.addr.ip()).await {
+            Ok(Some(_)) => {
+                context.send_back(&generate_chat("{banned_chat_tip}", Colors::Red, context.get_region())).await.ok();
+                context.block_message()
+            },
+            Ok(None) => Ok(false),
+            Err(e) => { error!("Failed to check mute of {}: {:?}", name, e); Ok(false) }
+        }
+    })).register_for_plugin("miniluv");
+
+    chat_command::before_message("ban", |context, message| Box::pin(async move {
+        penalty_command(context, message, PenaltyKind::Ban).await;
+    })).register_for_plugin("miniluv");
+
+    chat_command::before_message("mute", |context, message| Box::pin(async move {
+        penalty_command(context, message, PenaltyKind::Mute).await;
+    })).register_for_plugin("miniluv");
+
+    chat_command::before_message("kick", |context, message| Box::pin(async move {
+        if !is_admin(context) { return reply(context, "{miniluv_permission_denied}").await; }
+        let name = match message.split_whitespace().nth(1) {
+            Some(name) => name.to_string(),
+            None => return reply(context, "{miniluv_usage}").await
+        };
+        let template = if kick(Some(&name), None) > 0 { "{miniluv_done}" } else { "{miniluv_player_not_found}" };
+        reply(context, template).await;
+    })).register_for_plugin("miniluv");
+
+    Handler::before_message::<Shutdown, _>(100, "miniluv_database_closer", |_, _| Box::pin(async move {
+        if let Some(pool) = SQLITE_POOL.get() { pool.close().await; }
+        Ok(false)
+    })).register_for_plugin("miniluv");
+}
+
+async fn check_ban(context: &mut Context<'_>, name: &str) -> anyhow::Result<bool> {
+    match find_penalty(PenaltyKind::Ban, name, &context.addr.ip()).await {
+        Ok(Some(penalty)) => {
+            info!("Banned player {} from {} is refused: {}", name, context.addr, penalty.reason);
+            context.refuse_join_game(Some("{banned_user_login}")).await
+        },
+        Ok(None) => Ok(false),
+        Err(e) => { error!("Failed to check ban of {}: {:?}", name, e); Ok(false) }
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  penalty_command
+// ----------------------------------------------------------------------------------------------------
+/// Handle `/ban` and `/mute`, in format `/<command> <name> [minutes] [reason]`.
+///
+/// Ip of the online player with that name is also penalized.
+// ----------------------------------------------------------------------------------------------------
+async fn penalty_command(context: &mut Context<'_>, message: &str, kind: PenaltyKind) {
+    if !is_admin(context) { return reply(context, "{miniluv_permission_denied}").await; }
+    let mut arguments = message.split_whitespace().skip(1);
+    let name = match arguments.next() {
+        Some(name) => name.to_string(),
+        None => return reply(context, "{miniluv_usage}").await
+    };
+    let mut arguments = arguments.peekable();
+    let duration = match arguments.peek().map(|minutes| minutes.parse::<i64>()) {
+        Some(Ok(minutes)) => { arguments.next(); Some(minutes * 60) },
+        _ => None
+    };
+    let reason = arguments.collect::<Vec<_>>().join(" ");
+    let ip = online_players(Some(&name), None).first().map(|player| player.lock().client_addr.ip().to_string());
+    let request = PenaltyRequest { kind, name: Some(name), ip, duration, reason };
+    match add_penalty(request).await {
+        Ok(_) => reply(context, "{miniluv_done}").await,
+        Err(e) => {
+            error!("Failed to add penalty: {:?}", e);
+            reply(context, "{miniluv_usage}").await;
+        }
+    }
+}
+
+fn is_admin(context: &Context<'_>) -> bool {
+    let origin_name = match context.get_player() {
+        Some(player) => player.lock().origin_name.clone(),
+        None => return false
+    };
+    origin_name.is_some_and(|origin_name| get_configuration().admins.contains(&origin_name))
+}
+
+async fn reply(context: &mut Context<'_>, template: &str) {
+    context.send_back(&generate_chat(template, Colors::Babyblue, context.get_region())).await.ok();
+}
+
+static SQLITE_POOL: OnceCell<SqlitePool> = OnceCell::const_new();
+async fn get_sqlite_pool() -> anyhow::Result<&'static SqlitePool> {
+    SQLITE_POOL.get_or_try_init(|| async {
+        let pool = SqlitePool::connect(&format!("sqlite://{}?mode=rwc", get_configuration().database)).await?;
+        sqlx::query("CREATE TABLE IF NOT EXISTS penalties (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            name TEXT,
+            ip TEXT,
+            reason TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            expire_at INTEGER
+        )").execute(&pool).await?;
+        Ok(pool)
+    }).await
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  add_penalty
+// ----------------------------------------------------------------------------------------------------
+/// Persist a penalty. Matching online players are kicked for a ban.
+///
+/// #### Return
+/// id of the new penalty.
+// ----------------------------------------------------------------------------------------------------
+pub async fn add_penalty(request: PenaltyRequest) -> anyhow::Result<i64> {
+    if request.name.is_none() && request.ip.is_none() { return Err(anyhow!("Penalty must have a name or an ip.")); }
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query("INSERT INTO penalties (kind, name, ip, reason, created_at, expire_at) VALUES (?, ?, ?, ?, ?, ?)")
+        .bind(request.kind.as_str())
+        .bind(&request.name)
+        .bind(&request.ip)
+        .bind(&request.reason)
+        .bind(now)
+        .bind(request.duration.map(|duration| now + duration))
+        .execute(get_sqlite_pool().await?).await?;
+    info!("Add {} on {:?}/{:?}: {}", request.kind.as_str(), request.name, request.ip, request.reason);
+    if request.kind == PenaltyKind::Ban {
+        kick(request.name.as_deref(), request.ip.as_deref());
+    }
+    Ok(result.last_insert_rowid())
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  find_penalty
+// ----------------------------------------------------------------------------------------------------
+/// Find an unexpired penalty aiming at given player.
+// ----------------------------------------------------------------------------------------------------
+pub async fn find_penalty(kind: PenaltyKind, name: &str, ip: &IpAddr) -> anyhow::Result<Option<Penalty>> {
+    let rows = sqlx::query("SELECT * FROM penalties WHERE kind = ? AND (expire_at IS NULL OR expire_at > ?) AND (name = ? OR ip = ?)")
+        .bind(kind.as_str())
+        .bind(chrono::Utc::now().timestamp())
+        .bind(name)
+        .bind(ip.to_string())
+        .fetch_all(get_sqlite_pool().await?).await?;
+    for row in rows.iter() {
+        let penalty = Penalty::from_row(row)?;
+        if penalty.matches(name, ip) { return Ok(Some(penalty)); }
+    }
+    Ok(None)
+}
+
+/// List all unexpired penalties.
+pub async fn list_penalties() -> anyhow::Result<Vec<Penalty>> {
+    sqlx::query("SELECT * FROM penalties WHERE expire_at IS NULL OR expire_at > ? ORDER BY id")
+        .bind(chrono::Utc::now().timestamp())
+        .fetch_all(get_sqlite_pool().await?).await?
+        .iter().map(Penalty::from_row).collect()
+}
+
+/// Remove a penalty, return `false` if it doesn't exist.
+pub async fn remove_penalty(id: i64) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM penalties WHERE id = ?").bind(id).execute(get_sqlite_pool().await?).await?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn online_players(name: Option<&str>, ip: Option<&str>) -> Vec<std::sync::Arc<parking_lot::Mutex<crate::srvpru::Player>>> {
+    PLAYERS.read().values().filter(|player| {
+        let player = player.lock();
+        name == Some(player.name.as_str()) || ip == Some(player.client_addr.ip().to_string().as_str())
+    }).cloned().collect()
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  kick
+// ----------------------------------------------------------------------------------------------------
+/// Expel online players with given name or ip.
+///
+/// #### Return
+/// count of players kicked.
+// ----------------------------------------------------------------------------------------------------
+pub fn kick(name: Option<&str>, ip: Option<&str>) -> usize {
+    let players = online_players(name, ip);
+    for player in players.iter() {
+        player.lock().expel();
+    }
+    players.len()
+}
+
+fn register_apis() {
+    if !plugin_enabled("miniluv") { return; }
+    register_api(|router| router
+        .route("/miniluv/penalties", routing::get(get_penalties).post(post_penalty))
+        .route("/miniluv/penalties/:id", routing::delete(delete_penalty))
+        .route("/miniluv/kick", routing::post(post_kick))
+    );
+}
+
+fn authorize(query: &HashMap<String, String>) -> Result<(), StatusCode> {
+    let access_key = &get_configuration().access_key;
+    if access_key.is_empty() || query.get("key") != Some(access_key) { return Err(StatusCode::UNAUTHORIZED); }
+    Ok(())
+}
+
+fn internal_error(e: anyhow::Error) -> StatusCode {
+    error!("Miniluv api failed: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+async fn get_penalties(Query(query): Query<HashMap<String, String>>) -> Result<Json<Vec<Penalty>>, StatusCode> {
+    authorize(&query)?;
+    list_penalties().await.map(Json).map_err(internal_error)
+}
+
+async fn post_penalty(Query(query): Query<HashMap<String, String>>, Json(request): Json<PenaltyRequest>) -> Result<Json<i64>, StatusCode> {
+    authorize(&query)?;
+    if request.name.is_none() && request.ip.is_none() { return Err(StatusCode::BAD_REQUEST); }
+    add_penalty(request).await.map(Json).map_err(internal_error)
+}
+
+async fn delete_penalty(Query(query): Query<HashMap<String, String>>, Path(id): Path<i64>) -> Result<StatusCode, StatusCode> {
+    authorize(&query)?;
+    match remove_penalty(id).await.map_err(internal_error)? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(StatusCode::NOT_FOUND)
+    }
+}
+
+async fn post_kick(Query(query): Query<HashMap<String, String>>, Json(request): Json<KickRequest>) -> Result<Json<usize>, StatusCode> {
+    authorize(&query)?;
+    if request.name.is_none() && request.ip.is_none() { return Err(StatusCode::BAD_REQUEST); }
+    Ok(Json(kick(request.name.as_deref(), request.ip.as_deref())))
+}
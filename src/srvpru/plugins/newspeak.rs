@@ -2,6 +2,9 @@
 // newspeak
 // ------------------------------------------------------------
 //! Stop user sending sensetive words in chat and room name.
+//! 
+//! Dependency:
+//! - [position_recorder](super::recorder::position_recorder)
 // ============================================================
 
 use std::net::IpAddr;
@@ -31,6 +34,10 @@ set_configuration! {
     big_brother_access_key: String
 }
 
+depend_on! {
+    "position_recorder"
+}
+
 /// Decide what to do on bad words.
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
 pub enum BadwordBehavior {
@@ -46,6 +53,7 @@ pub enum BadwordBehavior {
 
 pub fn init() -> anyhow::Result<()>  {
     load_configuration()?;
+    register_dependency()?;
     register_handlers();
     Ok(())
 }
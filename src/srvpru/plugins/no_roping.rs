@@ -2,6 +2,9 @@
 // no_roping
 // ------------------------------------------------------------
 //! Limit player rope.
+//! 
+//! Dependency:
+//! - [position_recorder](super::recorder::position_recorder)
 // ============================================================
 
 use once_cell::sync::OnceCell;
@@ -31,12 +34,17 @@ fn default_max_roping_time() -> i64 { 90000 }
 fn default_roping_warn_time() -> i64 { 70000 }
 fn default_scan_interval() -> u64 { 4000 }
 
+depend_on! {
+    "position_recorder"
+}
+
 player_attach! {
     last_action_time: i64
 }
 
 pub fn init() -> anyhow::Result<()> {
     load_configuration()?;
+    register_dependency()?;
     register_handlers();
     start_scanner();
     Ok(())
@@ -0,0 +1,109 @@
+// ============================================================
+// plugin_manager
+// ------------------------------------------------------------
+//! Enable or disable plugins without restarting srvpru.
+//!
+//! Apis, with `?key={access_key}`:
+//! - `GET /plugins`: enabled plugins, and all plugins which
+//!   can be enabled.
+//! - `POST /plugins/:name/enable`
+//! - `POST /plugins/:name/disable`
+//! - `POST /plugins/reload`: trigger [Reload], which also reads
+//!   `plugins` of `srvpru.yaml` again.
+//!
+//! Rooms keep running, and handlers of newly enabled plugins
+//! apply to their next message. Only handlers follow: apis,
+//! listeners and anything else a plugin sets up when srvpru
+//! starts stay as they were. So plugins without handlers,
+//! like `metrics` and `websocket`, can't be toggled here.
+//!
+//! Dependency:
+//! - [api](super::base::api)
+// ============================================================
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use axum::Json;
+use axum::extract::ConnectInfo;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::routing;
+use serde::Serialize;
+
+use crate::srvpru::get_server;
+use crate::srvpru::message::Reload;
+use crate::srvpru::plugins::PluginError;
+use crate::srvpru::plugins::available_plugins;
+use crate::srvpru::plugins::base::api::register_api;
+use crate::srvpru::plugins::enabled_plugins;
+use crate::srvpru::plugins::plugin_enabled;
+use crate::srvpru::trigger_internal;
+
+set_configuration! {
+    /// Api is refused if it's empty.
+    #[serde(default)]
+    access_key: String
+}
+
+depend_on! {
+    "api"
+}
+
+/// Body of `GET /plugins`.
+#[derive(Serialize, Debug)]
+pub struct PluginList {
+    pub enabled: Vec<String>,
+    pub available: Vec<String>
+}
+
+pub fn init() -> anyhow::Result<()> {
+    register_dependency()?;
+    if !plugin_enabled("plugin_manager") { return Ok(()); }
+    load_configuration()?;
+    register_api(|router| router
+        .route("/plugins", routing::get(get_plugins))
+        .route("/plugins/reload", routing::post(post_reload))
+        .route("/plugins/:name/:action", routing::post(post_plugin))
+    );
+    Ok(())
+}
+
+fn authorize(query: &HashMap<String, String>) -> Result<(), StatusCode> {
+    let access_key = &get_configuration().access_key;
+    if access_key.is_empty() || query.get("key") != Some(access_key) { return Err(StatusCode::UNAUTHORIZED); }
+    Ok(())
+}
+
+async fn get_plugins(Query(query): Query<HashMap<String, String>>) -> Result<Json<PluginList>, StatusCode> {
+    authorize(&query)?;
+    Ok(Json(PluginList { enabled: enabled_plugins(), available: available_plugins() }))
+}
+
+async fn post_plugin(Query(query): Query<HashMap<String, String>>, Path((name, action)): Path<(String, String)>) -> Result<Json<PluginList>, StatusCode> {
+    authorize(&query)?;
+    let enabled = match action.as_str() {
+        "enable" => true,
+        "disable" => false,
+        _ => return Err(StatusCode::NOT_FOUND)
+    };
+    match get_server().set_plugin_enabled(&name, enabled) {
+        Ok(()) => Ok(Json(PluginList { enabled: enabled_plugins(), available: available_plugins() })),
+        Err(PluginError::Unknown(_)) => Err(StatusCode::NOT_FOUND),
+        Err(PluginError::Required(_)) | Err(PluginError::Static(_)) => Err(StatusCode::FORBIDDEN),
+        Err(e @ PluginError::MissingDependency { .. }) | Err(e @ PluginError::Cycle(_)) => {
+            warn!("Refused to {} plugin {}: {}", action, name, e);
+            Err(StatusCode::CONFLICT)
+        }
+    }
+}
+
+async fn post_reload(Query(query): Query<HashMap<String, String>>, ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Result<Json<PluginList>, StatusCode> {
+    authorize(&query)?;
+    trigger_internal(addr, Reload).await.map_err(|e| {
+        error!("Failed to reload: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(PluginList { enabled: enabled_plugins(), available: available_plugins() }))
+}
@@ -5,6 +5,7 @@
 //! 
 //! Dependency:
 //! - [stage_recorder](super::recorder::stage_recorder)
+//! - [chat_command](super::base::chat_command)
 //! 
 //! **ATTENTION**  
 //! Reconnect plugin must record the last stoc game message 
@@ -48,7 +49,8 @@ set_configuration! {
 }
 
 depend_on! {
-    "stage_recorder"
+    "stage_recorder",
+    "chat_command"
 }
 
 room_attach! {
@@ -0,0 +1,63 @@
+// ============================================================
+// response_recorder
+// ------------------------------------------------------------
+//! Record which select message each duelist is answering,
+//! so that [ctos::Response] can be interpreted.
+// ============================================================
+
+use crate::srvpru::Context;
+use crate::srvpru::Handler;
+use crate::srvpru::HandlerCondition;
+use crate::srvpru::HandlerOccasion;
+use crate::ygopro::message::gm;
+use crate::ygopro::message::ctos;
+use crate::ygopro::message::ctos::ResponseDecision;
+use crate::ygopro::message::Direction;
+use crate::ygopro::message::MessageType;
+
+pub fn init() -> anyhow::Result<()> {
+    register_handlers();
+    Ok(())
+}
+
+player_attach! {
+    pending_select: Option<gm::MessageType>
+}
+
+export_player_attach_as!(get_pending_select, Option<gm::MessageType>, transformer);
+
+fn register_handlers() {
+    // Retry don't change pending select, duelist will answer the same message again.
+    Handler::new(100, "response_recorder", HandlerOccasion::Before, HandlerCondition::Dynamic(Box::new(|context|
+        matches!(context.message_type, Some(MessageType::GM(kind)) if ResponseDecision::is_select(kind))
+    )), |context| Box::pin(async move {
+        if let Some(MessageType::GM(kind)) = context.message_type {
+            get_player_attachment_sure(context).pending_select = Some(kind);
+        }
+        Ok(false)
+    })).register();
+
+    register_player_attachment_dropper();
+    register_player_attachment_mover();
+    Handler::register_handlers("response_recorder", Direction::STOC, vec!["response_recorder"]);
+}
+
+impl<'a> Context<'a> {
+    // ----------------------------------------------------------------------------------------------------
+    //  interpret_response
+    // ----------------------------------------------------------------------------------------------------
+    /// Interpret a [ctos::Response] from current player, by the last select message sent to that player.
+    ///
+    /// Return [ResponseDecision::Unknown] if no select message is recorded.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn interpret_response(&self, response: &ctos::Response) -> ResponseDecision {
+        match self.get_pending_select() {
+            Some(kind) => response.interpret(kind),
+            None => ResponseDecision::Unknown(response.data.clone())
+        }
+    }
+}
+
+fn transformer<'b>(attachment: Option<parking_lot::MappedRwLockWriteGuard<'b, PlayerAttachment>>) -> Option<gm::MessageType> {
+    attachment.and_then(|attachment| attachment.pending_select)
+}
@@ -0,0 +1,205 @@
+// ============================================================
+// replay_archive
+// ------------------------------------------------------------
+//! Persist every replay of a room, and serve them by replay id.
+//!
+//! Each duel is stored once as a `.yrp`, in a directory or in a
+//! sqlite database. Replay ids of the room are announced at
+//! `DuelEnd`, and can be downloaded by `GET /replay/:id`.
+//!
+//! Dependency:
+//! - [api](super::base::api)
+// ============================================================
+
+use std::io::Cursor;
+
+use axum::extract::Path;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
+use axum::http::StatusCode;
+use axum::http::header;
+use axum::routing;
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::Row;
+use sqlx::SqlitePool;
+use tokio::sync::OnceCell;
+
+use crate::srvpru::Handler;
+use crate::srvpru::generate_chat;
+use crate::srvpru::plugins::plugin_enabled;
+use crate::srvpru::plugins::base::api::register_api;
+use crate::ygopro::Colors;
+use crate::ygopro::data::ReplayHeader;
+use crate::ygopro::message::Direction;
+use crate::ygopro::message::stoc;
+
+set_configuration! {
+    #[serde(default)]
+    storage: ReplayStorage,
+    /// Directory for `directory` storage, or database file for `sqlite` storage.
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default = "default_id_length")]
+    id_length: usize
+}
+
+fn default_path() -> String { "./replays".to_string() }
+fn default_id_length() -> usize { 10 }
+
+depend_on! {
+    "api"
+}
+
+// Seeds of archived duels, so replay sent to each player is only stored once.
+room_attach! {
+    seeds: Vec<u32>,
+    replay_ids: Vec<String>
+}
+
+/// Where replays are persisted.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayStorage {
+    /// One `{id}.yrp` file per replay under `path`.
+    #[default]
+    Directory,
+    /// A `replays` table in sqlite database `path`.
+    Sqlite
+}
+
+pub fn init() -> anyhow::Result<()> {
+    load_configuration()?;
+    register_dependency()?;
+    register_handlers();
+    register_apis();
+    Ok(())
+}
+
+fn register_handlers() {
+    // Run before delayed_replay, which may block the replay.
+    Handler::before_message::<stoc::Replay, _>(90, "replay_archiver", |context, message| Box::pin(async move {
+        // Only header is needed to tell duels apart, body is stored as is.
+        let header = match ReplayHeader::from_reader(&mut Cursor::new(&message.data)) {
+            Ok(header) => header,
+            Err(e) => { warn!("Cannot parse replay from {}: {:?}", context.addr, e); return Ok(false); }
+        };
+        let id = {
+            let mut attachment = get_room_attachment_sure(context)?;
+            if attachment.seeds.contains(&header.seed) { return Ok(false); }
+            attachment.seeds.push(header.seed);
+            let id = generate_replay_id();
+            attachment.replay_ids.push(id.clone());
+            id
+        };
+        if let Err(e) = store_replay(&id, &message.data).await {
+            error!("Failed to archive replay {}: {:?}", id, e);
+        }
+        Ok(false)
+    })).register();
+
+    Handler::follow_message::<stoc::DuelEnd, _>(100, "replay_id_announcer", |context, _| Box::pin(async move {
+        let replay_ids = match get_room_attachment(context) {
+            Some(attachment) if !attachment.replay_ids.is_empty() => attachment.replay_ids.join(", "),
+            _ => return Ok(false)
+        };
+        context.send(&generate_chat(&format!("{{replay_archive_hint}} {}", replay_ids), Colors::Babyblue, context.get_region())).await?;
+        Ok(false)
+    })).register();
+
+    register_room_attachement_dropper();
+    Handler::register_handlers("replay_archive", Direction::STOC, vec!["replay_archiver", "replay_id_announcer"]);
+}
+
+fn generate_replay_id() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(get_configuration().id_length).map(char::from).collect()
+}
+
+/// Replay ids are generated alphanumeric, anything else is never stored.
+fn is_legal_replay_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|chr| chr.is_ascii_alphanumeric())
+}
+
+static SQLITE_POOL: OnceCell<SqlitePool> = OnceCell::const_new();
+async fn get_sqlite_pool() -> anyhow::Result<&'static SqlitePool> {
+    SQLITE_POOL.get_or_try_init(|| async {
+        let pool = SqlitePool::connect(&format!("sqlite://{}?mode=rwc", get_configuration().path)).await?;
+        sqlx::query("CREATE TABLE IF NOT EXISTS replays (id TEXT PRIMARY KEY, data BLOB NOT NULL, created_at INTEGER NOT NULL)").execute(&pool).await?;
+        Ok(pool)
+    }).await
+}
+
+fn replay_file_path(id: &str) -> std::path::PathBuf {
+    std::path::Path::new(&get_configuration().path).join(format!("{}.yrp", id))
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  store_replay
+// ----------------------------------------------------------------------------------------------------
+/// Persist a whole `.yrp` file by configured storage.
+// ----------------------------------------------------------------------------------------------------
+pub async fn store_replay(id: &str, data: &[u8]) -> anyhow::Result<()> {
+    match get_configuration().storage {
+        ReplayStorage::Directory => {
+            let path = replay_file_path(id);
+            if let Some(parent) = path.parent() { tokio::fs::create_dir_all(parent).await?; }
+            tokio::fs::write(path, data).await?;
+        }
+        ReplayStorage::Sqlite => {
+            sqlx::query("INSERT INTO replays (id, data, created_at) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(data)
+                .bind(chrono::Utc::now().timestamp())
+                .execute(get_sqlite_pool().await?).await?;
+        }
+    }
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  load_replay
+// ----------------------------------------------------------------------------------------------------
+/// Load a stored `.yrp` file.
+///
+/// #### Return
+/// `None` if no replay with this id is stored.
+// ----------------------------------------------------------------------------------------------------
+pub async fn load_replay(id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    if !is_legal_replay_id(id) { return Ok(None); }
+    match get_configuration().storage {
+        ReplayStorage::Directory => match tokio::fs::read(replay_file_path(id)).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into())
+        },
+        ReplayStorage::Sqlite => {
+            let row = sqlx::query("SELECT data FROM replays WHERE id = ?").bind(id).fetch_optional(get_sqlite_pool().await?).await?;
+            Ok(row.map(|row| row.try_get("data")).transpose()?)
+        }
+    }
+}
+
+fn register_apis() {
+    if !plugin_enabled("replay_archive") { return; }
+    register_api(|router| router.route("/replay/:id", routing::get(download_replay)));
+}
+
+async fn download_replay(Path(id): Path<String>) -> Result<(HeaderMap, Vec<u8>), StatusCode> {
+    let data = match load_replay(&id).await {
+        Ok(Some(data)) => data,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to load replay {}: {:?}", id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"));
+    // id is checked alphanumeric, so it's always a legal header value.
+    if let Ok(disposition) = HeaderValue::from_str(&format!("attachment; filename=\"{}.yrp\"", id)) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    Ok((headers, data))
+}
@@ -23,6 +23,7 @@ use crate::ygopro::Netplayer;
 
 pub fn init() -> anyhow::Result<()> {
     load_configuration()?;
+    register_dependency()?;
     register_handlers();
     Ok(())
 }
@@ -49,6 +50,11 @@ set_configuration! {
     arena: String
 }
 
+depend_on! {
+    "position_recorder",
+    "deck_recorder"
+}
+
 room_attach! {
     player_a_result: PlayerMatchResult,
     player_b_result: PlayerMatchResult,
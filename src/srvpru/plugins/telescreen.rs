@@ -4,19 +4,24 @@
 //! Insert a telescreen user in any duel,
 //! it will record all the message received.  
 //! Offer a half-way observer for outside. 
+//! With [api](super::base::api), duel log of a room is served by `GET /duel_log/:room?key={access_key}`.
 //! 
 //! Dependency :
 //! - [version_checker](super::version_checker)
 //! - [stage_recorder](super::recorder::stage_recorder)
 // ============================================================
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
+use axum::Json;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::routing;
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
 use tokio::task::JoinHandle;
-use tokio::io::AsyncReadExt;
 use parking_lot::Mutex;
 
 use crate::srvpru::CommonError;
@@ -30,16 +35,25 @@ use crate::srvpru::processor::Handler;
 use crate::srvpru::plugins::recorder::stage_recorder;
 use crate::srvpru::plugins::version_checker;
 use crate::srvpru::PlayerPrecursor;
+use crate::srvpru::plugins::plugin_enabled;
+use crate::srvpru::plugins::base::api::register_api;
 
 use crate::ygopro::Colors;
 use crate::ygopro::Netplayer;
+use crate::ygopro::client::YgoClient;
+use crate::ygopro::duel_log::DuelEventRecorder;
+use crate::ygopro::duel_log::DuelLog;
 use crate::ygopro::message::ctos;
 use crate::ygopro::message::stoc;
 use crate::ygopro::message::srvpru;
 use crate::ygopro::message::Direction;
 use crate::ygopro::message::MessageType;
-use crate::ygopro::message::string::cast_to_fix_length_array;
-use crate::ygopro::message::generate::wrap_mapped_struct;
+
+set_configuration! {
+    /// Duel log api is refused if it's empty.
+    #[serde(default)]
+    access_key: String
+}
 
 room_attach! {
     pointer: Arc<Mutex<Telescreen>>,
@@ -53,14 +67,27 @@ pub struct Telescreen {
     watchers: Vec<Player>,
 }
 
+impl Telescreen {
+    /// Events of the room so far, from what the telescreen received as an observer.
+    pub fn duel_log(&self) -> DuelLog {
+        let mut recorder = DuelEventRecorder::new();
+        for data in self.buffer.iter() {
+            recorder.record_stream(data);
+        }
+        recorder.into_log(None)
+    }
+}
+
 depend_on! {
     "version_checker",
     "stage_recorder"
 }
 
 pub fn init() -> anyhow::Result<()> {
+    load_configuration()?;
     register_handlers();
     register_dependency()?;
+    register_apis();
     Ok(())
 }
 
@@ -180,40 +207,36 @@ fn register_handlers() {
     Handler::register_handlers("telescreen", Direction::SRVPRU, vec!["telescreen_injector", "telescreen_room_attachment_dropper"]);
 }
 
+fn register_apis() {
+    if !plugin_enabled("telescreen") || !plugin_enabled("api") { return; }
+    register_api(|router| router.route("/duel_log/:room", routing::get(get_duel_log)));
+}
+
+fn authorize(query: &HashMap<String, String>) -> Result<(), StatusCode> {
+    let access_key = &get_configuration().access_key;
+    if access_key.is_empty() || query.get("key") != Some(access_key) { return Err(StatusCode::UNAUTHORIZED); }
+    Ok(())
+}
+
+async fn get_duel_log(Path(room): Path<String>, Query(query): Query<HashMap<String, String>>) -> Result<Json<DuelLog>, StatusCode> {
+    authorize(&query)?;
+    let pointer = ROOM_ATTACHMENTS.read().get(&room).map(|attachment| attachment.pointer.clone()).ok_or(StatusCode::NOT_FOUND)?;
+    let log = pointer.lock().duel_log();
+    Ok(Json(log))
+}
+
 async fn register_telescreen(addr: SocketAddr, telescreen: Arc<Mutex<Telescreen>>) -> anyhow::Result<()> {
-    let stream = TcpStream::connect(addr).await?;
-    let (mut reader, mut writer) = stream.into_split();
     let version = version_checker::get_configuration().version;
-    writer.write_all(&wrap_mapped_struct(&struct_sequence! {
-        ctos::PlayerInfo { name: cast_to_fix_length_array(TELESCREEN_NAME) },
-        ctos::JoinGame { 
-            version,
-            align: 0,
-            gameid: 0,
-            pass: cast_to_fix_length_array(TELESCREEN_NAME)
-        },
-        ctos::HsToOBServer {}
-    })).await?;
-    writer.forget();
+    let mut client = YgoClient::connect(addr).version(version).player_info(TELESCREEN_NAME).observe().join(TELESCREEN_NAME).await?;
     let telescreen_for_listener = telescreen.clone();
     let mut _telescreen = telescreen.lock();
     _telescreen.listener = Some(tokio::spawn(async move {
-        let mut buf = [0; 10240];
-        loop {
-            let n = match reader.read(&mut buf).await {
-                Ok(n) if n == 0 => break,
-                Ok(n) => n,
-                Err(e) => {
-                    error!("Error on big brother listening on {:}: {}", &addr, e);
-                    break
-                }
-            };
-            let slice = &buf[0..n];
+        while let Some(message) = client.recv().await {
             let mut telescreen = telescreen_for_listener.lock();
-            telescreen.buffer.push(slice.to_vec());
+            telescreen.buffer.push(message.raw.clone());
             for player in telescreen.watchers.iter_mut() {
                 if let Some(stream) = player.client_stream_writer.as_mut() {
-                    stream.write_all(slice).await.ok(); // We don't care watcher success or not. If it fail he can rejoin.
+                    stream.write_all(&message.raw).await.ok(); // We don't care watcher success or not. If it fail he can rejoin.
                 }
             }
         }
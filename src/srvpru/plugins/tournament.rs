@@ -4,7 +4,9 @@
 //! Limit a match to target time, and step in death 3 turn 
 //! when timeout.
 //! 
-//! dependecy:
+//! Dependency:
+//! - [api](super::base::api)
+//! - [position_recorder](super::recorder::position_recorder)
 //! - [lp_recorder](super::lp_recorder)
 // ============================================================
 
@@ -29,6 +31,12 @@ set_configuration! {
 
 fn default_round_time() -> u64 { 40 }
 
+depend_on! {
+    "api",
+    "position_recorder",
+    "lp_recorder"
+}
+
 room_attach! {
     countdown: Option<JoinHandle<()>>,
     tournament_state: TournamentState
@@ -38,6 +46,7 @@ export_room_attach_as!(get_tournament);
 
 pub fn init() -> anyhow::Result<()> {
     load_configuration()?;
+    register_dependency()?;
     register_handlers();
     Ok(())
 }
@@ -0,0 +1,136 @@
+// ============================================================
+// websocket
+// ------------------------------------------------------------
+//! Accept ygopro clients on `path` of api, with `CTOS` frames
+//! carried in binary websocket messages.
+//!
+//! Connections are served by the same pipeline as tcp ones,
+//! so web and desktop players can share rooms. Each binary
+//! message sent back holds one or more complete `STOC` frames.
+//!
+//! Dependency:
+//! - [api](super::base::api)
+// ============================================================
+
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::Poll;
+
+use axum::extract::ConnectInfo;
+use axum::extract::ws::Message;
+use axum::extract::ws::WebSocket;
+use axum::extract::ws::WebSocketUpgrade;
+use axum::response::IntoResponse;
+use axum::routing;
+use futures_util::SinkExt;
+use futures_util::StreamExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::srvpru::MessageFramer;
+use crate::srvpru::get_server;
+use crate::srvpru::plugins::base::api::register_api;
+use crate::srvpru::plugins::plugin_enabled;
+
+set_configuration! {
+    #[serde(default = "default_path")]
+    path: String
+}
+
+fn default_path() -> String { "/ygopro".to_string() }
+
+depend_on! {
+    "api"
+}
+
+/// Bytes buffered from websocket before server reads them.
+const BRIDGE_BUFFER_SIZE: usize = 10240;
+
+pub fn init() -> anyhow::Result<()> {
+    register_dependency()?;
+    if !plugin_enabled("websocket") { return Ok(()); }
+    load_configuration()?;
+    register_api(|router| router.route(&get_configuration().path, routing::get(upgrade)));
+    Ok(())
+}
+
+// ============================================================
+//  WebSocketSink
+// ------------------------------------------------------------
+/// Writer of a websocket player. \
+/// Data is passed to the task sending websocket messages; like
+/// a tcp writer, dropping it closes the connection.
+///
+/// Writing never waits, as callers may hold room or player locks.
+/// If [SINK_BUFFER_SIZE] writes are not sent yet, the client is
+/// too slow: the sender is dropped to close it, and writes fail
+/// like a broken tcp connection instead of stalling its room.
+// ============================================================
+#[derive(Debug)]
+struct WebSocketSink {
+    addr: SocketAddr,
+    sender: Option<mpsc::Sender<Vec<u8>>>
+}
+
+/// Writes buffered for websocket before they are sent.
+const SINK_BUFFER_SIZE: usize = 64;
+
+impl AsyncWrite for WebSocketSink {
+    fn poll_write(self: Pin<&mut Self>, _: &mut std::task::Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = match this.sender.as_ref() {
+            Some(sender) => sender.try_send(buf.to_vec()),
+            None => return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+        };
+        match result {
+            Ok(_) => Poll::Ready(Ok(buf.len())),
+            Err(TrySendError::Full(_)) => {
+                warn!("Websocket {} is too slow, closing it.", this.addr);
+                this.sender = None;
+                Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+            }
+            Err(TrySendError::Closed(_)) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+async fn upgrade(ws: WebSocketUpgrade, ConnectInfo(addr): ConnectInfo<SocketAddr>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| bridge(socket, addr))
+}
+
+async fn bridge(socket: WebSocket, addr: SocketAddr) {
+    let (mut websocket_writer, mut websocket_reader) = socket.split();
+    let (mut inbound, reader) = tokio::io::duplex(BRIDGE_BUFFER_SIZE);
+    let (sender, mut receiver) = mpsc::channel::<Vec<u8>>(SINK_BUFFER_SIZE);
+    tokio::spawn(get_server().serve(reader, Box::new(WebSocketSink { addr, sender: Some(sender) }), addr));
+    tokio::spawn(async move {
+        let mut framer = MessageFramer::new();
+        while let Some(data) = receiver.recv().await {
+            match framer.feed(&data) {
+                Ok(Some(frames)) => if websocket_writer.send(Message::Binary(frames)).await.is_err() { break; },
+                Ok(None) => continue,
+                Err(e) => { warn!("Broken frame sent to websocket {}: {}", addr, e); break; }
+            }
+        }
+        websocket_writer.close().await.ok();
+    });
+    // Dropping `inbound` ends the server side with an eof, as a closed tcp connection.
+    while let Some(Ok(message)) = websocket_reader.next().await {
+        match message {
+            Message::Binary(data) => if inbound.write_all(&data).await.is_err() { break; },
+            Message::Close(_) => break,
+            _ => debug!("Ignored non-binary message from websocket {}", addr)
+        }
+    }
+}
@@ -7,6 +7,9 @@
 //! Different from srvpro, `windbot` plugin make windbot directly
 //! join to inner ygopro server, don't pass through srvpru
 //! so that any other plugin won't influence windbot.
+//! 
+//! Dependency:
+//! - [chat_command](super::base::chat_command)
 // ============================================================
 
 use std::sync::Arc;
@@ -35,6 +38,10 @@ set_configuration! {
     bots: Vec<Bot>
 }
 
+depend_on! {
+    "chat_command"
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct Bot {
     name: String,
@@ -48,6 +55,7 @@ use_http_client!();
 
 pub fn init() -> anyhow::Result<()> {
     load_configuration()?; 
+    register_dependency()?;
     register_handlers();
     Ok(())
 }
@@ -14,13 +14,16 @@ use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::future::Future;
+use std::panic::AssertUnwindSafe;
 
+use futures_util::FutureExt;
 use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use serde::Serialize;
 use parking_lot::RwLock;
 use parking_lot::Mutex;
 use tokio::io::AsyncWriteExt;
 use anyhow::Result;
-use tokio::net::tcp::OwnedWriteHalf;
 
 use crate::ygopro::message::stoc;
 use crate::ygopro::message::Struct;
@@ -30,7 +33,9 @@ use crate::ygopro::message::MessageType;
 use crate::ygopro::message::try_get_message_type;
 use crate::ygopro::message::deserialize_struct_by_type;
 
+use crate::srvpru::BoxedPacketSink;
 use crate::srvpru::CommonError;
+use crate::srvpru::RoomSelector;
 use crate::srvpru::message::SRVPRUProcessError;
 
 // ============================================================
@@ -54,7 +59,30 @@ pub struct Handler {
     occasion: HandlerOccasion,
     /// What handler actually execute when condition meets. \
     /// Go to [Processor] to get what return value means.
-    execution: Box<dyn for <'a, 'b> Fn(&'b mut Context<'a>) -> Pin<Box<dyn Future<Output = Result<bool>> + Send + 'b>> + Send + Sync>
+    execution: Box<dyn for <'a, 'b> Fn(&'b mut Context<'a>) -> Pin<Box<dyn Future<Output = Result<bool>> + Send + 'b>> + Send + Sync>,
+    /// Deadline, and what to do if it times out or panics.
+    policy: HandlerPolicy
+}
+
+/// Deadline and failure policy of a [Handler], in `handler_policies` of srvpru configuration.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HandlerPolicy {
+    /// Deadline in milliseconds. `0` for `handler_timeout` of srvpru configuration.
+    #[serde(default)]
+    pub timeout: u64,
+    #[serde(default)]
+    pub on_failure: FailurePolicy
+}
+
+/// What to do when a [Handler] times out or panics.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Throw [ProcessorError::HandlerFailed], as if handler returned an error.
+    #[default]
+    Closed,
+    /// Report [ProcessorError::HandlerFailed], and go on as if handler returned `Ok(false)`.
+    Open
 }
 
 /// Decide when to check or run a [Handler].
@@ -93,7 +121,8 @@ impl Handler {
             owner: None,
             occasion,
             condition,
-            execution: Box::new(execution)
+            execution: Box::new(execution),
+            policy: HandlerPolicy::default()
         }
     }
 
@@ -128,7 +157,8 @@ impl Handler {
             owner: None,
             occasion: HandlerOccasion::Before,
             condition: HandlerCondition::MessageType(S::message()),
-            execution: Box::new(move |context| Box::pin(Handler::typed_execute::<S, F>(context, execution)))
+            execution: Box::new(move |context| Box::pin(Handler::typed_execute::<S, F>(context, execution))),
+            policy: HandlerPolicy::default()
         }
     }
 
@@ -154,7 +184,8 @@ impl Handler {
             owner: None,
             occasion: HandlerOccasion::After,
             condition: HandlerCondition::MessageType(S::message()),
-            execution: Box::new(move |context| Box::pin(Handler::typed_execute::<S, F>(context, execution)))
+            execution: Box::new(move |context| Box::pin(Handler::typed_execute::<S, F>(context, execution))),
+            policy: HandlerPolicy::default()
         }
     }
 
@@ -177,15 +208,54 @@ impl Handler {
             if !context.deserialized { context.deserialize_message(); }
             let mut result = Ok(false);
             if let Some(mut message) = context.message.take() {
+                let mut panic = None;
                 if let Ok(data) = message.downcast_mut::<S>().ok_or(CommonError::IllegalType) {
-                    result = Ok(execution(context, data).await?);
+                    match AssertUnwindSafe(execution(context, data)).catch_unwind().await {
+                        Ok(execution_result) => result = execution_result,
+                        Err(payload) => panic = Some(payload)
+                    }
                 }
                 context.message.replace(message);
+                // Pass panic on after message is back, so a fail-open handler doesn't lose it.
+                if let Some(payload) = panic { std::panic::resume_unwind(payload); }
             }
             result
     }
 }
 
+impl Handler {
+    /// Set default deadline and failure policy, which `handler_policies` of srvpru configuration overrides.
+    pub fn with_policy(mut self, policy: HandlerPolicy) -> Handler {
+        self.policy = policy;
+        self
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  execute
+    // ----------------------------------------------------------------------------------------------------
+    /// Run `execution` within deadline of `policy`, catching panic.
+    ///
+    /// #### Return
+    /// * `Ok(result)`: what `execution` returns.
+    /// * `Err(ProcessorError::HandlerFailed)`: `execution` timed out or panicked.
+    // ----------------------------------------------------------------------------------------------------
+    async fn execute<'a>(&self, context: &mut Context<'a>, policy: &HandlerPolicy) -> core::result::Result<Result<bool>, ProcessorError> {
+        let execution = AssertUnwindSafe((*self.execution)(context)).catch_unwind();
+        let result = if policy.timeout == 0 { execution.await }
+        else {
+            let timeout = tokio::time::Duration::from_millis(policy.timeout);
+            tokio::time::timeout(timeout, execution).await
+                .map_err(|_| ProcessorError::HandlerFailed { handler: self.name.clone(), cause: HandlerFailure::Timeout })?
+        };
+        result.map_err(|payload| {
+            let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            ProcessorError::HandlerFailed { handler: self.name.clone(), cause: HandlerFailure::Panic(message) }
+        })
+    }
+}
+
 impl HandlerCondition {
     /// Check if current condition meets.
     #[inline]
@@ -207,7 +277,7 @@ impl std::fmt::Display for Handler {
 lazy_static! {
     /// Handlers simply sorted by name. \
     /// Keep the real [Handler] instances.
-    pub static ref HANDLER_LIBRARY: RwLock<HashMap<String, Handler>> = RwLock::new(HashMap::new());
+    pub static ref HANDLER_LIBRARY: RwLock<HashMap<String, Arc<Handler>>> = RwLock::new(HashMap::new());
     /// Handlers sorted by plugins. \
     /// Only save handler name.
     pub static ref HANDLER_LIBRARY_BY_PLUGIN: RwLock<HashMap<String, HashMap<Direction, Vec<String>>>> = RwLock::new(HashMap::new());
@@ -225,7 +295,7 @@ impl Handler {
     /// configuration in `ctos_handlers`, `stoc_handlers`, or `srvpru_handlers`
     // ----------------------------------------------------------------------------------------------------
     pub fn register(self) {
-        HANDLER_LIBRARY.write().insert(self.name.clone(), self);
+        HANDLER_LIBRARY.write().insert(self.name.clone(), Arc::new(self));
     }
 
     // ----------------------------------------------------------------------------------------------------
@@ -268,7 +338,7 @@ impl Handler {
 
     #[allow(dead_code)]
     pub fn register_handler(name: &str, handler: Handler) {
-        HANDLER_LIBRARY.write().insert(name.to_string(), handler);
+        HANDLER_LIBRARY.write().insert(name.to_string(), Arc::new(handler));
     }
 
     
@@ -291,7 +361,10 @@ impl Handler {
         let mut handlers: Vec<String> = handlers.into_iter().map(|_ref| _ref.to_string()).collect();
         let mut handler_library = HANDLER_LIBRARY.write();
         for handler_name in handlers.iter() {
-            if let Some(mut handler) = handler_library.get_mut(handler_name) { handler.owner = Some(plugin_name.to_string()); }
+            // Handlers are still unique before processors are built.
+            if let Some(handler) = handler_library.get_mut(handler_name) { 
+                if let Some(handler) = Arc::get_mut(handler) { handler.owner = Some(plugin_name.to_string()); }
+            }
             else { warn!("Plugin {} is try to register a unexist handler {}.", plugin_name, handler_name) }
         }
 
@@ -316,7 +389,7 @@ impl Handler {
 // ====================================================================================================
 pub struct Context<'a> {
     /// Socket which will point to origin **target**.
-    pub socket: &'a mut Option<tokio::net::tcp::OwnedWriteHalf>,
+    pub socket: &'a mut Option<BoxedPacketSink>,
     /// For `CTOS` and `STOC` message, always [`Player`](crate::srvpru::Player)'s address. \
     /// For `SRVPRU` message, it depends on specific message type.
     pub addr: SocketAddr,
@@ -361,6 +434,33 @@ impl<'a> Context<'a> {
         self.message = self.message_type.map(|_type| deserialize_struct_by_type(_type, &self.message_buffer[3..])).flatten();
         self.deserialized = true
     }    
+
+    /// Serialize [`message`](Context#structfield.message), to restore it if a handler times out holding it.
+    fn backup_message(&self) -> Option<Vec<u8>> {
+        let data = bincode::serialize(&**self.message.as_ref()?).ok()?;
+        match self.message_type? {
+            // Game message children are deserialized with their kind.
+            MessageType::GM(kind) => Some([vec![kind.into()], data].concat()),
+            _ => Some(data)
+        }
+    }
+
+    /// Put message back, if a failed handler was holding it. \
+    /// It's deserialized from `backup` of [`backup_message`](Context::backup_message), or from
+    /// [`message_buffer`](Context#structfield.message_buffer) without one. Internal messages can't be restored.
+    fn restore_message(&mut self, backup: Option<Vec<u8>>) {
+        if self.message.is_some() || !self.deserialized { return; }
+        let message_type = match self.message_type { Some(message_type) => message_type, None => return };
+        self.message = match (message_type, backup) {
+            (MessageType::GM(_), Some(backup)) => deserialize_struct_by_type(MessageType::STOC(stoc::MessageType::GameMessage), &backup)
+                .and_then(|message| message.downcast::<stoc::GameMessage>().ok())
+                .map(|message| message.message),
+            (MessageType::GM(_), None) => None,
+            (_, Some(backup)) => deserialize_struct_by_type(message_type, &backup),
+            (_, None) if self.message_buffer.len() >= 3 => deserialize_struct_by_type(message_type, &self.message_buffer[3..]),
+            _ => None
+        };
+    }
 }
 
 
@@ -372,9 +472,13 @@ impl<'a> Context<'a> {
 pub struct Processor {
     direction: Direction,
     /// Handlers run after message sent to server/client.
-    before_handlers: Vec<Handler>,
+    before_handlers: Vec<Arc<Handler>>,
     /// Handlers run before message sent to server/client.
-    after_handlers: Vec<Handler>
+    after_handlers: Vec<Arc<Handler>>,
+    /// Policies overriding those of handlers, by handler name.
+    policies: HashMap<String, HandlerPolicy>,
+    /// Rooms where handlers run, by handler name. Handlers not listed run in all rooms.
+    room_selectors: HashMap<String, Arc<RoomSelector>>
 }
 
 #[doc(hidden)]
@@ -405,7 +509,19 @@ pub enum ProcessorError {
     #[error("Some handler decide to kick the client off.")]
     Abort,
     #[error("After error process, handler decide to kick the client off.")]
-    ErrorAbort
+    ErrorAbort,
+    /// See [HandlerPolicy].
+    #[error("Handler {handler} {cause}.")]
+    HandlerFailed { handler: String, cause: HandlerFailure }
+}
+
+/// Why a [Handler] failed, see [ProcessorError::HandlerFailed].
+#[derive(Error, Debug)]
+pub enum HandlerFailure {
+    #[error("timed out")]
+    Timeout,
+    #[error("panicked: {0}")]
+    Panic(String)
 }
 
 /// Errors happen on listening to sockets.
@@ -419,6 +535,35 @@ pub enum ListenError {
     Drop(anyhow::Error),
 }
 
+impl ProcessorError {
+    /// Variant name, as metrics label.
+    pub fn variant(&self) -> &'static str {
+        match self {
+            ProcessorError::Oversize => "Oversize",
+            ProcessorError::BufferLength => "BufferLength",
+            ProcessorError::ProtoLength => "ProtoLength",
+            ProcessorError::MessageLength => "MessageLength",
+            ProcessorError::FailedToWrite(_) => "FailedToWrite",
+            ProcessorError::FailedToProcess(_) => "FailedToProcess",
+            ProcessorError::FailedToSerialize(_) => "FailedToSerialize",
+            ProcessorError::Abort => "Abort",
+            ProcessorError::ErrorAbort => "ErrorAbort",
+            ProcessorError::HandlerFailed { .. } => "HandlerFailed"
+        }
+    }
+}
+
+impl ListenError {
+    /// Variant name, as metrics label.
+    pub fn variant(&self) -> &'static str {
+        match self {
+            ListenError::Oversize => "Oversize",
+            ListenError::Timeout => "Timeout",
+            ListenError::Drop(_) => "Drop"
+        }
+    }
+}
+
 impl core::convert::From<anyhow::Error> for ProcessorError {
     fn from(err: anyhow::Error) -> Self {
         if err.is::<ProcessorError>() { return err.downcast::<ProcessorError>().unwrap(); }
@@ -429,7 +574,7 @@ impl core::convert::From<anyhow::Error> for ProcessorError {
 impl Processor {
     #[doc(hidden)]
     pub(super) fn new(direction: Direction) -> Processor {
-        Processor { direction, before_handlers: Vec::new(), after_handlers: Vec::new() }
+        Processor { direction, before_handlers: Vec::new(), after_handlers: Vec::new(), policies: HashMap::new(), room_selectors: HashMap::new() }
     }
 
     // ----------------------------------------------------------------------------------------------------
@@ -453,6 +598,9 @@ impl Processor {
     /// 
     /// `Err` means some unexpected scene happen. Be cautious about throwing errors. 
     /// 
+    /// A handler timing out or panicking is a [`ProcessorError::HandlerFailed`], or ignored after
+    /// reported if it fails open. See [HandlerPolicy].
+    /// 
     /// #### [Game Message](crate::ygopro::message::gm)
     /// For convenience of processing [`GameMessage`](crate::ygopro::message::stoc::GameMessage), 
     /// an additional round will be added after message processed, with [MessageType::GM].
@@ -461,7 +609,7 @@ impl Processor {
     /// the additional round will be skipped. if an `Err` happen on addtional round, the final result of 
     /// message is `Err`.
     // ----------------------------------------------------------------------------------------------------
-    pub async fn process_multiple_messages<'a>(&self, socket: &mut Option<tokio::net::tcp::OwnedWriteHalf>, addr: SocketAddr, data: &'a [u8]) -> core::result::Result<(), ProcessorError> {
+    pub async fn process_multiple_messages<'a>(&self, socket: &mut Option<BoxedPacketSink>, addr: SocketAddr, data: &'a [u8]) -> core::result::Result<(), ProcessorError> {
         let mut rest_data = data;
         let mut count = 0;
         let mut requests = Vec::new();
@@ -537,7 +685,7 @@ impl Processor {
     /// * Happen in [HandlerOccasion::After]: Immediately start a [SRVPRUProcessError] with no recursive,
     /// also ignore the `block_message`
     // ----------------------------------------------------------------------------------------------------
-    pub async fn process_internal_message<S: Struct + MappedStruct>(&'static self, addr: SocketAddr, obj: S) -> core::result::Result<bool, ProcessorError> {
+    pub async fn process_internal_message<S: Struct + MappedStruct>(self: &Arc<Self>, addr: SocketAddr, obj: S) -> core::result::Result<bool, ProcessorError> {
         let mut socket = None;
         let message_buffer: [u8; 0] = [0; 0];
         let mut context = self.generate_context(&mut socket, addr, Some(S::message()), HandlerOccasion::Before, &message_buffer, Some(Box::new(obj) as Box<dyn Struct>));
@@ -546,23 +694,25 @@ impl Processor {
         let message = context.message;
         let block_message = context.block_message;
         if interrupted { return Ok(block_message); }
+        let processor = self.clone();
         tokio::spawn(async move {
-            let mut context = self.generate_context(&mut socket, addr, Some(S::message()), HandlerOccasion::After, &message_buffer, message);
+            let mut context = processor.generate_context(&mut socket, addr, Some(S::message()), HandlerOccasion::After, &message_buffer, message);
             context.deserialized = true;
-            if let Err(error) = self.process_handlers(&mut context).await {
+            if let Err(error) = processor.process_handlers(&mut context).await {
+                crate::srvpru::metrics::record_processor_error(Direction::SRVPRU, &error);
                 let message = Some(Box::new(SRVPRUProcessError { error }) as Box<dyn Struct>);
-                let mut inner_context = self.generate_context(&mut context.socket, addr, Some(SRVPRUProcessError::message()), HandlerOccasion::Before, &context.message_buffer, message);
+                let mut inner_context = processor.generate_context(&mut context.socket, addr, Some(SRVPRUProcessError::message()), HandlerOccasion::Before, &context.message_buffer, message);
                 inner_context.deserialized = true;
-                if self.process_handlers(&mut inner_context).await.is_ok() {
+                if processor.process_handlers(&mut inner_context).await.is_ok() {
                     inner_context.occasion = HandlerOccasion::After;
-                    self.process_handlers(&mut inner_context).await.ok();
+                    processor.process_handlers(&mut inner_context).await.ok();
                 }
             }
         });
         Ok(block_message)
     }
 
-    fn generate_context<'a>(&self, socket: &'a mut Option<OwnedWriteHalf>, addr: SocketAddr, message_type: Option<MessageType>, occasion: HandlerOccasion, message_buffer: &'a [u8], message: Option<Box<dyn Struct>>) -> Context<'a> {
+    fn generate_context<'a>(&self, socket: &'a mut Option<BoxedPacketSink>, addr: SocketAddr, message_type: Option<MessageType>, occasion: HandlerOccasion, message_buffer: &'a [u8], message: Option<Box<dyn Struct>>) -> Context<'a> {
         Context {
             socket,
             addr,
@@ -585,6 +735,7 @@ impl Processor {
     }
 
     async fn process_runtime_error(&self, addr: SocketAddr, error: ProcessorError) -> bool {
+        crate::srvpru::metrics::record_processor_error(self.direction, &error);
         match self.direction {
             Direction::STOC =>   crate::srvpru::server::trigger_internal(addr, crate::srvpru::message::STOCProcessError   { error }).await.map_or(true, |block_message| block_message),
             Direction::CTOS =>   crate::srvpru::server::trigger_internal(addr, crate::srvpru::message::CTOSProcessError   { error }).await.map_or(true, |block_message| block_message),
@@ -592,7 +743,16 @@ impl Processor {
         }
     }
 
-    async fn process_context<'a>(&self, socket: &mut Option<OwnedWriteHalf>, context: &mut Context<'a>) -> core::result::Result<ResponseData<'a>, ProcessorError> {
+    /// Report a failure of fail-open handler, which is ignored.
+    fn report_handler_failure(&self, addr: SocketAddr, error: ProcessorError) {
+        warn!("{} Ignored as it fails open.", error);
+        crate::srvpru::metrics::record_processor_error(self.direction, &error);
+        // Reporting by another internal message may fail again, endlessly.
+        if self.direction == Direction::SRVPRU { return; }
+        crate::srvpru::server::report_processor_error(self.direction, addr, error);
+    }
+
+    async fn process_context<'a>(&self, socket: &mut Option<BoxedPacketSink>, context: &mut Context<'a>) -> core::result::Result<ResponseData<'a>, ProcessorError> {
         // take actual socket into it
         if let Some(actual_socket) = socket.take() { context.socket.replace(actual_socket); }
 
@@ -647,8 +807,22 @@ impl Processor {
             HandlerOccasion::Never => return Ok(false),
         };
         for handler in handlers {
-            if handler.condition.meet(context) {
-                if (*handler.execution)(context).await.map_err::<ProcessorError, _>(|err| err.into())? {
+            if handler.condition.meet(context) && self.in_selected_room(&handler.name, context) {
+                let timer = crate::srvpru::metrics::start_handler_timer(&handler.name);
+                let policy = self.policies.get(&handler.name).unwrap_or(&handler.policy);
+                let backup = if policy.on_failure == FailurePolicy::Open { context.backup_message() } else { None };
+                let result = handler.execute(context, policy).await;
+                drop(timer);
+                let result = match result {
+                    Ok(result) => result,
+                    Err(error) if policy.on_failure == FailurePolicy::Open => {
+                        self.report_handler_failure(context.addr, error);
+                        context.restore_message(backup);
+                        continue;
+                    },
+                    Err(error) => Err(error)?
+                };
+                if result.map_err::<ProcessorError, _>(|err| err.into())? {
                     trace!("    {:} decide to break process.", handler.name);
                     return Ok(true)
                 }
@@ -660,6 +834,12 @@ impl Processor {
 
     /// Add a handler to this processor.
     pub fn add_handler(&mut self, handler: Handler) {
+        self.add_shared_handler(Arc::new(handler), None);
+    }
+
+    /// Add a handler in [HANDLER_LIBRARY] to this processor, overriding its policy if `policy` is given.
+    pub fn add_shared_handler(&mut self, handler: Arc<Handler>, policy: Option<HandlerPolicy>) {
+        if let Some(policy) = policy { self.policies.insert(handler.name.clone(), policy); }
         match handler.occasion {
             HandlerOccasion::Before => self.before_handlers.push(handler),
             HandlerOccasion::After => self.after_handlers.push(handler),
@@ -667,6 +847,25 @@ impl Processor {
         }
     }
 
+    /// Run handler named `name` only in rooms matching `selector`.
+    pub fn restrict_to_rooms(&mut self, name: &str, selector: Arc<RoomSelector>) {
+        self.room_selectors.insert(name.to_string(), selector);
+    }
+
+    /// If handler named `name` should run in room of `context`. \
+    /// Without a room, e.g. before joining one, handlers always run.
+    fn in_selected_room(&self, name: &str, context: &Context) -> bool {
+        match (self.room_selectors.get(name), context.get_room()) {
+            (Some(selector), Some(room)) => room.lock().match_selector(selector),
+            _ => true
+        }
+    }
+
+    /// If a handler named `name` is added.
+    pub fn contains_handler(&self, name: &str) -> bool {
+        self.before_handlers.iter().chain(self.after_handlers.iter()).any(|handler| handler.name == name)
+    }
+
     /// Sort handlers by priority.
     /// Run this method before process.
     pub fn prepare(&mut self) {
@@ -750,4 +949,158 @@ impl std::fmt::Debug for Processor {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::srvpru::MemorySink;
+    use crate::ygopro::message::ctos;
+    use crate::ygopro::message::generate::wrap_mapped_struct;
+    use crate::ygopro::message::string::cast_to_c_array;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:7911".parse().unwrap()
+    }
+
+    fn chat(message: &str) -> Vec<u8> {
+        wrap_mapped_struct(&ctos::Chat { msg: cast_to_c_array(message) })
+    }
+
+    #[tokio::test]
+    async fn forward_unchanged_messages() {
+        let processor = Processor::new(Direction::CTOS);
+        let sink = MemorySink::new();
+        let data = [chat("hello"), wrap_mapped_struct(&ctos::HsReady)].concat();
+        processor.process_multiple_messages(&mut Some(sink.boxed()), addr(), &data).await.unwrap();
+        assert_eq!(sink.take(), data);
+
+        processor.process_multiple_messages(&mut None, addr(), &data).await.unwrap();
+        assert!(sink.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn handlers_rewrite_and_block_messages() {
+        let mut processor = Processor::new(Direction::CTOS);
+        processor.add_handler(Handler::before_message::<ctos::Chat, _>(100, "test_rewriter", |context, message| Box::pin(async move {
+            message.msg = cast_to_c_array("rewritten");
+            context.reserialize = true;
+            Ok(false)
+        })));
+        processor.add_handler(Handler::before_message::<ctos::HsReady, _>(100, "test_blocker", |context, _| Box::pin(async move {
+            context.block_message()
+        })));
+        processor.prepare();
+        let sink = MemorySink::new();
+        let data = [chat("hello"), wrap_mapped_struct(&ctos::HsReady), wrap_mapped_struct(&ctos::HsNotReady)].concat();
+        processor.process_multiple_messages(&mut Some(sink.boxed()), addr(), &data).await.unwrap();
+        assert_eq!(sink.take(), [chat("rewritten"), wrap_mapped_struct(&ctos::HsNotReady)].concat());
+    }
+
+    #[tokio::test]
+    async fn catch_panic_and_timeout() {
+        let processor = Processor::new(Direction::CTOS);
+        let data = chat("hello");
+        let mut socket = None;
+        let mut context = processor.generate_context(&mut socket, addr(), try_get_message_type(Direction::CTOS, data[2]), HandlerOccasion::Before, &data, None);
+
+        let panicking = Handler::before_message::<ctos::Chat, _>(100, "test_panicking", |_, _| Box::pin(async move { panic!("boom") }));
+        let result = panicking.execute(&mut context, &panicking.policy).await;
+        assert!(matches!(result, Err(ProcessorError::HandlerFailed { cause: HandlerFailure::Panic(ref message), .. }) if message == "boom"));
+        // Message is put back before the panic goes on.
+        assert!(context.message.as_ref().and_then(|message| message.downcast_ref::<ctos::Chat>()).is_some());
+
+        let sleeping = Handler::before_message::<ctos::Chat, _>(100, "test_sleeping", |_, _| Box::pin(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            Ok(false)
+        })).with_policy(HandlerPolicy { timeout: 10, on_failure: FailurePolicy::Closed });
+        let backup = context.backup_message();
+        let result = sleeping.execute(&mut context, &sleeping.policy).await;
+        assert!(matches!(result, Err(ProcessorError::HandlerFailed { cause: HandlerFailure::Timeout, .. })));
+        // Message is dropped with the timed out handler.
+        assert!(context.message.is_none());
+        context.restore_message(backup);
+        assert!(context.message.as_ref().and_then(|message| message.downcast_ref::<ctos::Chat>()).is_some());
+    }
+
+    #[tokio::test]
+    async fn failed_handler_open_or_closed() {
+        let failing = || Handler::before_message::<ctos::Chat, _>(1, "test_failing", |_, _| Box::pin(async move { panic!("boom") }));
+        let rewriter = || Handler::before_message::<ctos::Chat, _>(100, "test_rewriter", |context, message| Box::pin(async move {
+            message.msg = cast_to_c_array("rewritten");
+            context.reserialize = true;
+            Ok(false)
+        }));
+
+        let mut processor = Processor::new(Direction::CTOS);
+        processor.add_handler(failing().with_policy(HandlerPolicy { timeout: 0, on_failure: FailurePolicy::Open }));
+        processor.add_handler(rewriter());
+        processor.prepare();
+        let sink = MemorySink::new();
+        processor.process_multiple_messages(&mut Some(sink.boxed()), addr(), &chat("hello")).await.unwrap();
+        assert_eq!(sink.take(), chat("rewritten"));
+
+        let mut processor = Processor::new(Direction::CTOS);
+        processor.add_handler(failing());
+        processor.add_handler(rewriter());
+        processor.prepare();
+        let (data, mut socket) = (chat("hello"), None);
+        let mut context = processor.generate_context(&mut socket, addr(), try_get_message_type(Direction::CTOS, data[2]), HandlerOccasion::Before, &data, None);
+        assert!(matches!(processor.process_handlers(&mut context).await, Err(ProcessorError::HandlerFailed { .. })));
+    }
+
+    #[tokio::test]
+    async fn failed_open_handler_keeps_game_message() {
+        use crate::ygopro::Netplayer;
+        use crate::ygopro::message::gm;
+        let damage = |value| wrap_mapped_struct(&stoc::GameMessage::from_child_message(gm::Damage { player: Netplayer::Player1, value }));
+        let mut processor = Processor::new(Direction::STOC);
+        processor.add_handler(Handler::before_message::<gm::Damage, _>(1, "test_rewriter", |context, message| Box::pin(async move {
+            message.value = 2000;
+            context.reserialize = true;
+            Ok(false)
+        })));
+        processor.add_handler(Handler::before_message::<gm::Damage, _>(2, "test_sleeping", |_, _| Box::pin(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            Ok(false)
+        })).with_policy(HandlerPolicy { timeout: 10, on_failure: FailurePolicy::Open }));
+        processor.add_handler(Handler::before_message::<gm::Damage, _>(3, "test_panicking", |_, _| Box::pin(async move { panic!("boom") }))
+            .with_policy(HandlerPolicy { timeout: 0, on_failure: FailurePolicy::Open }));
+        processor.prepare();
+        let sink = MemorySink::new();
+        processor.process_multiple_messages(&mut Some(sink.boxed()), addr(), &damage(1000)).await.unwrap();
+        assert_eq!(sink.take(), damage(2000));
+    }
+
+    fn room_with_flag(flag: &str) -> Arc<Mutex<crate::srvpru::Room>> {
+        Arc::new(Mutex::new(crate::srvpru::Room::for_test(crate::ygopro::message::HostInfo::default(), &[(flag, "true")])))
+    }
+
+    #[tokio::test]
+    async fn skip_handlers_out_of_selected_rooms() {
+        let mut processor = Processor::new(Direction::CTOS);
+        processor.add_handler(Handler::before_message::<ctos::Chat, _>(100, "test_ranked_only", |context, _| Box::pin(async move {
+            context.block_message()
+        })));
+        processor.restrict_to_rooms("test_ranked_only", Arc::new(serde_yaml::from_str("[ranked]").unwrap()));
+        processor.prepare();
+        let data = chat("hello");
+        for (room, blocked) in [(Some(room_with_flag("casual")), false), (Some(room_with_flag("ranked")), true), (None, true)] {
+            let mut socket = None;
+            let mut context = processor.generate_context(&mut socket, addr(), try_get_message_type(Direction::CTOS, data[2]), HandlerOccasion::Before, &data, None);
+            if let Some(room) = room { context.room.set(room).ok(); }
+            processor.process_handlers(&mut context).await.unwrap();
+            assert_eq!(context.block_message, blocked);
+        }
+    }
+
+    #[tokio::test]
+    async fn refuse_incomplete_frame() {
+        let processor = Processor::new(Direction::CTOS);
+        let sink = MemorySink::new();
+        let data = chat("hello");
+        let result = processor.process_multiple_messages(&mut Some(sink.boxed()), addr(), &data[..data.len() - 1]).await;
+        assert!(matches!(result, Err(ProcessorError::MessageLength)));
+        assert!(sink.take().is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+// ============================================================
+// proxy_protocol
+// ------------------------------------------------------------
+//! [PROXY protocol](https://www.haproxy.org/download/2.0/doc/proxy-protocol.txt)
+//! v1 and v2 header, sent by load balancers before client data,
+//! which carries the real client address.
+// ============================================================
+
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// Longest v1 header, with the ending `\r\n`.
+const V1_MAX_LENGTH: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+/// Signature, version and command, family, length.
+const V2_HEADER_LENGTH: usize = 16;
+
+/// Errors happen on parsing PROXY protocol header.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ProxyHeaderError {
+    #[error("Connection doesn't start with a PROXY protocol header.")]
+    Missing,
+    #[error("PROXY protocol header is malformed.")]
+    Malformed,
+    #[error("PROXY protocol v1 header is longer than 107 bytes.")]
+    Oversize
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  parse_header
+// ----------------------------------------------------------------------------------------------------
+/// Parse a v1 or v2 header at the beginning of `data`.
+///
+/// #### Return
+/// * `Ok(None)`: `data` is not a complete header yet.
+/// * `Ok(Some((source, length)))`: header takes `length` bytes. `source` is `None` if
+///   the connection is from balancer itself (`LOCAL`, `UNKNOWN` or non-inet address).
+// ----------------------------------------------------------------------------------------------------
+pub fn parse_header(data: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, ProxyHeaderError> {
+    if is_prefix(data, V2_SIGNATURE) { parse_v2(data) }
+    else if is_prefix(data, V1_PREFIX) { parse_v1(data) }
+    else { Err(ProxyHeaderError::Missing) }
+}
+
+/// Whether `data` and `prefix` agree on their common length.
+fn is_prefix(data: &[u8], prefix: &[u8]) -> bool {
+    let length = data.len().min(prefix.len());
+    data[..length] == prefix[..length]
+}
+
+fn parse_v1(data: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, ProxyHeaderError> {
+    let searched = &data[..data.len().min(V1_MAX_LENGTH)];
+    let end = match searched.windows(2).position(|window| window == b"\r\n") {
+        Some(end) => end,
+        None if data.len() >= V1_MAX_LENGTH => return Err(ProxyHeaderError::Oversize),
+        None => return Ok(None)
+    };
+    let line = std::str::from_utf8(&data[..end]).map_err(|_| ProxyHeaderError::Malformed)?;
+    let parts: Vec<&str> = line.split(' ').collect();
+    let source = match parts.get(1) {
+        Some(&"UNKNOWN") => None,
+        Some(&"TCP4") | Some(&"TCP6") if parts.len() == 6 => {
+            let ip: IpAddr = parts[2].parse().map_err(|_| ProxyHeaderError::Malformed)?;
+            let port: u16 = parts[4].parse().map_err(|_| ProxyHeaderError::Malformed)?;
+            Some(SocketAddr::new(ip, port))
+        },
+        _ => return Err(ProxyHeaderError::Malformed)
+    };
+    Ok(Some((source, end + 2)))
+}
+
+fn parse_v2(data: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, ProxyHeaderError> {
+    if data.len() < V2_HEADER_LENGTH { return Ok(None); }
+    let (version, command) = (data[12] >> 4, data[12] & 0x0F);
+    let family = data[13] >> 4;
+    let length = V2_HEADER_LENGTH + u16::from_be_bytes([data[14], data[15]]) as usize;
+    if version != 2 { return Err(ProxyHeaderError::Malformed); }
+    if data.len() < length { return Ok(None); }
+    let body = &data[V2_HEADER_LENGTH..length];
+    let source = match (command, family) {
+        // LOCAL, health checks of balancer.
+        (0, _) => None,
+        (1, 1) if body.len() >= 12 => {
+            let ip = Ipv4Addr::new(body[0], body[1], body[2], body[3]);
+            Some(SocketAddr::new(IpAddr::V4(ip), u16::from_be_bytes([body[8], body[9]])))
+        },
+        (1, 2) if body.len() >= 36 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&body[0..16]);
+            Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), u16::from_be_bytes([body[32], body[33]])))
+        },
+        (1, 1) | (1, 2) => return Err(ProxyHeaderError::Malformed),
+        // Unspecified or unix socket
+        (1, _) => None,
+        _ => return Err(ProxyHeaderError::Malformed)
+    };
+    Ok(Some((source, length)))
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  read_header
+// ----------------------------------------------------------------------------------------------------
+/// Read a header from `reader`.
+///
+/// #### Return
+/// Source address in header, and bytes read after header, which belongs to client.
+// ----------------------------------------------------------------------------------------------------
+pub async fn read_header<R: AsyncRead + Unpin>(reader: &mut R) -> anyhow::Result<(Option<SocketAddr>, Vec<u8>)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 256];
+    loop {
+        if let Some((source, length)) = parse_header(&buffer)? {
+            return Ok((source, buffer.split_off(length)));
+        }
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 { return Err(anyhow!("Connection closed before PROXY protocol header.")); }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_v1_header() {
+        let data = b"PROXY TCP4 10.0.0.1 10.0.0.2 51234 7911\r\n\x05\x00";
+        assert_eq!(parse_header(data), Ok(Some((Some("10.0.0.1:51234".parse().unwrap()), 41))));
+        assert_eq!(parse_header(b"PROXY TCP6 ::1 ::1 4000 7911\r\n"), Ok(Some((Some("[::1]:4000".parse().unwrap()), 30))));
+        assert_eq!(parse_header(b"PROXY UNKNOWN\r\n"), Ok(Some((None, 15))));
+        assert_eq!(parse_header(b"PROXY TCP4 10.0"), Ok(None));
+        assert_eq!(parse_header(b"PRO"), Ok(None));
+        assert_eq!(parse_header(b"PROXY TCP4 nonsense\r\n"), Err(ProxyHeaderError::Malformed));
+        assert_eq!(parse_header(&[b'P'; 5]), Err(ProxyHeaderError::Missing));
+        assert_eq!(parse_header(&[b' '; 200]), Err(ProxyHeaderError::Missing));
+    }
+
+    #[test]
+    fn parse_v2_header() {
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend([0x21, 0x11, 0x00, 0x0C, 10, 0, 0, 1, 10, 0, 0, 2, 0xC8, 0x22, 0x1E, 0xE7]);
+        assert_eq!(parse_header(&data[..20]), Ok(None));
+        data.push(0x05);
+        assert_eq!(parse_header(&data), Ok(Some((Some("10.0.0.1:51234".parse().unwrap()), 28))));
+
+        let mut local = V2_SIGNATURE.to_vec();
+        local.extend([0x20, 0x00, 0x00, 0x00]);
+        assert_eq!(parse_header(&local), Ok(Some((None, 16))));
+    }
+}
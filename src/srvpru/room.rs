@@ -6,7 +6,7 @@ use tokio::process::Command;
 use tokio::process::Child;
 use tokio::io::AsyncBufReadExt;
 use tokio::io::BufReader;
-use tokio::net::tcp::OwnedWriteHalf;
+use crate::srvpru::BoxedPacketSink;
 use tokio::task::JoinHandle;
 
 use parking_lot::RwLock;
@@ -209,7 +209,7 @@ impl Room {
     // ---------------------------------------------------------------------------------------------------- 
     /// Create a [`Player`] instance, and join that player to this room.
     // ----------------------------------------------------------------------------------------------------
-    pub async fn join(this: Arc<Mutex<Room>>, client_addr: SocketAddr, client_writer: OwnedWriteHalf) -> Option<()> {
+    pub async fn join(this: Arc<Mutex<Room>>, client_addr: SocketAddr, client_writer: BoxedPacketSink) -> Option<()> {
         let player = Player::new(&this, client_addr, client_writer).await.ok()?;
         let mut room = this.lock();
         info!("Player {} join room {}", player.lock().name, room.name);
@@ -287,6 +287,22 @@ impl Room {
         Ok(room)
     }
 
+    /// An established room without ygopro server, for tests.
+    #[cfg(test)]
+    pub(crate) fn for_test(host_info: HostInfo, flags: &[(&str, &str)]) -> Room {
+        Room {
+            host_info,
+            origin_name: String::new(),
+            name: String::new(),
+            status: RoomStatus::Established,
+            server_addr: None,
+            server_process: None,
+            server_stderr_hanlder: None,
+            players: Vec::new(),
+            flags: flags.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+        }
+    }
+
     pub fn exist(name: &String) -> bool {
         ROOMS.read().contains_key(name)
     }
@@ -313,11 +329,13 @@ impl Room {
 
     pub fn register_handlers() {
         Handler::before_message::<ctos::JoinGame, _>(10, "room_producer", |context, message| Box::pin(async move { 
+            if server::is_draining() { return context.refuse_join_game(Some("{server_shutting_down}")).await; }
             let password = context.get_string(&message.pass, "pass")?;
             let room = match Room::get_or_create_by_name(password).await {
                 Ok(room) => room,
                 Err(e) => {
                     error!("Failed to spawn room: {:}", e);
+                    crate::srvpru::metrics::record_spawn_failure();
                     return context.refuse_join_game(Some("{create_room_failed}")).await;
                 }
             };
@@ -548,62 +566,3 @@ macro_rules! export_room_attach_in_join_game_as {
         }
     }
 }
-
-#[derive(serde::Serialize, Debug)]
-#[serde(transparent)]
-pub struct RoomSelector {
-    flags: Vec<String>
-}
-
-impl Room {
-    pub fn match_selector(&self, selector: &RoomSelector) -> bool {
-        selector.flags.iter().any(|flag| self.flags.contains_key(flag))
-    }
-}
-
-struct RoomSelectorVisitor;
-impl<'de> serde::de::Visitor<'de> for RoomSelectorVisitor {
-    type Value = RoomSelector;
-
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("String, String Array or null")
-    }
-
-    
-    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> where E: serde::de::Error, {
-        Ok(RoomSelector { flags: vec![v.to_string()] })
-    }
-
-    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> where E: serde::de::Error, {
-        Ok(RoomSelector { flags: vec![v] })
-    }
-
-    fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> where E: serde::de::Error, {
-        Ok(RoomSelector { flags: vec![] })
-    }
-
-    fn visit_none<E>(self) -> Result<Self::Value, E> where E: serde::de::Error, {
-        Ok(RoomSelector { flags: vec![] })
-    }
-
-    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> where A: serde::de::SeqAccess<'de>, {
-        let mut flags = Vec::new();
-        while let Ok(Some(element)) = seq.next_element() {
-            flags.push(element);
-        }
-        Ok(RoomSelector { flags })
-    }
-
-}
-
-impl<'de> serde::de::Deserialize<'de> for RoomSelector {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
-        deserializer.deserialize_any(RoomSelectorVisitor)
-    }
-}
-
-impl std::default::Default for RoomSelector {
-    fn default() -> Self {
-        Self { flags: Vec::new() }
-    }
-}
\ No newline at end of file
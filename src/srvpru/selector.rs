@@ -0,0 +1,602 @@
+// ============================================================
+// selector
+// ------------------------------------------------------------
+//! [RoomSelector], a boolean expression on rooms.
+//!
+//! ```text
+//! mode == Match && flag(arena) == "athletic" && !flag(random_match)
+//! ```
+//!
+//! - `a && b`, `a || b`, `!a`, `(a)`, `true`, `false`.
+//! - `flag(name)` or a bare `name`: room has that flag.
+//! - `field <op> value`, `<op>` as one of `== != < <= > >=`:
+//!   * `mode`: `Single`, `Match` or `Tag`.
+//!   * `rule`, `time_limit`: numbers in [HostInfo](crate::ygopro::message::HostInfo).
+//!   * `lflist`: hash of lflist in [HostInfo](crate::ygopro::message::HostInfo), only `==` or `!=`.
+//!   * `stage`: [DuelStage](crate::srvpru::plugins::recorder::stage_recorder::DuelStage),
+//!     always `Void` without `stage_recorder`.
+//!   * `players`: count of players in room.
+//!   * `flag(name)`: value of a flag, a room without it is only `!=` anything.
+//!
+//! Values are numbers, quoted strings, or bare words. Names
+//! of `mode` and `stage` are case insensitive. Only `rule`, `time_limit`
+//! and `players` are compared as numbers, others as words.
+//!
+//! In configuration, a single word (even `mode`, `true` or `2v2`),
+//! or a string not parsed as an expression, is a flag name.
+// ============================================================
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::srvpru::Room;
+
+/// Errors happen on parsing a [RoomSelector].
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SelectorError {
+    #[error("Unexpected character {1:?} at {0}.")]
+    UnexpectedCharacter(usize, char),
+    #[error("Unterminated string at {0}.")]
+    UnterminatedString(usize),
+    #[error("Expected {0}, but found {1}.")]
+    Unexpected(&'static str, String),
+    #[error("Unknown room field {0}.")]
+    UnknownField(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Number(i64),
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+    Comparator(Comparator)
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{}", word),
+            Token::Text(text) => write!(f, "{:?}", text),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Not => write!(f, "!"),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::Comparator(comparator) => write!(f, "{}", comparator)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparator { Equal, NotEqual, Less, LessEqual, Greater, GreaterEqual }
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparator::Equal => "==",
+            Comparator::NotEqual => "!=",
+            Comparator::Less => "<",
+            Comparator::LessEqual => "<=",
+            Comparator::Greater => ">",
+            Comparator::GreaterEqual => ">="
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field { Mode, Rule, Lflist, TimeLimit, Stage, Players, Flag(String) }
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Mode => write!(f, "mode"),
+            Field::Rule => write!(f, "rule"),
+            Field::Lflist => write!(f, "lflist"),
+            Field::TimeLimit => write!(f, "time_limit"),
+            Field::Stage => write!(f, "stage"),
+            Field::Players => write!(f, "players"),
+            Field::Flag(name) => write!(f, "flag({})", Value::Text(name.clone()))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value { Number(i64), Text(String) }
+
+impl Value {
+    fn as_number(&self) -> Option<i64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Text(text) => text.parse().ok()
+        }
+    }
+
+    fn as_text(&self) -> String {
+        match self {
+            Value::Number(number) => number.to_string(),
+            Value::Text(text) => text.clone()
+        }
+    }
+
+    /// Compare as numbers if `self` is a number, or as words.
+    fn compare(&self, comparator: Comparator, other: &Value, case_insensitive: bool) -> bool {
+        let ordering = match (self, other.as_number()) {
+            (Value::Number(left), Some(right)) => left.cmp(&right),
+            _ => {
+                let (left, right) = (self.as_text(), other.as_text());
+                let equal = if case_insensitive { left.eq_ignore_ascii_case(&right) } else { left == right };
+                return match comparator {
+                    Comparator::Equal => equal,
+                    Comparator::NotEqual => !equal,
+                    // Words have no order.
+                    _ => false
+                };
+            }
+        };
+        match comparator {
+            Comparator::Equal => ordering.is_eq(),
+            Comparator::NotEqual => ordering.is_ne(),
+            Comparator::Less => ordering.is_lt(),
+            Comparator::LessEqual => ordering.is_le(),
+            Comparator::Greater => ordering.is_gt(),
+            Comparator::GreaterEqual => ordering.is_ge()
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(text) if is_word(text) => write!(f, "{}", text),
+            Value::Text(text) => write!(f, "{:?}", text)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Constant(bool),
+    HasFlag(String),
+    Compare(Field, Comparator, Value),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>)
+}
+
+impl Expression {
+    fn evaluate(&self, room: &Room) -> bool {
+        match self {
+            Expression::Constant(value) => *value,
+            Expression::HasFlag(name) => room.flags.contains_key(name),
+            Expression::Compare(field, comparator, value) => {
+                let (actual, case_insensitive) = match field {
+                    Field::Mode => (Value::Text(format!("{:?}", room.host_info.mode)), true),
+                    Field::Rule => (Value::Number(room.host_info.rule as i64), false),
+                    Field::Lflist => (Value::Text(room.host_info.lflist.to_string()), false),
+                    Field::TimeLimit => (Value::Number(room.host_info.time_limit as i64), false),
+                    Field::Stage => (Value::Text(format!("{:?}", room.get_duel_stage())), true),
+                    Field::Players => (Value::Number(room.players.len() as i64), false),
+                    Field::Flag(name) => match room.flags.get(name) {
+                        Some(flag) => (Value::Text(flag.clone()), false),
+                        None => return *comparator == Comparator::NotEqual
+                    }
+                };
+                actual.compare(*comparator, value, case_insensitive)
+            },
+            Expression::Not(expression) => !expression.evaluate(room),
+            Expression::And(left, right) => left.evaluate(room) && right.evaluate(room),
+            Expression::Or(left, right) => left.evaluate(room) || right.evaluate(room)
+        }
+    }
+
+    /// Binding strength, to decide parentheses when displayed.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Or(_, _) => 0,
+            Expression::And(_, _) => 1,
+            _ => 2
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence { write!(f, "({})", self) } else { write!(f, "{}", self) }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::HasFlag(name) => write!(f, "{}", Field::Flag(name.clone())),
+            Expression::Compare(field, comparator, value) => write!(f, "{} {} {}", field, comparator, value),
+            Expression::Not(expression) => { write!(f, "!")?; expression.fmt_operand(f, 2) },
+            Expression::And(left, right) => {
+                left.fmt_operand(f, 1)?;
+                write!(f, " && ")?;
+                right.fmt_operand(f, 2)
+            },
+            Expression::Or(left, right) => {
+                left.fmt_operand(f, 0)?;
+                write!(f, " || ")?;
+                right.fmt_operand(f, 1)
+            }
+        }
+    }
+}
+
+fn is_word_start(character: char) -> bool {
+    character.is_alphabetic() || character == '_'
+}
+
+fn is_word_part(character: char) -> bool {
+    character.is_alphanumeric() || matches!(character, '_' | '-' | '.')
+}
+
+fn is_word(text: &str) -> bool {
+    let mut characters = text.chars();
+    characters.next().is_some_and(is_word_start) && characters.all(is_word_part)
+        && !matches!(text, "true" | "false")
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, SelectorError> {
+    let mut tokens = Vec::new();
+    let mut characters: Peekable<CharIndices> = source.char_indices().peekable();
+    while let Some((position, character)) = characters.next() {
+        let mut next_is = |expected: char| characters.next_if(|(_, next)| *next == expected).is_some();
+        let token = match character {
+            _ if character.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '=' if next_is('=') => Token::Comparator(Comparator::Equal),
+            '!' if next_is('=') => Token::Comparator(Comparator::NotEqual),
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Comparator(Comparator::LessEqual),
+            '<' => Token::Comparator(Comparator::Less),
+            '>' if next_is('=') => Token::Comparator(Comparator::GreaterEqual),
+            '>' => Token::Comparator(Comparator::Greater),
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match characters.next() {
+                        Some((_, end)) if end == character => break,
+                        Some((_, '\\')) => match characters.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => return Err(SelectorError::UnterminatedString(position))
+                        },
+                        Some((_, part)) => text.push(part),
+                        None => return Err(SelectorError::UnterminatedString(position))
+                    }
+                }
+                Token::Text(text)
+            },
+            _ if character.is_ascii_digit() || character == '-' => {
+                let mut number = character.to_string();
+                while let Some((_, digit)) = characters.next_if(|(_, next)| next.is_ascii_digit()) { number.push(digit); }
+                Token::Number(number.parse().map_err(|_| SelectorError::UnexpectedCharacter(position, character))?)
+            },
+            _ if is_word_start(character) => {
+                let mut word = character.to_string();
+                while let Some((_, part)) = characters.next_if(|(_, next)| is_word_part(*next)) { word.push(part); }
+                Token::Word(word)
+            },
+            _ => return Err(SelectorError::UnexpectedCharacter(position, character))
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser, `||` binds looser than `&&`, then `!`.
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>
+}
+
+impl Parser {
+    fn parse(source: &str) -> Result<Expression, SelectorError> {
+        let mut parser = Parser { tokens: tokenize(source)?.into_iter().peekable() };
+        let expression = parser.parse_or()?;
+        match parser.tokens.next() {
+            None => Ok(expression),
+            Some(token) => Err(SelectorError::Unexpected("end of selector", token.to_string()))
+        }
+    }
+
+    fn expect(&mut self, expected: Token, description: &'static str) -> Result<(), SelectorError> {
+        match self.tokens.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(SelectorError::Unexpected(description, describe(other)))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, SelectorError> {
+        let mut expression = self.parse_and()?;
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, SelectorError> {
+        let mut expression = self.parse_not()?;
+        while self.tokens.next_if_eq(&Token::And).is_some() {
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_not()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, SelectorError> {
+        if self.tokens.next_if_eq(&Token::Not).is_some() { return Ok(Expression::Not(Box::new(self.parse_not()?))); }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, SelectorError> {
+        let word = match self.tokens.next() {
+            Some(Token::LeftParen) => {
+                let expression = self.parse_or()?;
+                self.expect(Token::RightParen, ")")?;
+                return Ok(expression);
+            },
+            Some(Token::Word(word)) => word,
+            other => return Err(SelectorError::Unexpected("a condition", describe(other)))
+        };
+        let field = match word.as_str() {
+            "true" => return Ok(Expression::Constant(true)),
+            "false" => return Ok(Expression::Constant(false)),
+            "flag" if self.tokens.peek() == Some(&Token::LeftParen) => {
+                self.tokens.next();
+                let name = match self.tokens.next() {
+                    Some(Token::Word(name)) | Some(Token::Text(name)) => name,
+                    other => return Err(SelectorError::Unexpected("a flag name", describe(other)))
+                };
+                self.expect(Token::RightParen, ")")?;
+                Some(Field::Flag(name))
+            },
+            "mode" => Some(Field::Mode),
+            "rule" => Some(Field::Rule),
+            "lflist" => Some(Field::Lflist),
+            "time_limit" => Some(Field::TimeLimit),
+            "stage" => Some(Field::Stage),
+            "players" => Some(Field::Players),
+            _ => None
+        };
+        let (comparator, field) = match (self.tokens.peek(), field) {
+            (Some(Token::Comparator(comparator)), Some(field)) => (*comparator, field),
+            (Some(Token::Comparator(_)), None) => return Err(SelectorError::UnknownField(word)),
+            // A flag alone checks if it exists, as a string selector always did.
+            (_, Some(Field::Flag(name))) => return Ok(Expression::HasFlag(name)),
+            (_, None) => return Ok(Expression::HasFlag(word)),
+            (_, Some(field)) => return Err(SelectorError::Unexpected("a comparator", format!("{} alone", field)))
+        };
+        self.tokens.next();
+        let value = match self.tokens.next() {
+            Some(Token::Number(number)) => Value::Number(number),
+            Some(Token::Text(text)) | Some(Token::Word(text)) => Value::Text(text),
+            other => return Err(SelectorError::Unexpected("a value", describe(other)))
+        };
+        Ok(Expression::Compare(field, comparator, value))
+    }
+}
+
+fn describe(token: Option<Token>) -> String {
+    token.map_or("end of selector".to_string(), |token| token.to_string())
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  parse_configured
+// ----------------------------------------------------------------------------------------------------
+/// Parse a string in configuration, which was always a flag name before expressions.
+///
+/// A single word, or a string failed to parse, is taken as a flag name.
+// ----------------------------------------------------------------------------------------------------
+fn parse_configured(source: &str) -> Expression {
+    let name = source.trim();
+    let is_single_word = !name.is_empty() && !name.contains(|character: char| character.is_whitespace() || "()!&|=<>\"'".contains(character));
+    if is_single_word { return Expression::HasFlag(name.to_string()); }
+    Parser::parse(source).unwrap_or_else(|e| {
+        warn!("Room selector {:?} is taken as a flag name: {}", source, e);
+        Expression::HasFlag(source.to_string())
+    })
+}
+
+// ============================================================
+//  RoomSelector
+// ------------------------------------------------------------
+/// Which rooms a plugin works in, see [module document](self).
+///
+/// Deserialized from:
+/// - a string: an expression, or a flag name, see [parse_configured].
+/// - an array of strings: any of them matches.
+/// - `null` or a bool: no room matches.
+// ============================================================
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomSelector {
+    expression: Expression
+}
+
+impl RoomSelector {
+    /// Parse an expression.
+    pub fn parse(source: &str) -> Result<RoomSelector, SelectorError> {
+        Ok(RoomSelector { expression: Parser::parse(source)? })
+    }
+
+    /// Whether `room` is selected.
+    pub fn matches(&self, room: &Room) -> bool {
+        self.expression.evaluate(room)
+    }
+}
+
+impl Room {
+    pub fn match_selector(&self, selector: &RoomSelector) -> bool {
+        selector.matches(self)
+    }
+}
+
+impl fmt::Display for RoomSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl std::str::FromStr for RoomSelector {
+    type Err = SelectorError;
+
+    fn from_str(source: &str) -> Result<RoomSelector, SelectorError> {
+        RoomSelector::parse(source)
+    }
+}
+
+impl std::default::Default for RoomSelector {
+    fn default() -> Self {
+        Self { expression: Expression::Constant(false) }
+    }
+}
+
+struct RoomSelectorVisitor;
+impl<'de> serde::de::Visitor<'de> for RoomSelectorVisitor {
+    type Value = RoomSelector;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("String, String Array or null")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> where E: serde::de::Error, {
+        Ok(RoomSelector { expression: parse_configured(v) })
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> where E: serde::de::Error, {
+        Ok(RoomSelector::default())
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> where E: serde::de::Error, {
+        Ok(RoomSelector::default())
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> where E: serde::de::Error, {
+        Ok(RoomSelector::default())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> where A: serde::de::SeqAccess<'de>, {
+        let mut selector: Option<Expression> = None;
+        while let Some(element) = seq.next_element::<String>()? {
+            let expression = parse_configured(&element);
+            selector = Some(match selector {
+                Some(former) => Expression::Or(Box::new(former), Box::new(expression)),
+                None => expression
+            });
+        }
+        Ok(selector.map_or_else(RoomSelector::default, |expression| RoomSelector { expression }))
+    }
+}
+
+impl<'de> serde::de::Deserialize<'de> for RoomSelector {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        deserializer.deserialize_any(RoomSelectorVisitor)
+    }
+}
+
+impl serde::Serialize for RoomSelector {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ygopro::message::HostInfo;
+
+    fn room(flags: &[(&str, &str)]) -> Room {
+        Room::for_test(HostInfo { mode: crate::ygopro::Mode::Match, time_limit: 180, ..HostInfo::default() }, flags)
+    }
+
+    fn matches(source: &str, room: &Room) -> bool {
+        RoomSelector::parse(source).unwrap().matches(room)
+    }
+
+    #[test]
+    fn evaluate_expressions() {
+        let athletic = room(&[("arena", "athletic")]);
+        let random = room(&[("arena", "athletic"), ("random_match", "true")]);
+        let selector = r#"mode == Match && flag(arena) == "athletic" && !flag(random_match)"#;
+        assert!(matches(selector, &athletic));
+        assert!(!matches(selector, &random));
+        assert!(matches("mode == match && time_limit >= 180 && players < 2", &athletic));
+        assert!(matches("stage == Void", &athletic));
+        assert!(matches("mode == Single || random_match", &random));
+        assert!(!matches("!(arena && random_match)", &random));
+        assert!(matches("flag(tournament) != 'swiss'", &athletic));
+        assert!(matches("lflist == 0 && time_limit == '180'", &athletic));
+        assert!(!matches("flag(tournament) == 'swiss'", &athletic));
+        assert!(!matches("false || (true && false)", &athletic));
+    }
+
+    #[test]
+    fn refuse_malformed_expressions() {
+        assert_eq!(RoomSelector::parse("mdoe == Match"), Err(SelectorError::UnknownField("mdoe".to_string())));
+        assert_eq!(RoomSelector::parse("flag(arena"), Err(SelectorError::Unexpected(")", "end of selector".to_string())));
+        assert_eq!(RoomSelector::parse("arena &&"), Err(SelectorError::Unexpected("a condition", "end of selector".to_string())));
+        assert_eq!(RoomSelector::parse("arena == 'a"), Err(SelectorError::UnterminatedString(9)));
+        assert_eq!(RoomSelector::parse("arena # b"), Err(SelectorError::UnexpectedCharacter(6, '#')));
+        assert!(RoomSelector::parse("mode").is_err());
+    }
+
+    #[test]
+    fn deserialize_former_forms() {
+        let ranked = room(&[("ranked", "true")]);
+        let selector: RoomSelector = serde_yaml::from_str("ranked").unwrap();
+        assert!(selector.matches(&ranked));
+        let selector: RoomSelector = serde_yaml::from_str("[casual, ranked]").unwrap();
+        assert!(selector.matches(&ranked));
+        assert!(!selector.matches(&room(&[])));
+        for empty in ["[]", "~", "true", "false"] {
+            let selector: RoomSelector = serde_yaml::from_str(empty).unwrap();
+            assert!(!selector.matches(&ranked));
+        }
+    }
+
+    #[test]
+    fn deserialize_words_as_flags() {
+        for name in ["mode", "rule", "lflist", "time_limit", "stage", "players", "true", "false", "2v2", "ranked &&"] {
+            let selector: RoomSelector = serde_yaml::from_str(&format!("'{}'", name)).unwrap();
+            assert!(selector.matches(&room(&[(name, "")])), "{}", name);
+            assert!(!selector.matches(&room(&[])), "{}", name);
+            let serialized: RoomSelector = serde_yaml::from_str(&serde_yaml::to_string(&selector).unwrap()).unwrap();
+            assert_eq!(serialized, selector);
+        }
+        let selector: RoomSelector = serde_yaml::from_str("[2v2, players >= 4]").unwrap();
+        assert!(selector.matches(&room(&[("2v2", "")])));
+        assert!(!selector.matches(&room(&[])));
+    }
+
+    #[test]
+    fn compare_words_of_flags() {
+        let season = room(&[("season", "01")]);
+        assert!(matches("flag(season) == '01'", &season));
+        assert!(!matches("flag(season) == 1", &season));
+        assert!(!matches("flag(season) < 2", &season));
+        let lflist = Room::for_test(HostInfo { lflist: 1, ..HostInfo::default() }, &[]);
+        assert!(matches("lflist == 1", &lflist));
+        assert!(!matches("lflist == '01'", &lflist));
+        assert!(!matches("lflist < 2", &lflist));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for source in ["(flag(a) || flag(b)) && !flag(c)", "flag(a) || flag(b) && flag(c)", "!(flag(a) && flag(b))", r#"flag("my flag") == "x y" || rule != 5"#] {
+            let selector = RoomSelector::parse(source).unwrap();
+            assert_eq!(selector.to_string(), source);
+            assert_eq!(RoomSelector::parse(&selector.to_string()).unwrap(), selector);
+        }
+        assert_eq!(serde_json::to_string(&RoomSelector::parse("a || b").unwrap()).unwrap(), r#""flag(a) || flag(b)""#);
+    }
+}
@@ -1,9 +1,15 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 
 use tokio::net::TcpListener;
+use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
+use tokio::sync::Notify;
+use tokio_rustls::TlsAcceptor;
 use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
 
 use crate::ygopro::message;
 use crate::ygopro::message::srvpru;
@@ -12,171 +18,415 @@ use crate::ygopro::message::MappedStruct;
 
 use crate::srvpru::processor::*;
 use crate::srvpru::player::*;
+use crate::srvpru::framer::MessageFramer;
+use crate::srvpru::framer::MAX_CLIENT_FRAME_LENGTH;
+use crate::srvpru::BoxedPacketSink;
+use crate::srvpru::metrics;
+use crate::srvpru::proxy_protocol;
+use crate::srvpru::tls;
+use crate::srvpru::tls::PrefixedStream;
+use crate::srvpru::tls::ShutdownOnDrop;
+use crate::ygopro::message::Direction;
 use crate::ygopro::message::srvpru::ServerStart;
+use crate::ygopro::message::srvpru::Shutdown;
+use crate::ygopro::message::srvpru::Reload;
+use crate::ygopro::Colors;
+use crate::srvpru::room::ROOMS;
+use crate::srvpru::generate_chat;
+use crate::srvpru::plugins;
 
 use super::message::SRVPRUProcessError;
 
 pub static SOCKET_SERVER: OnceCell<Server> = OnceCell::new();
 
+static DRAINING: AtomicBool = AtomicBool::new(false);
+/// Seconds to wait for killed rooms to be destroyed.
+const KILLED_ROOM_TIMEOUT: u64 = 5;
 
+lazy_static! {
+    static ref SHUTDOWN_REQUEST: Notify = Notify::new();
+}
+
+
+// ============================================================
+//  Server
+// ------------------------------------------------------------
+/// Socket server, owning processors of each direction. \
+/// Processors are snapshots, rebuilt and swapped when enabled
+/// plugins change. Messages in processing keep the snapshot
+/// they started with, so running rooms are never interrupted.
+// ============================================================
 pub struct Server {
-    pub stoc_processor: Processor,
-    pub ctos_processor: Processor,
-    internal_processor: Processor
+    stoc_processor: RwLock<Arc<Processor>>,
+    ctos_processor: RwLock<Arc<Processor>>,
+    internal_processor: RwLock<Arc<Processor>>
 }
 
+/// Handler reloading enabled plugins on [Reload], always registered.
+const PLUGIN_RELOADER: &str = "server_plugin_reloader";
+
 impl Server {
     pub fn new() -> Server {
         Server {
-            stoc_processor: Processor::new(message::Direction::STOC),
-            ctos_processor: Processor::new(message::Direction::CTOS),
-            internal_processor: Processor::new(message::Direction::SRVPRU)
+            stoc_processor: RwLock::new(Arc::new(Processor::new(message::Direction::STOC))),
+            ctos_processor: RwLock::new(Arc::new(Processor::new(message::Direction::CTOS))),
+            internal_processor: RwLock::new(Arc::new(Processor::new(message::Direction::SRVPRU)))
         }
     }
 
     pub fn init() -> anyhow::Result<()>{
-        let mut server = Server::new();
-        let config = crate::srvpru::get_configuration();
-        let plugins:           Vec<&'static str> = config.plugins          .iter().map(|s| s.as_str()).collect();
-        let ctos_handlers:     Vec<&'static str> = config.ctos_handlers    .iter().map(|s| s.as_str()).collect();
-        let stoc_handlers:     Vec<&'static str> = config.stoc_handlers    .iter().map(|s| s.as_str()).collect();
-        let internal_handlers: Vec<&'static str> = config.internal_handlers.iter().map(|s| s.as_str()).collect();
-        server.register_handlers(&plugins, &ctos_handlers, &stoc_handlers, &internal_handlers);
+        Server::register_reload_handler();
+        let server = Server::new();
+        server.rebuild_processors();
         info!("{:}", server);
         SOCKET_SERVER.set(server).ok().expect("Failed to set socket server.");
         Ok(())
     }
 
-    pub fn register_handlers(&mut self, plugins: &[&str], ctos_handlers: &[&str], stoc_handlers: &[&str], internal_handlers: &[&str]) {        
-        Server::register_directional_handlers("ctos",     ctos_handlers,     &mut self.ctos_processor);
-        Server::register_directional_handlers("stoc",     stoc_handlers,     &mut self.stoc_processor);
-        Server::register_directional_handlers("internal", internal_handlers, &mut self.internal_processor);
-        Server::check_plugin_dependency(plugins, ctos_handlers, stoc_handlers, internal_handlers);
-        self.register_plugin_handlers(plugins);
-        self.ctos_processor.prepare();
-        self.stoc_processor.prepare();
-        self.internal_processor.prepare();
+    /// Current `STOC` processor.
+    pub fn stoc_processor(&self) -> Arc<Processor> {
+        self.stoc_processor.read().clone()
+    }
+
+    /// Current `CTOS` processor.
+    pub fn ctos_processor(&self) -> Arc<Processor> {
+        self.ctos_processor.read().clone()
+    }
+
+    /// Current `SRVPRU` processor.
+    pub fn internal_processor(&self) -> Arc<Processor> {
+        self.internal_processor.read().clone()
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  set_plugins
+    // ----------------------------------------------------------------------------------------------------
+    /// Replace enabled plugins, and swap processors without restarting.
+    /// 
+    /// Only handlers follow. What plugins do in `init` or on [ServerStart], like registering api or
+    /// starting listeners, happens once when srvpru starts.
+    /// 
+    /// #### If error happens
+    /// Enabled plugins and processors are not changed, if `plugins` adds or removes a plugin without
+    /// handlers, or misses one of [REQUIRED_PLUGINS](plugins::REQUIRED_PLUGINS).
+    // ----------------------------------------------------------------------------------------------------
+    pub fn set_plugins(&self, plugins: Vec<String>) -> Result<(), plugins::PluginError> {
+        plugins::set_enabled_plugins(plugins)?;
+        self.rebuild_processors();
+        info!("Enabled plugins changed to {:?}.", plugins::enabled_plugins());
+        Ok(())
+    }
+
+    /// Enable or disable one plugin, see [set_plugins](Server::set_plugins).
+    pub fn set_plugin_enabled(&self, plugin: &str, enabled: bool) -> Result<(), plugins::PluginError> {
+        let mut plugins: Vec<String> = plugins::enabled_plugins().into_iter().filter(|enabled_plugin| enabled_plugin != plugin).collect();
+        if enabled { plugins.push(plugin.to_string()); }
+        self.set_plugins(plugins)
+    }
+
+    /// Build processors for enabled plugins, then swap them in.
+    fn rebuild_processors(&self) {
+        let config = crate::srvpru::get_configuration();
+        let enabled_plugins = plugins::enabled_plugins();
+        let plugins:           Vec<&str> = enabled_plugins         .iter().map(|s| s.as_str()).collect();
+        let ctos_handlers:     Vec<&str> = config.ctos_handlers    .iter().map(|s| s.as_str()).collect();
+        let stoc_handlers:     Vec<&str> = config.stoc_handlers    .iter().map(|s| s.as_str()).collect();
+        let mut internal_handlers: Vec<&str> = vec![PLUGIN_RELOADER];
+        internal_handlers.extend(config.internal_handlers.iter().map(|s| s.as_str()));
+        let mut ctos_processor = Processor::new(message::Direction::CTOS);
+        let mut stoc_processor = Processor::new(message::Direction::STOC);
+        let mut internal_processor = Processor::new(message::Direction::SRVPRU);
+        Server::register_directional_handlers("ctos",     &ctos_handlers,     &mut ctos_processor);
+        Server::register_directional_handlers("stoc",     &stoc_handlers,     &mut stoc_processor);
+        Server::register_directional_handlers("internal", &internal_handlers, &mut internal_processor);
+        Server::register_plugin_handlers(&plugins, &mut ctos_processor, &mut stoc_processor, &mut internal_processor);
+        ctos_processor.prepare();
+        stoc_processor.prepare();
+        internal_processor.prepare();
+        *self.ctos_processor.write() = Arc::new(ctos_processor);
+        *self.stoc_processor.write() = Arc::new(stoc_processor);
+        *self.internal_processor.write() = Arc::new(internal_processor);
     }
 
     fn register_directional_handlers(direction_name: &'static str, handler_names: &[&str], target_processor: &mut Processor) {
-        let mut handler_library = HANDLER_LIBRARY.write();
+        let configuration = crate::srvpru::get_configuration();
+        let handler_library = HANDLER_LIBRARY.read();
         for handler_name in handler_names.iter() {
-            if let Some(handler) = handler_library.remove(*handler_name) {
-                target_processor.add_handler(handler);
+            // A handler may be listed twice, by plugins and configuration.
+            if target_processor.contains_handler(handler_name) { continue; }
+            if let Some(handler) = handler_library.get(*handler_name) {
+                let mut policy = configuration.handler_policies.get(*handler_name).copied().unwrap_or_default();
+                if policy.timeout == 0 { policy.timeout = configuration.handler_timeout; }
+                target_processor.add_shared_handler(handler.clone(), Some(policy));
             }
             else { warn!("No {} processor named {}", direction_name, handler_name); }
         }
     }
 
-    fn register_plugin_handlers(&mut self, plugin_names: &[&str]) {
-        let mut handlers_library = HANDLER_LIBRARY_BY_PLUGIN.write();
+    fn register_plugin_handlers(plugin_names: &[&str], ctos_processor: &mut Processor, stoc_processor: &mut Processor, internal_processor: &mut Processor) {
+        let handlers_library = HANDLER_LIBRARY_BY_PLUGIN.read();
         for plugin_name in plugin_names.iter() {
-            if let Some(mut library) = handlers_library.remove(*plugin_name) {
-                if let Some(handlers) = library.remove(&message::Direction::CTOS)   { Server::register_directional_handlers("ctos",     &handlers.iter().map(|s| s as &str).collect::<Vec<&str>>(), &mut self.ctos_processor); }
-                if let Some(handlers) = library.remove(&message::Direction::STOC)   { Server::register_directional_handlers("stoc",     &handlers.iter().map(|s| s as &str).collect::<Vec<&str>>(), &mut self.stoc_processor); }
-                if let Some(handlers) = library.remove(&message::Direction::SRVPRU) { Server::register_directional_handlers("internal", &handlers.iter().map(|s| s as &str).collect::<Vec<&str>>(), &mut self.internal_processor); }
+            if let Some(library) = handlers_library.get(*plugin_name) {
+                if let Some(handlers) = library.get(&message::Direction::CTOS)   { Server::register_directional_handlers("ctos",     &handlers.iter().map(|s| s as &str).collect::<Vec<&str>>(), ctos_processor); }
+                if let Some(handlers) = library.get(&message::Direction::STOC)   { Server::register_directional_handlers("stoc",     &handlers.iter().map(|s| s as &str).collect::<Vec<&str>>(), stoc_processor); }
+                if let Some(handlers) = library.get(&message::Direction::SRVPRU) { Server::register_directional_handlers("internal", &handlers.iter().map(|s| s as &str).collect::<Vec<&str>>(), internal_processor); }
+                if let Some(selector) = plugins::load_room_selector(plugin_name) {
+                    let selector = Arc::new(selector);
+                    for (direction, handlers) in library.iter() {
+                        let processor = match direction {
+                            message::Direction::CTOS => &mut *ctos_processor,
+                            message::Direction::STOC => &mut *stoc_processor,
+                            message::Direction::SRVPRU => &mut *internal_processor
+                        };
+                        for handler in handlers.iter() { processor.restrict_to_rooms(handler, selector.clone()); }
+                    }
+                }
             }
             else { warn!("No plugin named {}", plugin_name); }
         }
     }
 
-    fn check_plugin_dependency(plugins: &[&str], ctos_handlers: &[&str], stoc_handlers: &[&str], internal_handlers: &[&str]) {
-        let mut handlers = plugins.to_vec();
-        handlers.extend(ctos_handlers.iter());
-        handlers.extend(stoc_handlers.iter());
-        handlers.extend(internal_handlers.iter());
-        let handler_depnedencies = HANDLER_DEPENDENCIES.read();
-        for handler in handlers.iter() {
-            if ! handler_depnedencies.contains_key(*handler) { continue; }
-            let dependencies = handler_depnedencies.get(*handler).unwrap();
-            for dependency in dependencies.iter() {
-                if !handlers.contains(dependency) {
-                    warn!("Plugin {} need plugin {} as dependency, but it's not registered.", handler, dependency);
-                }
-            }
-        }
+    /// Reload enabled plugins from `srvpru.yaml` on [Reload]. Other configurations need a restart.
+    fn register_reload_handler() {
+        Handler::before_message::<Reload, _>(255, PLUGIN_RELOADER, |_, _| Box::pin(async move {
+            let configuration = plugins::load_configuration::<crate::srvpru::Configuration>("srvpru")?;
+            get_server().set_plugins(configuration.plugins)?;
+            Ok(false)
+        })).register();
     }
 
-    /// Start socket server.
+    /// Start socket server, and tls one if configured.
     pub async fn start(&'static self) -> anyhow::Result<()> {
         let configuration = crate::srvpru::get_configuration();
         let listener = TcpListener::bind(format!("0.0.0.0:{}", configuration.port)).await?;
-        let timeout = tokio::time::Duration::from_secs(configuration.timeout);
+        let tls_listener = match configuration.tls.as_ref().and_then(|tls| tls.port.map(|port| (tls, port))) {
+            Some((tls, port)) => Some((TcpListener::bind(format!("0.0.0.0:{}", port)).await?, tls::load_acceptor(tls)?)),
+            None => None
+        };
         if trigger_internal(listener.local_addr()?, ServerStart {}).await? { return Ok(()) };
         info!("Socket server started.");
+        match tls_listener {
+            Some((tls_listener, acceptor)) => {
+                info!("Tls socket server started.");
+                tokio::try_join!(self.listen(listener, None), self.listen(tls_listener, Some(acceptor)))?;
+                Ok(())
+            },
+            None => self.listen(listener, None).await
+        }
+    }
+
+    /// Accept connections on `listener`, with a tls handshake if `acceptor` is given.
+    async fn listen(&'static self, listener: TcpListener, acceptor: Option<TlsAcceptor>) -> anyhow::Result<()> {
+        let configuration = crate::srvpru::get_configuration();
+        let timeout = tokio::time::Duration::from_secs(configuration.timeout);
         loop {
-            let (socket, addr) = listener.accept().await?;
-            let (mut reader, writer) = socket.into_split();
-            let mut writer = Some(writer);
-            let server = SOCKET_SERVER.get().expect("socket server not propered initialized");
+            let (mut socket, peer_addr) = listener.accept().await?;
+            let acceptor = acceptor.clone();
             tokio::spawn(async move {
-                let mut buf = [0; 10240];
-                loop {
-                    let data = match tokio::time::timeout(timeout, reader.read(&mut buf)).await {
-                        Ok(data) => data,
-                        Err(_) => {
-                            if Player::get_player(addr).map(|player| player.lock().timeout_exempt) == Some(true) { continue; }
-                            if self.trigger_internal(addr, srvpru::CtosListenError { error: ListenError::Timeout }).await.map_or(true, |block_message| !block_message) { 
-                                break; 
-                            } else { continue }
-                        }
-                    };
-                    let n = match data {
-                        Ok(n) if n == 0 => break,
-                        Ok(n) => n,
-                        Err(e) => {
-                            if self.trigger_internal(addr, srvpru::CtosListenError { error: ListenError::Drop(anyhow::Error::new(e)) }).await.map_or(true, |block_message| !block_message) {
-                                break;
-                            } else { continue }
-                        }
-                    };
-                    if n > 10240 { 
-                        if self.trigger_internal(addr, srvpru::CTOSProcessError { error: ProcessorError::Oversize }).await.map_or(true, |block_message| !block_message) {
-                            break;
-                        } else { continue } 
-                    }
-                    let result = if let Some(player) = Player::get_player(addr) {
-                        // Steal the socket, so that player won't be locked.
-                        let mut socket = player.lock().server_stream_writer.take();
-                        let res = server.ctos_processor.process_multiple_messages(&mut socket, addr, &buf[0..n]).await;
-                        // return the socket, player or socket both may disappear.
-                        if let (Some(player), Some(_socket)) = (Player::get_player(addr), socket) {
-                            player.lock().server_stream_writer.replace(_socket);
-                        }
-                        res
+                // Behind a balancer, real client address replaces the peer one everywhere.
+                let (addr, rest_data) = if configuration.proxy_protocol {
+                    match tokio::time::timeout(timeout, proxy_protocol::read_header(&mut socket)).await {
+                        Ok(Ok((source, rest_data))) => (source.unwrap_or(peer_addr), rest_data),
+                        Ok(Err(e)) => { warn!("Refused connection from {}: {}", peer_addr, e); return; },
+                        Err(_) => { warn!("Waiting for PROXY protocol header from {} timeout.", peer_addr); return; }
                     }
-                    else {
-                        server.ctos_processor.process_multiple_messages(&mut writer, addr, &buf[0..n]).await
-                    };
-                    // Some process happen an error
-                    if let Err(error) = result {
-                        let break_user = match error { ProcessorError::Abort | ProcessorError::ErrorAbort => true, _ => false };
-                        if break_user { Player::get_player(addr).map(|player| player.lock().expel()); break; }
-                    }
-                };
-                // Out of loop, Drop that player
-                {
-                    let player = { PLAYERS.write().remove(&addr) };
-                    if let Some(player) = player {
-                        self.trigger_internal(addr, srvpru::PlayerDestroy { player: player.clone() }).await.ok();
-                        if Arc::strong_count(&player) > 4 {
-                            let player = player.lock();
-                            warn!("Player {} seems still exist reference when drop. This may lead to memory leak.", player);
-                        }
+                }
+                else { (peer_addr, Vec::new()) };
+                match acceptor {
+                    None => {
+                        let (reader, writer) = socket.into_split();
+                        self.serve(std::io::Cursor::new(rest_data).chain(reader), Box::new(writer), addr).await;
+                    },
+                    Some(acceptor) => {
+                        let stream = match tokio::time::timeout(timeout, acceptor.accept(PrefixedStream::new(rest_data, socket))).await {
+                            Ok(Ok(stream)) => stream,
+                            Ok(Err(e)) => { warn!("Tls handshake with {} failed: {}", addr, e); return; },
+                            Err(_) => { warn!("Tls handshake with {} timeout.", addr); return; }
+                        };
+                        let (reader, writer) = tokio::io::split(stream);
+                        self.serve(reader, Box::new(ShutdownOnDrop::new(writer)), addr).await;
                     }
                 }
             });
         }
     }
 
+    // ----------------------------------------------------------------------------------------------------
+    //  serve
+    // ----------------------------------------------------------------------------------------------------
+    /// Process `CTOS` messages from a client connection, until it closes.
+    /// 
+    /// Transports other than tcp can join the same pipeline by a `reader` and a `writer` here.
+    /// 
+    /// #### Arguments
+    /// * `addr`: the client address, which is the key of its [Player].
+    // ----------------------------------------------------------------------------------------------------
+    pub async fn serve<R: AsyncRead + Send + Unpin>(&'static self, mut reader: R, writer: BoxedPacketSink, addr: SocketAddr) {
+        let timeout = tokio::time::Duration::from_secs(crate::srvpru::get_configuration().timeout);
+        let mut writer = Some(writer);
+        let mut buf = [0; 10240];
+        let mut framer = MessageFramer::with_max_length(MAX_CLIENT_FRAME_LENGTH);
+        loop {
+            let data = match tokio::time::timeout(timeout, reader.read(&mut buf)).await {
+                Ok(data) => data,
+                Err(_) => {
+                    if Player::get_player(addr).map(|player| player.lock().timeout_exempt) == Some(true) { continue; }
+                    metrics::record_listen_error(Direction::CTOS, &ListenError::Timeout);
+                    if self.trigger_internal(addr, srvpru::CtosListenError { error: ListenError::Timeout }).await.map_or(true, |block_message| !block_message) { 
+                        break; 
+                    } else { continue }
+                }
+            };
+            let n = match data {
+                Ok(n) if n == 0 => break,
+                Ok(n) => n,
+                Err(e) => {
+                    let error = ListenError::Drop(anyhow::Error::new(e));
+                    metrics::record_listen_error(Direction::CTOS, &error);
+                    if self.trigger_internal(addr, srvpru::CtosListenError { error }).await.map_or(true, |block_message| !block_message) {
+                        break;
+                    } else { continue }
+                }
+            };
+            if n > 10240 { 
+                metrics::record_processor_error(Direction::CTOS, &ProcessorError::Oversize);
+                if self.trigger_internal(addr, srvpru::CTOSProcessError { error: ProcessorError::Oversize }).await.map_or(true, |block_message| !block_message) {
+                    break;
+                } else { continue } 
+            }
+            // Only complete frames go to processor, rest wait for next read.
+            let frames = match framer.feed(&buf[0..n]) {
+                Ok(Some(frames)) => frames,
+                Ok(None) => continue,
+                Err(error) => {
+                    metrics::record_processor_error(Direction::CTOS, &error);
+                    if self.trigger_internal(addr, srvpru::CTOSProcessError { error }).await.map_or(true, |block_message| !block_message) {
+                        break;
+                    } else { continue }
+                }
+            };
+            let result = if let Some(player) = Player::get_player(addr) {
+                // Steal the socket, so that player won't be locked.
+                let mut socket = player.lock().server_stream_writer.take();
+                let res = self.ctos_processor().process_multiple_messages(&mut socket, addr, &frames).await;
+                // return the socket, player or socket both may disappear.
+                if let (Some(player), Some(_socket)) = (Player::get_player(addr), socket) {
+                    player.lock().server_stream_writer.replace(_socket);
+                }
+                res
+            }
+            else {
+                self.ctos_processor().process_multiple_messages(&mut writer, addr, &frames).await
+            };
+            // Some process happen an error
+            if let Err(error) = result {
+                metrics::record_processor_error(Direction::CTOS, &error);
+                let break_user = match error { ProcessorError::Abort | ProcessorError::ErrorAbort => true, _ => false };
+                if break_user { Player::get_player(addr).map(|player| player.lock().expel()); break; }
+            }
+        };
+        // Out of loop, Drop that player
+        {
+            let player = { PLAYERS.write().remove(&addr) };
+            if let Some(player) = player {
+                self.trigger_internal(addr, srvpru::PlayerDestroy { player: player.clone() }).await.ok();
+                if Arc::strong_count(&player) > 4 {
+                    let player = player.lock();
+                    warn!("Player {} seems still exist reference when drop. This may lead to memory leak.", player);
+                }
+            }
+        }
+    }
+
     async fn trigger_internal<S: Struct + MappedStruct>(&'static self, addr: SocketAddr, obj: S) -> anyhow::Result<bool> {
-        match self.internal_processor.process_internal_message(addr, obj).await {
+        let processor = self.internal_processor();
+        match processor.process_internal_message(addr, obj).await {
             Ok(block_message) => Ok(block_message),
-            Err(error) => Ok(!self.internal_processor.process_internal_message(addr, SRVPRUProcessError { error }).await?)
+            Err(error) => Ok(!processor.process_internal_message(addr, SRVPRUProcessError { error }).await?)
+        }
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  shutdown
+    // ----------------------------------------------------------------------------------------------------
+    /// Drain the server, for a graceful exit.
+    /// 
+    /// - refuse new `JoinGame` (see [is_draining]).
+    /// - warn all players by chat.
+    /// - wait for rooms to end, in `drain_timeout` seconds.
+    /// - kill ygopro servers of rooms still remain, and wait for their `RoomDestroy`.
+    /// - trigger [Shutdown], so plugins can flush their states.
+    /// 
+    /// Socket server keeps running, it's up to caller to exit.
+    // ----------------------------------------------------------------------------------------------------
+    pub async fn shutdown(&'static self) {
+        let configuration = crate::srvpru::get_configuration();
+        DRAINING.store(true, Ordering::Relaxed);
+        info!("Srvpru is shutting down, draining {} rooms.", ROOMS.read().len());
+        Server::broadcast_shutdown_warning().await;
+
+        Server::wait_for_rooms(configuration.drain_timeout).await;
+        let processes: Vec<_> = ROOMS.read().values().filter_map(|room| room.lock().server_process.take()).collect();
+        if !processes.is_empty() { warn!("Killing {} rooms not ended in time.", processes.len()); }
+        for mut process in processes {
+            process.kill().await.ok();
+        }
+        Server::wait_for_rooms(KILLED_ROOM_TIMEOUT).await;
+
+        let addr = SocketAddr::from(([0, 0, 0, 0], configuration.port));
+        self.trigger_internal(addr, Shutdown).await.ok();
+        info!("Srvpru shut down.");
+    }
+
+    /// Wait until all rooms are destroyed, at most `seconds`.
+    async fn wait_for_rooms(seconds: u64) {
+        let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(seconds);
+        while !ROOMS.read().is_empty() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+    }
+
+    async fn broadcast_shutdown_warning() {
+        let players: Vec<_> = ROOMS.read().values().flat_map(|room| room.lock().players.clone()).collect();
+        for player in players {
+            // Take the socket, so that player won't be locked in sending.
+            let (socket, region) = { let mut player = player.lock(); (player.client_stream_writer.take(), player.region) };
+            if let Some(mut socket) = socket {
+                crate::srvpru::send(&mut socket, &generate_chat("{server_shutting_down}", Colors::Red, region)).await.ok();
+                player.lock().client_stream_writer.replace(socket);
+            }
         }
     }
 }
 
+/// If server is shutting down. New `JoinGame` should be refused.
+pub fn is_draining() -> bool {
+    DRAINING.load(Ordering::Relaxed)
+}
+
+/// Ask [start](crate::start) to shut down, as if a SIGTERM is received.
+pub fn request_shutdown() {
+    SHUTDOWN_REQUEST.notify_one();
+}
+
+/// Wait for SIGTERM, SIGINT, or [request_shutdown].
+pub async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => { terminate.recv().await; },
+            Err(e) => { warn!("Cannot listen to SIGTERM: {}", e); std::future::pending::<()>().await; }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = terminate => info!("Received SIGTERM."),
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT."),
+        _ = SHUTDOWN_REQUEST.notified() => info!("Shutdown requested.")
+    }
+}
+
 
 // ----------------------------------------------------------------------------------------------------
 //  trigger_internal
@@ -198,11 +448,11 @@ impl std::fmt::Debug for Server {
         writeln!(f, "")?;
         writeln!(f, "srvpru socket server")?;
         writeln!(f, "  CTOS processors:")?;
-        write!(f, "{:<4?}", self.ctos_processor)?;
+        write!(f, "{:<4?}", *self.ctos_processor())?;
         writeln!(f, "  STOC processors:")?;
-        write!(f, "{:<4?}", self.stoc_processor)?;
+        write!(f, "{:<4?}", *self.stoc_processor())?;
         writeln!(f, "  INTERNAL processors:")?;
-        write!(f, "{:<4?}", self.internal_processor)?;
+        write!(f, "{:<4?}", *self.internal_processor())?;
         Ok(())
     }
 }
@@ -212,13 +462,26 @@ impl std::fmt::Display for Server {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "")?;
         writeln!(f, "srvpru socket server")?;
-        write!(f, "{:<4}", self.ctos_processor)?;
-        write!(f, "{:<4}", self.stoc_processor)?;
-        write!(f, "{:<4}", self.internal_processor)?;
+        write!(f, "{:<4}", *self.ctos_processor())?;
+        write!(f, "{:<4}", *self.stoc_processor())?;
+        write!(f, "{:<4}", *self.internal_processor())?;
         Ok(())
     }
 }
 
+/// Trigger a process error event of `direction` in background, ignoring whether it's blocked.
+pub fn report_processor_error(direction: Direction, addr: SocketAddr, error: ProcessorError) {
+    // Processors running without server, such as in tests, have nobody to report to.
+    let server = match SOCKET_SERVER.get() { Some(server) => server, None => return };
+    tokio::spawn(async move {
+        match direction {
+            Direction::STOC =>   server.trigger_internal(addr, srvpru::STOCProcessError   { error }).await,
+            Direction::CTOS =>   server.trigger_internal(addr, srvpru::CTOSProcessError   { error }).await,
+            Direction::SRVPRU => server.trigger_internal(addr, srvpru::SRVPRUProcessError { error }).await,
+        }.ok();
+    });
+}
+
 /// Get current socket server.
 pub fn get_server() -> &'static Server {
     SOCKET_SERVER.get().expect("Socket server not set")
@@ -0,0 +1,67 @@
+// ============================================================
+// sink
+// ------------------------------------------------------------
+//! Where packets are written to, a client or a ygopro server.
+// ============================================================
+
+use std::fmt::Debug;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+
+/// Anything packets can be written to. \
+/// Tcp sockets, bridges of other transports, or in-memory buffers.
+pub trait PacketSink: AsyncWrite + Debug + Send + Sync + Unpin {}
+
+impl<T: AsyncWrite + Debug + Send + Sync + Unpin> PacketSink for T {}
+
+/// Type of [Player](crate::srvpru::Player) and [Context](crate::srvpru::Context) sockets.
+pub type BoxedPacketSink = Box<dyn PacketSink>;
+
+// ============================================================
+//  MemorySink
+// ------------------------------------------------------------
+/// A [PacketSink] keeping all written bytes. \
+/// Clones share the buffer, so one can be given to a [Player](crate::srvpru::Player)
+/// or [Processor](crate::srvpru::Processor) while another checks what is sent.
+// ============================================================
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    buffer: Arc<Mutex<Vec<u8>>>
+}
+
+impl MemorySink {
+    pub fn new() -> MemorySink {
+        MemorySink::default()
+    }
+
+    /// A boxed clone, sharing the buffer.
+    pub fn boxed(&self) -> BoxedPacketSink {
+        Box::new(self.clone())
+    }
+
+    /// Take out all bytes written since last take.
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.buffer.lock())
+    }
+}
+
+impl AsyncWrite for MemorySink {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.buffer.lock().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
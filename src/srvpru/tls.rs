@@ -0,0 +1,232 @@
+// ============================================================
+// tls
+// ------------------------------------------------------------
+//! Tls termination for game port and api, by rustls.
+//!
+//! Tls listeners are extra ones, plain listeners keep running
+//! beside them. See [TlsConfiguration].
+// ============================================================
+
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use axum::extract::connect_info::Connected;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::io::ReadBuf;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::NoClientAuth;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::internal::pemfile;
+use tokio_rustls::server::TlsStream;
+
+/// Wait before accepting again, after an accept failed.
+const ACCEPT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tls listeners, in `tls` of `srvpru.yaml`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct TlsConfiguration {
+    /// PEM certificate chain path.
+    pub certificate: String,
+    /// PEM private key path, PKCS#8 or RSA.
+    pub key: String,
+    /// Port accepting ygopro clients over tls. Not listening if omitted.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Port serving api over tls, if `api` plugin is enabled. Not listening if omitted.
+    #[serde(default)]
+    pub api_port: Option<u16>
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  load_acceptor
+// ----------------------------------------------------------------------------------------------------
+/// Read certificate and key in `configuration`.
+// ----------------------------------------------------------------------------------------------------
+pub fn load_acceptor(configuration: &TlsConfiguration) -> anyhow::Result<TlsAcceptor> {
+    let certificates = pemfile::certs(&mut BufReader::new(File::open(&configuration.certificate)?))
+        .map_err(|_| anyhow!("Failed to read certificate {}", configuration.certificate))?;
+    let key = load_key(&configuration.key)?;
+    let mut server_configuration = ServerConfig::new(NoClientAuth::new());
+    server_configuration.set_single_cert(certificates, key)?;
+    Ok(TlsAcceptor::from(Arc::new(server_configuration)))
+}
+
+fn load_key(path: &str) -> anyhow::Result<tokio_rustls::rustls::PrivateKey> {
+    let pkcs8_keys = pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(path)?)).unwrap_or_default();
+    let rsa_keys = pemfile::rsa_private_keys(&mut BufReader::new(File::open(path)?)).unwrap_or_default();
+    pkcs8_keys.into_iter().chain(rsa_keys).next().ok_or_else(|| anyhow!("No private key found in {}", path))
+}
+
+// ============================================================
+//  PrefixedStream
+// ------------------------------------------------------------
+/// A stream reading `prefix` first. \
+/// Bytes read ahead, such as after a PROXY protocol header, are
+/// put back this way, before tls handshake.
+// ============================================================
+#[derive(Debug)]
+pub struct PrefixedStream<S> {
+    prefix: io::Cursor<Vec<u8>>,
+    inner: S
+}
+
+impl<S> PrefixedStream<S> {
+    pub fn new(prefix: Vec<u8>, inner: S) -> PrefixedStream<S> {
+        PrefixedStream { prefix: io::Cursor::new(prefix), inner }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for PrefixedStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let position = self.prefix.position() as usize;
+        let rest = &self.prefix.get_ref()[position..];
+        if rest.is_empty() { return Pin::new(&mut self.inner).poll_read(cx, buf); }
+        let length = rest.len().min(buf.remaining());
+        buf.put_slice(&rest[..length]);
+        self.prefix.set_position((position + length) as u64);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for PrefixedStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+// ============================================================
+//  ShutdownOnDrop
+// ------------------------------------------------------------
+/// Writer shutting down when dropped. \
+/// A tls write half won't close the connection by itself, but
+/// [expel](crate::srvpru::Player::expel) relies on it, as a tcp one.
+// ============================================================
+#[derive(Debug)]
+pub struct ShutdownOnDrop<W: AsyncWrite + Send + Unpin + 'static> {
+    inner: Option<W>
+}
+
+impl<W: AsyncWrite + Send + Unpin + 'static> ShutdownOnDrop<W> {
+    pub fn new(inner: W) -> ShutdownOnDrop<W> {
+        ShutdownOnDrop { inner: Some(inner) }
+    }
+
+    fn inner(&mut self) -> Pin<&mut W> {
+        Pin::new(self.inner.as_mut().expect("Writer taken before drop"))
+    }
+}
+
+impl<W: AsyncWrite + Send + Unpin + 'static> AsyncWrite for ShutdownOnDrop<W> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.inner().poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner().poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner().poll_shutdown(cx)
+    }
+}
+
+impl<W: AsyncWrite + Send + Unpin + 'static> Drop for ShutdownOnDrop<W> {
+    fn drop(&mut self) {
+        if let Some(mut inner) = self.inner.take() {
+            tokio::spawn(async move { inner.shutdown().await.ok(); });
+        }
+    }
+}
+
+// ============================================================
+//  TlsConnection
+// ------------------------------------------------------------
+/// An accepted tls connection of api, which tells its remote
+/// address to `ConnectInfo`.
+// ============================================================
+pub struct TlsConnection {
+    stream: TlsStream<TcpStream>,
+    remote_addr: SocketAddr
+}
+
+impl Connected<&TlsConnection> for SocketAddr {
+    fn connect_info(target: &TlsConnection) -> Self {
+        target.remote_addr
+    }
+}
+
+impl AsyncRead for TlsConnection {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConnection {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  accept_connections
+// ----------------------------------------------------------------------------------------------------
+/// Accept tls connections on `listener`, for `hyper::Server::builder`. \
+/// Handshakes run concurrently, so a slow client won't hold back others,
+/// and one not finished in `handshake_timeout` is dropped.
+/// 
+/// Failed accepts, such as running out of file descriptors, are logged
+/// and retried later, so the listener keeps running.
+// ----------------------------------------------------------------------------------------------------
+pub fn accept_connections(listener: TcpListener, acceptor: TlsAcceptor, handshake_timeout: Duration) -> impl hyper::server::accept::Accept<Conn = TlsConnection, Error = io::Error> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (socket, remote_addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Failed to accept tls connection: {}", e);
+                    tokio::time::sleep(ACCEPT_RETRY_INTERVAL).await;
+                    continue;
+                }
+            };
+            let (acceptor, connection_sender) = (acceptor.clone(), sender.clone());
+            tokio::spawn(async move {
+                match tokio::time::timeout(handshake_timeout, acceptor.accept(socket)).await {
+                    Ok(Ok(stream)) => { connection_sender.send(Ok(TlsConnection { stream, remote_addr })).ok(); },
+                    Ok(Err(e)) => debug!("Tls handshake with {} failed: {}", remote_addr, e),
+                    Err(_) => debug!("Tls handshake with {} timeout.", remote_addr)
+                }
+            });
+            if sender.is_closed() { break; }
+        }
+    });
+    hyper::server::accept::from_stream(futures_util::stream::poll_fn(move |cx| receiver.poll_recv(cx)))
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use parking_lot::Mutex;
 use srvpru::PlayerDestroy;
 use tokio::io::AsyncWriteExt;
-use tokio::net::tcp::OwnedWriteHalf;
+use tokio::io::AsyncWrite;
 use anyhow::Result;
 
 use crate::ygopro::Colors;
@@ -39,12 +39,12 @@ pub enum CommonError {
 }
 
 /// Send a struct to target socket.
-pub async fn send<T: Struct + MappedStruct + serde::Serialize>(socket: &mut OwnedWriteHalf, obj: &T) -> Result<()> {
+pub async fn send<T: Struct + MappedStruct + serde::Serialize>(socket: &mut (impl AsyncWrite + Unpin + ?Sized), obj: &T) -> Result<()> {
     send_raw_data(socket, T::message(), &(bincode::serialize(&obj)?)).await
 }
 
 /// Send data to target socket, adding a length header and type header.
-pub async fn send_raw_data(socket: &mut OwnedWriteHalf, message_type: MessageType, data: &[u8]) -> Result<()> {
+pub async fn send_raw_data(socket: &mut (impl AsyncWrite + Unpin + ?Sized), message_type: MessageType, data: &[u8]) -> Result<()> {
     socket.write_all(&wrap_data(message_type, data)).await?;
     Ok(())
 }
@@ -2,3 +2,5 @@ mod constants;
 pub use constants::*;
 #[macro_use] pub mod message;
 pub mod data;
+pub mod duel_log;
+pub mod client;
@@ -0,0 +1,238 @@
+// ============================================================
+// client
+// ------------------------------------------------------------
+//! Headless ygopro client, for telescreens, bots, tests and
+//! load generation.
+//!
+//! ```ignore
+//! let mut client = YgoClient::connect(addr).player_info("alice").join("M#room").await?;
+//! client.send(&ctos::HsReady).await?;
+//! let change = client.expect::<stoc::HsPlayerChange, _>(|_| true).await;
+//! ```
+//!
+//! Incoming messages are a [Stream] of [StocMessage], read by a
+//! background task, so a slow consumer never blocks the socket.
+// ============================================================
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use futures_util::FutureExt;
+use futures_util::Stream;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::srvpru::MessageFramer;
+use crate::ygopro::message::Direction;
+use crate::ygopro::message::MappedStruct;
+use crate::ygopro::message::MessageType;
+use crate::ygopro::message::Struct;
+use crate::ygopro::message::ctos;
+use crate::ygopro::message::deserialize_struct_by_type;
+use crate::ygopro::message::generate::wrap_mapped_struct;
+use crate::ygopro::message::string::cast_to_fix_length_array;
+use crate::ygopro::message::try_get_message_type;
+
+/// Version sent in `JoinGame` if not specified.
+pub const DEFAULT_VERSION: u16 = 0x1360;
+
+// ============================================================
+//  StocMessage
+// ------------------------------------------------------------
+/// A message received from server.
+// ============================================================
+#[derive(Debug)]
+pub struct StocMessage {
+    /// `None` for unknown type.
+    pub message_type: Option<MessageType>,
+    /// Whole frame, with length and type header.
+    pub raw: Vec<u8>,
+    /// `None` if type is unknown or data can't be deserialized.
+    pub message: Option<Box<dyn Struct>>
+}
+
+impl StocMessage {
+    fn from_frame(frame: &[u8]) -> StocMessage {
+        let message_type = try_get_message_type(Direction::STOC, frame[2]);
+        let message = message_type.and_then(|message_type| deserialize_struct_by_type(message_type, &frame[3..]));
+        StocMessage { message_type, raw: frame.to_vec(), message }
+    }
+
+    /// If it's a `T`.
+    pub fn is<T: MappedStruct>(&self) -> bool {
+        self.message_type == Some(T::message())
+    }
+
+    /// Cast to `T`.
+    pub fn downcast<T: MappedStruct>(&self) -> Option<&T> {
+        self.message.as_ref()?.downcast_ref::<T>()
+    }
+
+    /// Take out as `T`.
+    pub fn into_struct<T: MappedStruct>(self) -> Option<T> {
+        self.message?.downcast::<T>().ok().map(|message| *message)
+    }
+}
+
+// ============================================================
+//  YgoClientBuilder
+// ------------------------------------------------------------
+/// Decide how a [YgoClient] join a room.
+// ============================================================
+#[derive(Debug, Clone)]
+pub struct YgoClientBuilder {
+    addr: SocketAddr,
+    name: String,
+    version: u16,
+    observe: bool
+}
+
+impl YgoClientBuilder {
+    pub fn player_info(mut self, name: &str) -> YgoClientBuilder {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn version(mut self, version: u16) -> YgoClientBuilder {
+        self.version = version;
+        self
+    }
+
+    /// Move to observers right after join.
+    pub fn observe(mut self) -> YgoClientBuilder {
+        self.observe = true;
+        self
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  join
+    // ----------------------------------------------------------------------------------------------------
+    /// Connect, and send `PlayerInfo` and `JoinGame` in one write.
+    ///
+    /// It won't wait for any reply, a refused join shows as `ErrorMessage` in stream.
+    // ----------------------------------------------------------------------------------------------------
+    pub async fn join(self, pass: &str) -> anyhow::Result<YgoClient> {
+        let mut client = YgoClient::open(self.addr).await?;
+        let mut data = wrap_mapped_struct(&ctos::PlayerInfo { name: cast_to_fix_length_array(&self.name) });
+        data.extend(wrap_mapped_struct(&ctos::JoinGame { version: self.version, align: 0, gameid: 0, pass: cast_to_fix_length_array(pass) }));
+        if self.observe { data.extend(wrap_mapped_struct(&ctos::HsToOBServer)); }
+        client.send_raw(&data).await?;
+        Ok(client)
+    }
+}
+
+// ============================================================
+//  YgoClient
+// ------------------------------------------------------------
+/// A connection to ygopro server, or srvpru.
+// ============================================================
+pub struct YgoClient {
+    writer: OwnedWriteHalf,
+    receiver: mpsc::UnboundedReceiver<StocMessage>,
+    reader: JoinHandle<()>,
+    /// Skipped by [expect](YgoClient::expect), will be received first.
+    pending: VecDeque<StocMessage>
+}
+
+impl YgoClient {
+    /// Start to build a client for `addr`.
+    pub fn connect(addr: SocketAddr) -> YgoClientBuilder {
+        YgoClientBuilder { addr, name: String::new(), version: DEFAULT_VERSION, observe: false }
+    }
+
+    /// Connect without sending anything.
+    pub async fn open(addr: SocketAddr) -> anyhow::Result<YgoClient> {
+        let (mut reader, writer) = TcpStream::connect(addr).await?.into_split();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let reader = tokio::spawn(async move {
+            let mut buf = [0; 10240];
+            let mut framer = MessageFramer::new();
+            loop {
+                let n = match reader.read(&mut buf).await {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) => { debug!("Ygo client on {} closed: {}", addr, e); break; }
+                };
+                let frames = match framer.feed(&buf[0..n]) {
+                    Ok(Some(frames)) => frames,
+                    Ok(None) => continue,
+                    Err(e) => { warn!("Ygo client on {} received broken frame: {}", addr, e); break; }
+                };
+                let mut rest = &frames[..];
+                while rest.len() >= 3 {
+                    let length = 2 + u16::from_le_bytes([rest[0], rest[1]]) as usize;
+                    if sender.send(StocMessage::from_frame(&rest[..length])).is_err() { return; }
+                    rest = &rest[length..];
+                }
+            }
+        });
+        Ok(YgoClient { writer, receiver, reader, pending: VecDeque::new() })
+    }
+
+    pub async fn send<T: Struct + MappedStruct + serde::Serialize>(&mut self, obj: &T) -> anyhow::Result<()> {
+        self.send_raw(&wrap_mapped_struct(obj)).await
+    }
+
+    /// Send frames which already have length and type header.
+    pub async fn send_raw(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        self.writer.write_all(data).await?;
+        Ok(())
+    }
+
+    /// Next message, `None` if connection closed.
+    pub async fn recv(&mut self) -> Option<StocMessage> {
+        match self.pending.pop_front() {
+            Some(message) => Some(message),
+            None => self.receiver.recv().await
+        }
+    }
+
+    /// Next message if already received, without waiting.
+    pub fn try_recv(&mut self) -> Option<StocMessage> {
+        self.pending.pop_front().or_else(|| self.receiver.recv().now_or_never().flatten())
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  expect
+    // ----------------------------------------------------------------------------------------------------
+    /// Wait for a `T` matching `filter`.
+    /// Other messages are kept, and still come out of [recv](YgoClient::recv) in order.
+    ///
+    /// #### Return
+    /// `None` if connection closed before that.
+    // ----------------------------------------------------------------------------------------------------
+    pub async fn expect<T, F>(&mut self, filter: F) -> Option<T>
+        where T: MappedStruct, F: Fn(&T) -> bool {
+        let position = self.pending.iter().position(|message| message.downcast::<T>().is_some_and(&filter));
+        if let Some(position) = position { return self.pending.remove(position)?.into_struct(); }
+        while let Some(message) = self.receiver.recv().await {
+            if message.downcast::<T>().is_some_and(&filter) { return message.into_struct(); }
+            self.pending.push_back(message);
+        }
+        None
+    }
+}
+
+impl Stream for YgoClient {
+    type Item = StocMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<StocMessage>> {
+        match self.pending.pop_front() {
+            Some(message) => Poll::Ready(Some(message)),
+            None => self.receiver.poll_recv(cx)
+        }
+    }
+}
+
+impl Drop for YgoClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
@@ -4,9 +4,13 @@ use std::collections::HashMap;
 use std::io::BufRead;
 use std::io::Cursor;
 use std::io::Read;
+use std::io::Write;
+use std::sync::Arc;
 
 use byteorder::LittleEndian;
 use byteorder::ReadBytesExt;
+use byteorder::WriteBytesExt;
+use lzma_rs::lzma_compress_with_options;
 use lzma_rs::lzma_decompress_with_options;
 use serde::Serialize;
 use serde::Deserialize;
@@ -14,6 +18,7 @@ use sqlx::Connection;
 use sqlx::Row;
 
 use crate::ygopro::message::GreedyVector;
+use crate::ygopro::message::HostInfo;
 use crate::ygopro::message::string::cast_to_string;
 
 #[derive(PartialEq, Eq, Debug, Clone, Default, Serialize, Deserialize)]
@@ -48,6 +53,101 @@ impl Deck {
             side: Vec::new()
         })
     }
+
+    /// Write as replay does, reverse of [from_reader](Deck::from_reader).
+    pub fn to_writer<T: WriteBytesExt>(&self, writer: &mut T) -> anyhow::Result<()> {
+        write_array_with_length(writer, &self.main)?;
+        write_array_with_length(writer, &self.ex)?;
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  validate
+    // ----------------------------------------------------------------------------------------------------
+    /// Check deck as ygopro does for a room, with cards in [CARDS] and banlist in [LFLISTS].
+    ///
+    /// Extra deck cards can be either in `main` (as [UpdateDeck](crate::ygopro::message::ctos::UpdateDeck) sends)
+    /// or in `ex` (as replay reads), they are classified by card type.
+    ///
+    /// Copies are counted by alias, over main, extra and side.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn validate(&self, host_info: &HostInfo) -> Result<(), DeckError> {
+        let lflist = if host_info.lflist < 0 { None } else { LFLISTS.get(host_info.lflist) };
+        self.validate_with(host_info.rule, lflist)
+    }
+
+    /// [validate](Deck::validate) with a specified banlist.
+    pub fn validate_with(&self, rule: u8, lflist: Option<&LFList>) -> Result<(), DeckError> {
+        let cards = CARDS.read();
+        let mut main_count = 0;
+        let mut extra_count = 0;
+        let mut copies: HashMap<u32, u8> = HashMap::new();
+        let deck = self.main.iter().chain(self.ex.iter()).map(|code| (code, false)).chain(self.side.iter().map(|code| (code, true)));
+        for (&code, in_side) in deck {
+            let card = cards.get(&code).filter(|card| !card._type.contains(crate::ygopro::Type::Token)).ok_or(DeckError::UnknownCard(code))?;
+            card.check_available(rule)?;
+            if !in_side {
+                if card.is_extra() { extra_count += 1; } else { main_count += 1; }
+            }
+            let limit_code = card.limit_code();
+            let copy = copies.entry(limit_code).or_default();
+            *copy += 1;
+            if *copy > 3 { return Err(DeckError::CardCount(code)); }
+            if let Some(lflist) = lflist {
+                if *copy > lflist.limit(limit_code) { return Err(DeckError::LFList(code)); }
+            }
+        }
+        if !(40..=60).contains(&main_count) { return Err(DeckError::MainCount(main_count)); }
+        if extra_count > 15 { return Err(DeckError::ExtraCount(extra_count)); }
+        if self.side.len() > 15 { return Err(DeckError::SideCount(self.side.len())); }
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  DeckError
+// ----------------------------------------------------------------------------------------------------
+/// Why a deck fails [Deck::validate]. Same as ygopro `DECKERROR_*`.
+// ----------------------------------------------------------------------------------------------------
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeckError {
+    #[error("Card {} exceeds limit of banlist.", Card::display(*.0))]
+    LFList(u32),
+    #[error("Card {} is OCG only.", Card::display(*.0))]
+    OCGOnly(u32),
+    #[error("Card {} is TCG only.", Card::display(*.0))]
+    TCGOnly(u32),
+    #[error("Card {} is unknown.", Card::display(*.0))]
+    UnknownCard(u32),
+    #[error("Card {} has more than 3 copies.", Card::display(*.0))]
+    CardCount(u32),
+    #[error("Main deck has {0} cards.")]
+    MainCount(usize),
+    #[error("Extra deck has {0} cards.")]
+    ExtraCount(usize),
+    #[error("Side deck has {0} cards.")]
+    SideCount(usize),
+    #[error("Card {} is not available in this rule.", Card::display(*.0))]
+    NotAvailable(u32)
+}
+
+impl DeckError {
+    /// Code for [stoc::ErrorMessage](crate::ygopro::message::stoc::ErrorMessage) with 
+    /// [Deckerror](crate::ygopro::ErrorMessage::Deckerror), `type << 28 | card or count`.
+    pub fn error_code(&self) -> u32 {
+        let (error_type, value) = match *self {
+            DeckError::LFList(code)       => (1, code),
+            DeckError::OCGOnly(code)      => (2, code),
+            DeckError::TCGOnly(code)      => (3, code),
+            DeckError::UnknownCard(code)  => (4, code),
+            DeckError::CardCount(code)    => (5, code),
+            DeckError::MainCount(count)   => (6, count as u32),
+            DeckError::ExtraCount(count)  => (7, count as u32),
+            DeckError::SideCount(count)   => (8, count as u32),
+            DeckError::NotAvailable(code) => (9, code),
+        };
+        error_type << 28 | (value & 0x0fffffff)
+    }
 }
 
 impl core::convert::From<DeckBinaryStructure> for Deck {
@@ -70,18 +170,93 @@ impl core::convert::Into<DeckBinaryStructure> for Deck {
     }
 }
 
-impl std::fmt::Display for Deck {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "#generated by srvpru deck log")?;
-        writeln!(f, "#main")?;
-        for card in self.main.iter() {  
-            writeln!(f, "{:}", card)?;
+impl Deck {
+    // ----------------------------------------------------------------------------------------------------
+    //  from_ydk
+    // ----------------------------------------------------------------------------------------------------
+    /// Parse a `.ydk` text.
+    ///
+    /// Cards under `#main`, `#extra` and `!side` go to `main`, `ex` and `side`.
+    /// Other `#` lines are comments, and lines not starting with a code are ignored.
+    /// Extra deck cards misplaced in `#main` are moved to `ex` by [split_extra](Deck::split_extra).
+    // ----------------------------------------------------------------------------------------------------
+    pub fn from_ydk(text: &str) -> Deck {
+        let mut deck = Deck::default();
+        let mut section = &mut deck.main;
+        for line in text.lines() {
+            let line = line.trim();
+            match line {
+                "#main" => { section = &mut deck.main; continue; }
+                "#extra" => { section = &mut deck.ex; continue; }
+                "!side" => { section = &mut deck.side; continue; }
+                _ if line.starts_with('#') || line.starts_with('!') => continue,
+                _ => {}
+            }
+            if let Some(code) = line.split_whitespace().next().and_then(|code| code.parse::<u32>().ok()) {
+                section.push(code);
+            }
         }
-        writeln!(f, "!side")?;
-        for card in self.side.iter() {
-            writeln!(f, "{:}", card)?;
+        deck.split_extra();
+        deck
+    }
+
+    /// Read a `.ydk` file, see [from_ydk](Deck::from_ydk).
+    pub fn load_ydk<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Deck> {
+        Ok(Deck::from_ydk(&std::fs::read_to_string(path)?))
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  to_ydk
+    // ----------------------------------------------------------------------------------------------------
+    /// Write as `.ydk` text, with extra deck cards in `main` moved to `#extra`.
+    ///
+    /// #### Arguments
+    /// * `comment`: first line of the file, without `#`.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn to_ydk(&self, comment: &str) -> String {
+        let mut deck = self.clone();
+        deck.split_extra();
+        let mut ydk = format!("#{}\n#main\n", comment);
+        let sections = [(&deck.main, ""), (&deck.ex, "#extra\n"), (&deck.side, "!side\n")];
+        for (cards, header) in sections.iter() {
+            ydk.push_str(header);
+            for card in cards.iter() {
+                ydk.push_str(&card.to_string());
+                ydk.push('\n');
+            }
         }
-        Ok(())
+        ydk
+    }
+
+    /// If two decks have same cards in main (with extra) and side, ignoring order.
+    ///
+    /// #### Arguments
+    /// * `ignore_side`: compare all cards together, for a deck changed by side decking.
+    pub fn same_cards(&self, other: &Deck, ignore_side: bool) -> bool {
+        let sorted = |deck: &Deck| {
+            let mut main: Vec<u32> = deck.main.iter().chain(deck.ex.iter()).cloned().collect();
+            let mut side = deck.side.clone();
+            if ignore_side { main.append(&mut side); }
+            main.sort_unstable();
+            side.sort_unstable();
+            (main, side)
+        };
+        sorted(self) == sorted(other)
+    }
+
+    /// Move extra deck cards in `main` to the end of `ex`, by their type in [CARDS].
+    /// Unknown cards stay where they are.
+    pub fn split_extra(&mut self) {
+        let cards = CARDS.read();
+        let (extra, main): (Vec<u32>, Vec<u32>) = self.main.iter().partition(|code| cards.get(code).is_some_and(|card| card.is_extra()));
+        self.main = main;
+        self.ex.extend(extra);
+    }
+}
+
+impl std::fmt::Display for Deck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_ydk("generated by srvpru deck log"))
     }
 }
 
@@ -101,6 +276,7 @@ bitflags! {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct ReplayHeader {
     pub id: u32,
     pub version: u32,
@@ -111,6 +287,7 @@ pub struct ReplayHeader {
     pub props: [u8; 8]
 }
 
+#[derive(Clone, Debug)]
 pub struct Replay {
     pub header: ReplayHeader,
     pub host_name: String,
@@ -143,31 +320,73 @@ impl ReplayHeader {
         })
     }
 
+    pub fn to_writer<T: WriteBytesExt>(&self, writer: &mut T) -> anyhow::Result<()> {
+        writer.write_u32::<LittleEndian>(self.id)?;
+        writer.write_u32::<LittleEndian>(self.version)?;
+        writer.write_u32::<LittleEndian>(self.flag.bits())?;
+        writer.write_u32::<LittleEndian>(self.seed)?;
+        writer.write_u32::<LittleEndian>(self.data_size)?;
+        writer.write_u32::<LittleEndian>(self.start_time)?;
+        writer.write_all(&self.props)?;
+        Ok(())
+    }
+
     pub fn is_compressed(&self) -> bool { self.flag.contains(ReplayHeaderFlags::Compressed) }
     pub fn is_tag(&self)        -> bool { self.flag.contains(ReplayHeaderFlags::Tag) }
     pub fn is_decoded(&self)    -> bool { self.flag.contains(ReplayHeaderFlags::Decode) }
 }
 
 impl Replay {
-    // ==================================================
-    // Correct order: 
-    // prop  dict_size  datasize
-    //  93    0 0 0 1     u64
-    // Ygopro replay header:
-    // datasize  prop  dict_size  padding
-    //   u32      93    0 0 0 1    0 0 0
-    // ==================================================
     pub fn from_reader<T: ReadBytesExt + BufRead>(reader: &mut T) -> anyhow::Result<Replay> {
-        let header = ReplayHeader::from_reader(reader)?; 
+        let header = ReplayHeader::from_reader(reader)?;
+        let body = Replay::decode_body(&header, reader)?;
+        Replay::from_body(header, body)
+    }
+
+    /// Parse a whole `.yrp` file in memory, such as the data of a [stoc::Replay](crate::ygopro::message::stoc::Replay).
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Replay> {
+        Replay::from_reader(&mut Cursor::new(data))
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  decode_body
+    // ----------------------------------------------------------------------------------------------------
+    /// Read the replay body after header, decompress it if header says so.
+    ///
+    /// Correct order for lzma-rs:
+    /// ```text
+    /// prop  dict_size  datasize
+    ///  93    0 0 0 1     u64
+    /// ```
+    /// Ygopro replay header:
+    /// ```text
+    /// datasize  prop  dict_size  padding
+    ///   u32      93    0 0 0 1    0 0 0
+    /// ```
+    /// So the leading 5 props are chained before the stream, with the datasize provided by header.
+    ///
+    /// #### Arguments
+    /// * `header`: header just read from `reader`.
+    /// * `reader`: positioned right after the header.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn decode_body<T: BufRead>(header: &ReplayHeader, reader: &mut T) -> anyhow::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        if !header.is_compressed() {
+            reader.read_to_end(&mut body)?;
+            return Ok(body);
+        }
         let leading_props = Cursor::new(&header.props[0..5]);
         let mut compressed_data = leading_props.chain(reader);
-        let mut decompressed_vector = Vec::new();
-        lzma_decompress_with_options(&mut compressed_data, &mut decompressed_vector, &lzma_rs::decompress::Options { 
+        lzma_decompress_with_options(&mut compressed_data, &mut body, &lzma_rs::decompress::Options { 
             unpacked_size: lzma_rs::decompress::UnpackedSize::UseProvided(Some(header.data_size as u64)), 
             memlimit: None,
             allow_incomplete: false 
         })?;
-        let mut decompressed_reader = Cursor::new(decompressed_vector);
+        Ok(body)
+    }
+
+    fn from_body(header: ReplayHeader, body: Vec<u8>) -> anyhow::Result<Replay> {
+        let mut decompressed_reader = Cursor::new(body);
         let reader = &mut decompressed_reader;
         let is_tag = header.is_tag();
         let mut replay = Replay {
@@ -199,6 +418,82 @@ impl Replay {
         }
         Ok(replay)
     }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  encode_body
+    // ----------------------------------------------------------------------------------------------------
+    /// Write the uncompressed body, reverse of what [from_reader](Replay::from_reader) reads.
+    ///
+    /// Tag names and decks are only written if header is tag, missing ones are written empty.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn encode_body(&self) -> anyhow::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        let writer = &mut body;
+        let is_tag = self.header.is_tag();
+        write_string::<_, 20>(writer, &self.host_name)?;
+        if is_tag {
+            write_string::<_, 20>(writer, self.tag_host_name.as_deref().unwrap_or(""))?;
+            write_string::<_, 20>(writer, self.tag_client_name.as_deref().unwrap_or(""))?;
+        }
+        write_string::<_, 20>(writer, &self.client_name)?;
+        writer.write_u32::<LittleEndian>(self.start_lp)?;
+        writer.write_u32::<LittleEndian>(self.start_hand)?;
+        writer.write_u32::<LittleEndian>(self.draw_count)?;
+        writer.write_u32::<LittleEndian>(self.opt)?;
+        self.host_deck.to_writer(writer)?;
+        if is_tag {
+            self.tag_host_deck.clone().unwrap_or_default().to_writer(writer)?;
+            self.tag_client_deck.clone().unwrap_or_default().to_writer(writer)?;
+        }
+        self.client_deck.to_writer(writer)?;
+        for data in self.datas.iter() {
+            let length = u8::try_from(data.len()).map_err(|_| anyhow!("Replay data too long: {} bytes", data.len()))?;
+            writer.write_u8(length)?;
+            writer.write_all(data)?;
+        }
+        Ok(body)
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  to_writer
+    // ----------------------------------------------------------------------------------------------------
+    /// Write replay as a standalone `.yrp` file.
+    ///
+    /// `data_size` and `props` in header are recalculated from body. 
+    /// If header is compressed, body is lzma compressed, or it's written as is.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn to_writer<T: Write>(&self, writer: &mut T) -> anyhow::Result<()> {
+        let body = self.encode_body()?;
+        let mut header = ReplayHeader { props: [0u8; 8], data_size: body.len() as u32, ..self.header };
+        let payload = if header.is_compressed() {
+            let mut compressed = Vec::new();
+            lzma_compress_with_options(&mut Cursor::new(&body), &mut compressed, &lzma_rs::compress::Options {
+                unpacked_size: lzma_rs::compress::UnpackedSize::SkipWritingToHeader
+            })?;
+            header.props[0..5].copy_from_slice(&compressed[0..5]);
+            compressed.split_off(5)
+        }
+        else { body };
+        header.to_writer(writer)?;
+        writer.write_all(&payload)?;
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.to_writer(&mut data)?;
+        Ok(data)
+    }
+
+    /// Save as a `.yrp` file, create parent directories if not exist.
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() { std::fs::create_dir_all(parent)?; }
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.to_writer(&mut file)?;
+        file.flush()?;
+        Ok(())
+    }
 }
 
 fn read_array<T: ReadBytesExt, const N: usize>(reader: &mut T) -> anyhow::Result<[u8; N]> {
@@ -214,37 +509,111 @@ fn read_array_with_length<T: ReadBytesExt>(reader: &mut T) -> anyhow::Result<Vec
     Ok(vec)
 }
 
+fn write_array_with_length<T: WriteBytesExt>(writer: &mut T, array: &[u32]) -> anyhow::Result<()> {
+    writer.write_u32::<LittleEndian>(array.len() as u32)?;
+    for item in array.iter() { writer.write_u32::<LittleEndian>(*item)? }
+    Ok(())
+}
+
 fn read_string<T: ReadBytesExt, const N: usize>(reader: &mut T) -> anyhow::Result<String> {
     let mut arr = [0u16; N];
     for i in 0..N { arr[i] = reader.read_u16::<LittleEndian>()?; }
     cast_to_string(&arr).ok_or(anyhow!("Cannot cast byte array to string"))
 }
 
+/// Write string as a fixed \[u16; N\], truncated to keep at least one \0 in the end.
+fn write_string<T: WriteBytesExt, const N: usize>(writer: &mut T, string: &str) -> anyhow::Result<()> {
+    let mut arr = [0u16; N];
+    for (index, chr) in string.encode_utf16().take(N - 1).enumerate() { arr[index] = chr; }
+    for chr in arr.iter() { writer.write_u16::<LittleEndian>(*chr)?; }
+    Ok(())
+}
+
+/// A banlist in `lflist.conf`, started by a `!name` line.
+#[derive(Debug, Default, Clone)]
+pub struct LFList {
+    pub name: String,
+    /// Card code to allowed copies, 0 for forbidden.
+    pub limits: HashMap<u32, u8>,
+    /// Declared by `$whitelist`, cards not listed are forbidden.
+    pub whitelist: bool
+}
+
+impl LFList {
+    /// Allowed copies of a card in this list.
+    pub fn limit(&self, code: u32) -> u8 {
+        match self.limits.get(&code) {
+            Some(limit) => *limit,
+            None if self.whitelist => 0,
+            None => 3
+        }
+    }
+}
+
 pub struct LFLists {
-    lists: Vec<String>
+    lists: Vec<LFList>
 }
 
 impl LFLists {
     fn init(&mut self) -> std::io::Result<()> {
         let configuration = crate::srvpru::get_configuration();
         let file = std::fs::File::open(configuration.ygopro.lflist_conf.clone())?;
-        for line in std::io::BufReader::new(file).lines() {
-            let _line = line?;
-            if _line.starts_with("!") {
-                self.lists.push(_line[1..].to_string());
+        *self = LFLists::from_reader(std::io::BufReader::new(file))?;
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  from_reader
+    // ----------------------------------------------------------------------------------------------------
+    /// Parse `lflist.conf`.
+    ///
+    /// * `#...` are comments.
+    /// * `!name` starts a new list.
+    /// * `$whitelist` marks current list as whitelist.
+    /// * `code count --comment` sets limit of a card in current list.
+    ///
+    /// Illegal lines, or limits before any list, are ignored.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn from_reader<T: BufRead>(reader: T) -> std::io::Result<LFLists> {
+        let mut lists: Vec<LFList> = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix('!') {
+                lists.push(LFList { name: name.trim().to_string(), ..Default::default() });
+                continue;
+            }
+            let list = match lists.last_mut() {
+                Some(list) => list,
+                None => continue
+            };
+            if line.starts_with("$whitelist") {
+                list.whitelist = true;
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let code = parts.next().and_then(|code| code.parse::<u32>().ok());
+            let limit = parts.next().and_then(|limit| limit.parse::<i32>().ok());
+            if let (Some(code), Some(limit)) = (code, limit) {
+                list.limits.insert(code, limit.clamp(0, 3) as u8);
             }
         }
-        Ok(())
+        Ok(LFLists { lists })
     }
 
     pub fn first_tcg(&self) -> i32 {
-        for (index, name) in self.lists.iter().enumerate() {
-            if name.ends_with("TCG") {
+        for (index, list) in self.lists.iter().enumerate() {
+            if list.name.ends_with("TCG") {
                 return index as i32;
             }
         }
         return -1;
     } 
+
+    /// Get list by [HostInfo](crate::ygopro::message::HostInfo) `lflist` index, `None` for no list.
+    pub fn get(&self, index: i32) -> Option<&LFList> {
+        usize::try_from(index).ok().and_then(|index| self.lists.get(index))
+    }
 }
 
 lazy_static! {
@@ -255,9 +624,21 @@ lazy_static! {
     };
 }
 
+/// Strings in `texts` table of cdb.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CardText {
+    pub name: String,
+    pub desc: String,
+    /// `str1` to `str16`, hints for effects.
+    pub hints: Vec<String>
+}
+
+#[derive(Debug, Clone)]
 pub struct Card {
     pub code: u32,
     pub alias: u32,
+    /// Where this card is available, see [CARD_AVAILABLE_OCG] and so on.
+    pub ot: u32,
     pub setcode: i64,
     pub _type: crate::ygopro::Type,
     pub level: u32,
@@ -267,13 +648,16 @@ pub struct Card {
     pub defense: i32,
     pub left_scale: u32,
     pub right_scale: u32,
-    pub link_marker: crate::ygopro::Linkmarkers
+    pub link_marker: crate::ygopro::Linkmarkers,
+    /// Empty if card has no row in `texts`.
+    pub text: CardText
 }
 
 impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Card {
     fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
         let code = row.try_get("id")?;
         let alias = row.try_get("alias")?;
+        let ot = row.try_get("ot")?;
         let setcode = row.try_get("setcode")?;
         let _type = crate::ygopro::Type::from_bits_truncate(row.try_get("type")?);
         let _level: u32 = row.try_get("level")?;
@@ -290,10 +674,16 @@ impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Card {
         else {
             (_defense, crate::ygopro::Linkmarkers::empty())
         };
+        let text = CardText {
+            name: row.try_get::<Option<String>, _>("name")?.unwrap_or_default(),
+            desc: row.try_get::<Option<String>, _>("desc")?.unwrap_or_default(),
+            hints: (1..=16).map(|index| row.try_get::<Option<String>, _>(format!("str{}", index).as_str()).map(Option::unwrap_or_default)).collect::<Result<_, _>>()?
+        };
 
         Ok(Card{ 
             code,
             alias,
+            ot,
             setcode,
             _type,
             level,
@@ -303,50 +693,360 @@ impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Card {
             defense,
             left_scale,
             right_scale,
-            link_marker
+            link_marker,
+            text
         })
     }
 }
 
+pub const CARD_AVAILABLE_OCG: u32 = 0x1;
+pub const CARD_AVAILABLE_TCG: u32 = 0x2;
+pub const CARD_AVAILABLE_CUSTOM: u32 = 0x4;
+pub const CARD_AVAILABLE_SC: u32 = 0x8;
+
 impl Card {
+    /// Fusion, synchro, xyz and link monsters are put in extra deck.
+    pub fn is_extra(&self) -> bool {
+        use crate::ygopro::Type;
+        self._type.intersects(Type::Fusion | Type::Synchro | Type::Xyz | Type::Link)
+    }
+
+    /// Code used to count copies. Alternative arts count as the original card.
+    pub fn limit_code(&self) -> u32 {
+        if self.alias != 0 { self.alias } else { self.code }
+    }
+
+    /// Check `ot` against [HostInfo](crate::ygopro::message::HostInfo) `rule`, as ygopro does.
+    pub fn check_available(&self, rule: u8) -> Result<(), DeckError> {
+        let available = match rule {
+            0 => CARD_AVAILABLE_OCG,
+            1 => CARD_AVAILABLE_TCG,
+            2 => CARD_AVAILABLE_SC,
+            3 => CARD_AVAILABLE_CUSTOM,
+            4 => CARD_AVAILABLE_OCG | CARD_AVAILABLE_TCG,
+            _ => return Ok(())
+        };
+        if self.ot & available == available { return Ok(()); }
+        if self.ot & CARD_AVAILABLE_OCG != 0 && available != CARD_AVAILABLE_OCG { return Err(DeckError::OCGOnly(self.code)); }
+        if self.ot & CARD_AVAILABLE_TCG != 0 && available != CARD_AVAILABLE_TCG { return Err(DeckError::TCGOnly(self.code)); }
+        Err(DeckError::NotAvailable(self.code))
+    }
+
     pub async fn load_all_cards() -> anyhow::Result<()> {
         let configuration = &crate::srvpru::get_configuration().ygopro;
-        let path = std::path::Path::new(&configuration.cwd).join(&configuration.database);
+        let cwd = std::path::Path::new(&configuration.cwd);
+        let path = cwd.join(&configuration.database);
         let path= path.to_str().ok_or(anyhow!("Path not legal"))?;
-        Card::load_all_cards_from(path, "main").await?;
+        Card::load_all_cards_from(path, "main", 0).await?;
+        for (index, expansion) in configuration.expansions.iter().enumerate() {
+            let path = cwd.join(expansion);
+            let mut databases = Vec::new();
+            if path.is_dir() {
+                for entry in std::fs::read_dir(&path)? {
+                    let entry = entry?.path();
+                    if entry.extension().is_some_and(|extension| extension == "cdb") { databases.push(entry); }
+                }
+                databases.sort();
+            }
+            else { databases.push(path); }
+            for database in databases.iter() {
+                let database = database.to_str().ok_or(anyhow!("Path not legal"))?;
+                Card::load_all_cards_from(database, database, index as i32 + 1).await?;
+            }
+        }
         Ok(())
     }
 
-    pub async fn load_all_cards_from(path: &str, name: &str) -> anyhow::Result<()> {
+    // ----------------------------------------------------------------------------------------------------
+    //  load_all_cards_from
+    // ----------------------------------------------------------------------------------------------------
+    /// Load `datas` and `texts` from a cdb, as a card source.
+    ///
+    /// #### Arguments
+    /// * `path`: cdb file path.
+    /// * `name`: source name, loading with an existing name replaces it.
+    /// * `priority`: when a code exists in several sources, the one with higher priority is used.
+    ///   For same priority, the one loaded later is used.
+    // ----------------------------------------------------------------------------------------------------
+    pub async fn load_all_cards_from(path: &str, name: &str, priority: i32) -> anyhow::Result<()> {
         let mut conn = sqlx::SqliteConnection::connect(&("sqlite://".to_string() + path)).await?;
-        let cards = sqlx::query_as::<_, Card>("SELECT * FROM datas").fetch_all(&mut conn).await?;
-        Card::remove_cards(name);
-        Card::add_cards(name, cards);
+        // `SELECT *` would bring `texts.id` too, which is NULL for cards without texts.
+        let hints = (1..=16).map(|index| format!(", texts.str{}", index)).collect::<String>();
+        let query = format!("SELECT datas.*, texts.name, texts.desc{} FROM datas LEFT JOIN texts ON datas.id = texts.id", hints);
+        let cards = sqlx::query_as::<_, Card>(&query).fetch_all(&mut conn).await?;
+        Card::add_cards_with_priority(name, priority, cards);
         Ok(())
     }
 
     pub fn remove_cards(name: &str) {
-        if let Some(indexes) = CARD_INDEXES.lock().remove(name) {
-            let mut cards = CARDS.write();
-            for index in indexes.iter() {
-                cards.remove(index);
-            }
-        }
+        let mut sources = CARD_SOURCES.lock();
+        sources.retain(|source| source.name != name);
+        rebuild_cards(&sources);
     }
 
     pub fn add_cards(name: &str, cards: Vec<Card>) {
-        let mut all_cards = CARDS.write();
-        let mut all_card_indexes = CARD_INDEXES.lock();
-        let mut card_indexes = Vec::new();
-        for card in cards.into_iter() {
-            card_indexes.push(card.code);
-            all_cards.insert(card.code, card);
+        Card::add_cards_with_priority(name, 0, cards);
+    }
+
+    pub fn add_cards_with_priority(name: &str, priority: i32, cards: Vec<Card>) {
+        let mut sources = CARD_SOURCES.lock();
+        sources.retain(|source| source.name != name);
+        sources.push(CardSource { name: name.to_string(), priority, cards: cards.into_iter().map(Arc::new).collect() });
+        rebuild_cards(&sources);
+    }
+
+    /// Get a card by code.
+    pub fn get(code: u32) -> Option<Arc<Card>> {
+        CARDS.read().get(&code).cloned()
+    }
+
+    /// Get card name by code, `None` if card or its text not loaded.
+    pub fn name_of(code: u32) -> Option<String> {
+        CARDS.read().get(&code).map(|card| card.text.name.clone()).filter(|name| !name.is_empty())
+    }
+
+    /// Human readable card for chat or logs, like `Dark Magician(46986414)`.
+    /// Only code is shown if name is unknown.
+    pub fn display(code: u32) -> String {
+        match Card::name_of(code) {
+            Some(name) => format!("{}({})", name, code),
+            None => code.to_string()
+        }
+    }
+}
+
+struct CardSource {
+    name: String,
+    priority: i32,
+    cards: Vec<Arc<Card>>
+}
+
+fn rebuild_cards(sources: &[CardSource]) {
+    let mut ordered: Vec<&CardSource> = sources.iter().collect();
+    // Stable, so later loaded source wins in same priority.
+    ordered.sort_by_key(|source| source.priority);
+    let mut cards = HashMap::new();
+    for source in ordered {
+        for card in source.cards.iter() {
+            cards.insert(card.code, card.clone());
         }
-        all_card_indexes.insert(name.to_string(), card_indexes);
     }
+    *CARDS.write() = cards;
 }
 
 lazy_static! {
-    pub static ref CARDS: parking_lot::RwLock<HashMap<u32, Card>> = parking_lot::RwLock::new(HashMap::new());
-    static ref CARD_INDEXES: parking_lot::Mutex<HashMap<String, Vec<u32>>> = parking_lot::Mutex::new(HashMap::new());
-}
\ No newline at end of file
+    /// All loaded cards, merged from card sources.
+    pub static ref CARDS: parking_lot::RwLock<HashMap<u32, Arc<Card>>> = parking_lot::RwLock::new(HashMap::new());
+    static ref CARD_SOURCES: parking_lot::Mutex<Vec<CardSource>> = parking_lot::Mutex::new(Vec::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_replay(flag: ReplayHeaderFlags) -> Replay {
+        Replay {
+            header: ReplayHeader { id: 0x31707279, version: 0x1353, flag, seed: 42, data_size: 0, start_time: 1600000000, props: [0u8; 8] },
+            host_name: "host".to_string(),
+            client_name: "client".to_string(),
+            start_lp: 8000,
+            start_hand: 5,
+            draw_count: 1,
+            opt: 0,
+            host_deck: Deck { main: vec![89631139, 89631139, 46986414], side: Vec::new(), ex: vec![44508094] },
+            client_deck: Deck { main: vec![38033121], side: Vec::new(), ex: Vec::new() },
+            tag_host_name: None,
+            tag_client_name: None,
+            tag_host_deck: None,
+            tag_client_deck: None,
+            datas: vec![vec![1, 0, 0, 0], vec![0xff; 16]]
+        }
+    }
+
+    fn assert_round_trip(flag: ReplayHeaderFlags) {
+        let replay = sample_replay(flag);
+        let decoded = Replay::from_bytes(&replay.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.header.flag, flag);
+        assert_eq!(decoded.header.data_size as usize, replay.encode_body().unwrap().len());
+        assert_eq!(decoded.host_name, replay.host_name);
+        assert_eq!(decoded.client_name, replay.client_name);
+        assert_eq!(decoded.start_lp, 8000);
+        assert_eq!(decoded.host_deck, replay.host_deck);
+        assert_eq!(decoded.client_deck, replay.client_deck);
+        assert_eq!(decoded.datas, replay.datas);
+    }
+
+    /// A duel of two 40 card decks, compressed as ygopro does: raw lzma stream with 16M dictionary,
+    /// and its first 5 bytes of properties kept in header.
+    const DUEL_REPLAY: &[u8] = include_bytes!("../../tests/fixtures/duel.yrp");
+
+    #[test]
+    fn ygopro_replay_round_trip() {
+        let mut reader = Cursor::new(DUEL_REPLAY);
+        let header = ReplayHeader::from_reader(&mut reader).unwrap();
+        let body = Replay::decode_body(&header, &mut reader).unwrap();
+        assert_eq!(body.len(), header.data_size as usize);
+
+        let replay = Replay::from_bytes(DUEL_REPLAY).unwrap();
+        assert_eq!((replay.host_name.as_str(), replay.client_name.as_str()), ("Alice", "Bob"));
+        assert_eq!((replay.host_deck.main.len(), replay.host_deck.ex.len(), replay.client_deck.main.len()), (40, 3, 40));
+        assert_eq!(replay.datas.len(), 32);
+        assert_eq!(replay.encode_body().unwrap(), body);
+
+        let written = replay.to_bytes().unwrap();
+        let mut reader = Cursor::new(&written);
+        let written_header = ReplayHeader::from_reader(&mut reader).unwrap();
+        assert_eq!((written_header.seed, written_header.flag), (header.seed, header.flag));
+        assert_eq!(Replay::decode_body(&written_header, &mut reader).unwrap(), body);
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        assert_round_trip(ReplayHeaderFlags::Compressed | ReplayHeaderFlags::Uniform);
+    }
+
+    #[test]
+    fn uncompressed_round_trip() {
+        assert_round_trip(ReplayHeaderFlags::Uniform);
+    }
+
+    #[test]
+    fn parse_lflist() {
+        let conf = "#[2022.1 TCG]\n!2022.1 TCG\n#forbidden\n14558127 0 --Ash\n$whitelist\n!2022.1\n89631139 1\nbroken line\n";
+        let lflists = LFLists::from_reader(conf.as_bytes()).unwrap();
+        assert_eq!(lflists.first_tcg(), 0);
+        assert!(lflists.get(-1).is_none());
+        let tcg = lflists.get(0).unwrap();
+        assert!(tcg.whitelist);
+        assert_eq!(tcg.limit(14558127), 0);
+        assert_eq!(tcg.limit(89631139), 0);
+        let ocg = lflists.get(1).unwrap();
+        assert_eq!(ocg.name, "2022.1");
+        assert_eq!(ocg.limit(89631139), 1);
+        assert_eq!(ocg.limit(14558127), 3);
+    }
+
+    fn test_card(code: u32, alias: u32, _type: crate::ygopro::Type, ot: u32) -> Card {
+        Card {
+            code, alias, ot, setcode: 0, _type, level: 0,
+            attribute: crate::ygopro::Attribute::empty(), race: crate::ygopro::Race::empty(),
+            attack: 0, defense: 0, left_scale: 0, right_scale: 0, link_marker: crate::ygopro::Linkmarkers::empty(),
+            text: CardText::default()
+        }
+    }
+
+    #[test]
+    fn validate_deck() {
+        use crate::ygopro::Type;
+        let mut cards = vec![
+            test_card(900000001, 0, Type::Monster | Type::Normal, CARD_AVAILABLE_OCG | CARD_AVAILABLE_TCG),
+            test_card(900000002, 900000001, Type::Monster | Type::Normal, CARD_AVAILABLE_OCG | CARD_AVAILABLE_TCG),
+            test_card(900000003, 0, Type::Monster | Type::Xyz, CARD_AVAILABLE_OCG | CARD_AVAILABLE_TCG),
+            test_card(900000004, 0, Type::Spell, CARD_AVAILABLE_OCG),
+        ];
+        cards.extend((900000010..900000023).map(|code| test_card(code, 0, Type::Trap, CARD_AVAILABLE_OCG | CARD_AVAILABLE_TCG)));
+        Card::add_cards("validate_deck_test", cards);
+        // 39 traps, 1 ocg only spell, 1 xyz in extra.
+        let mut main: Vec<u32> = (900000010..900000023).flat_map(|code| [code; 3]).collect();
+        main.push(900000004);
+        main.push(900000003);
+        let deck = Deck { main, side: Vec::new(), ex: Vec::new() };
+        assert_eq!(deck.validate_with(0, None), Ok(()));
+        assert_eq!(deck.validate_with(1, None), Err(DeckError::OCGOnly(900000004)));
+        // Rule 4 needs cards available in both OCG and TCG.
+        assert_eq!(deck.validate_with(4, None), Err(DeckError::OCGOnly(900000004)));
+        assert_eq!(deck.validate_with(5, None), Ok(()));
+
+        let mut lflist = LFList::default();
+        lflist.limits.insert(900000001, 1);
+        let mut limited = Deck { main: vec![900000001, 900000002], side: Vec::new(), ex: Vec::new() };
+        assert_eq!(limited.validate_with(0, Some(&lflist)), Err(DeckError::LFList(900000002)));
+        limited.main = vec![900000004; 3];
+        assert_eq!(limited.validate_with(0, None), Err(DeckError::MainCount(3)));
+        limited.main.push(900000004);
+        assert_eq!(limited.validate_with(0, None), Err(DeckError::CardCount(900000004)));
+        limited.main = vec![900000005];
+        assert_eq!(limited.validate_with(0, None), Err(DeckError::UnknownCard(900000005)));
+        assert_eq!(DeckError::MainCount(3).error_code(), 0x60000003);
+        Card::remove_cards("validate_deck_test");
+    }
+
+    #[test]
+    fn merge_card_sources() {
+        use crate::ygopro::Type;
+        let mut base = test_card(900000101, 0, Type::Spell, CARD_AVAILABLE_OCG);
+        base.text.name = "Base".to_string();
+        let mut pre_release = base.clone();
+        pre_release.text.name = "Pre-release".to_string();
+        Card::add_cards_with_priority("merge_test_pre_release", 1, vec![pre_release]);
+        Card::add_cards_with_priority("merge_test_base", 0, vec![base]);
+        assert_eq!(Card::name_of(900000101).as_deref(), Some("Pre-release"));
+        assert_eq!(Card::display(900000101), "Pre-release(900000101)");
+        Card::remove_cards("merge_test_pre_release");
+        assert_eq!(Card::name_of(900000101).as_deref(), Some("Base"));
+        Card::remove_cards("merge_test_base");
+        assert!(Card::get(900000101).is_none());
+        assert_eq!(Card::display(900000101), "900000101");
+    }
+
+    #[tokio::test]
+    async fn load_cards_without_texts() {
+        let path = std::env::temp_dir().join(format!("srvpru_cards_{}.cdb", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut conn = sqlx::SqliteConnection::connect(&format!("sqlite://{}?mode=rwc", path)).await.unwrap();
+        let hints = (1..=16).map(|index| format!(", str{} TEXT", index)).collect::<String>();
+        sqlx::query("CREATE TABLE datas (id INTEGER PRIMARY KEY, ot INTEGER, alias INTEGER, setcode INTEGER, type INTEGER, atk INTEGER, def INTEGER, level INTEGER, race INTEGER, attribute INTEGER, category INTEGER)").execute(&mut conn).await.unwrap();
+        sqlx::query(&format!("CREATE TABLE texts (id INTEGER PRIMARY KEY, name TEXT, desc TEXT{})", hints)).execute(&mut conn).await.unwrap();
+        sqlx::query("INSERT INTO datas VALUES (900000301, 3, 0, 0, 17, 1000, 1000, 4, 1, 1, 0), (900000302, 3, 0, 0, 2, 0, 0, 0, 0, 0, 0)").execute(&mut conn).await.unwrap();
+        sqlx::query("INSERT INTO texts (id, name, desc) VALUES (900000301, 'With Text', 'A normal monster.')").execute(&mut conn).await.unwrap();
+        conn.close().await.unwrap();
+
+        Card::load_all_cards_from(path, "load_cards_test", 0).await.unwrap();
+        assert_eq!(Card::name_of(900000301).as_deref(), Some("With Text"));
+        let card = Card::get(900000302).unwrap();
+        assert_eq!(card.code, 900000302);
+        assert!(card.text.name.is_empty());
+        Card::remove_cards("load_cards_test");
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn ydk_round_trip() {
+        use crate::ygopro::Type;
+        Card::add_cards("ydk_test", vec![test_card(900000201, 0, Type::Monster | Type::Link, CARD_AVAILABLE_OCG)]);
+        let ydk = "#created by someone\r\n#main\r\n900000202\r\n900000201\r\n900000203 --comment\r\n#extra\r\n900000204\r\n!side\r\n900000205\r\n";
+        let deck = Deck::from_ydk(ydk);
+        assert_eq!(deck.main, vec![900000202, 900000203]);
+        assert_eq!(deck.ex, vec![900000204, 900000201]);
+        assert_eq!(deck.side, vec![900000205]);
+
+        let merged = Deck { main: vec![900000202, 900000201, 900000203], side: vec![900000205], ex: Vec::new() };
+        let text = merged.to_ydk("srvpru");
+        assert_eq!(text, "#srvpru\n#main\n900000202\n900000203\n#extra\n900000201\n!side\n900000205\n");
+        assert_eq!(Deck::from_ydk(&text), Deck { main: vec![900000202, 900000203], side: vec![900000205], ex: vec![900000201] });
+        Card::remove_cards("ydk_test");
+    }
+
+    #[test]
+    fn compare_decks() {
+        let registered = Deck { main: vec![1, 2, 3], side: vec![4], ex: vec![5] };
+        let submitted = Deck { main: vec![3, 5, 2, 1], side: vec![4], ex: Vec::new() };
+        assert!(registered.same_cards(&submitted, false));
+        let sided = Deck { main: vec![4, 5, 2, 1], side: vec![3], ex: Vec::new() };
+        assert!(!registered.same_cards(&sided, false));
+        assert!(registered.same_cards(&sided, true));
+        assert!(!registered.same_cards(&Deck { main: vec![1, 2, 3, 5], side: Vec::new(), ex: Vec::new() }, true));
+    }
+
+    #[test]
+    fn tag_round_trip() {
+        let mut replay = sample_replay(ReplayHeaderFlags::Compressed | ReplayHeaderFlags::Tag);
+        replay.tag_host_name = Some("tag host".to_string());
+        replay.tag_client_deck = Some(Deck { main: vec![1, 2, 3], side: Vec::new(), ex: Vec::new() });
+        let decoded = Replay::from_bytes(&replay.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.tag_host_name.as_deref(), Some("tag host"));
+        assert_eq!(decoded.tag_client_name.as_deref(), Some(""));
+        assert_eq!(decoded.tag_host_deck, Some(Deck::default()));
+        assert_eq!(decoded.tag_client_deck, replay.tag_client_deck);
+    }
+}
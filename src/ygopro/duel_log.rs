@@ -0,0 +1,382 @@
+// ============================================================
+//  duel_log
+// ------------------------------------------------------------
+//! Turn [GameMessage](gm::GameMessage)s into a JSON friendly event log.
+//!
+//! Each [DuelEvent] carries turn, phase and actor, with a typed [Event].
+//! Messages not meaningful for analysis (selects, hints, updates...) are skipped.
+//!
+//! A `.yrp` only records responses of players, the events can't be
+//! rebuilt from it without running ocgcore. So [DuelLog::from_replay] takes
+//! [DuelInfo] from the replay and events from the message stream of the same
+//! duel, like the one recorded by [telescreen](crate::srvpru::plugins::telescreen).
+// ============================================================
+
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::ygopro::Netplayer;
+use crate::ygopro::Phase;
+use crate::ygopro::data::Replay;
+use crate::ygopro::message::MessageType;
+use crate::ygopro::message::Struct;
+use crate::ygopro::message::deserialize_struct_by_type;
+use crate::ygopro::message::gm;
+use crate::ygopro::message::gm::LocationInfo;
+use crate::ygopro::message::gm::reveal_code;
+use crate::ygopro::message::stoc;
+
+/// One entry of the duel log.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DuelEvent {
+    /// Start from 1, 0 for events before first turn.
+    pub turn: u32,
+    #[serde(serialize_with = "serialize_phase")]
+    pub phase: Option<Phase>,
+    /// Player who does this, `None` if it's not done by a player.
+    pub actor: Option<Netplayer>,
+    #[serde(flatten)]
+    pub event: Event
+}
+
+/// Typed content of a [DuelEvent].
+///
+/// Card codes are `None` if they are hidden to the receiver of the message.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Start { life_points: [i32; 2] },
+    NewTurn,
+    NewPhase,
+    Draw { cards: Vec<Option<u32>> },
+    Summon { card: Option<u32>, location: LocationInfo },
+    SpecialSummon { card: Option<u32>, location: LocationInfo },
+    FlipSummon { card: Option<u32>, location: LocationInfo },
+    Set { card: Option<u32>, location: LocationInfo },
+    Move { card: Option<u32>, from: LocationInfo, to: LocationInfo, reason: u32 },
+    /// `chain_link` starts from 1, `desc` is string id of the effect.
+    Chain { card: Option<u32>, chain_link: u32, location: LocationInfo, desc: u32 },
+    ChainSolved { chain_link: u32 },
+    ChainNegated { chain_link: u32 },
+    ChainDisabled { chain_link: u32 },
+    ChainEnd,
+    /// `target` is `None` for a direct attack.
+    Attack { attacker: LocationInfo, target: Option<LocationInfo> },
+    Damage { amount: i32 },
+    Recover { amount: i32 },
+    PayLpCost { amount: i32 },
+    /// `winner` is `None` for a draw.
+    Win { winner: Option<Netplayer>, reason: u8 }
+}
+
+fn serialize_phase<S: Serializer>(phase: &Option<Phase>, serializer: S) -> Result<S::Ok, S::Error> {
+    match phase {
+        Some(phase) => serializer.serialize_some(&format!("{:?}", phase)),
+        None => serializer.serialize_none()
+    }
+}
+
+/// A player recorded in replay.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DuelPlayer {
+    pub name: String,
+    pub main: Vec<u32>,
+    pub extra: Vec<u32>
+}
+
+/// Duel settings and players, available from both replay and [gm::Start].
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct DuelInfo {
+    /// In replay order: host, (tag host, tag client,) client.
+    pub players: Vec<DuelPlayer>,
+    pub start_lp: u32,
+    pub start_hand: u32,
+    pub draw_count: u32,
+    pub seed: u32,
+    pub start_time: u32
+}
+
+/// Exported log of a duel.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct DuelLog {
+    pub info: Option<DuelInfo>,
+    pub events: Vec<DuelEvent>
+}
+
+impl DuelInfo {
+    pub fn from_replay(replay: &Replay) -> DuelInfo {
+        let mut players = vec![(&replay.host_name, &replay.host_deck)];
+        if let (Some(name), Some(deck)) = (&replay.tag_host_name, &replay.tag_host_deck) { players.push((name, deck)); }
+        if let (Some(name), Some(deck)) = (&replay.tag_client_name, &replay.tag_client_deck) { players.push((name, deck)); }
+        players.push((&replay.client_name, &replay.client_deck));
+        DuelInfo {
+            players: players.into_iter().map(|(name, deck)| DuelPlayer { name: name.clone(), main: deck.main.clone(), extra: deck.ex.clone() }).collect(),
+            start_lp: replay.start_lp,
+            start_hand: replay.start_hand,
+            draw_count: replay.draw_count,
+            seed: replay.header.seed,
+            start_time: replay.header.start_time
+        }
+    }
+}
+
+impl DuelLog {
+    /// Log a duel with its replay and the STOC frames received while playing it.
+    ///
+    /// See [module doc](self) for why `messages` is needed.
+    pub fn from_replay(replay: &Replay, messages: &[u8]) -> DuelLog {
+        let mut recorder = DuelEventRecorder::new();
+        recorder.record_stream(messages);
+        recorder.into_log(Some(DuelInfo::from_replay(replay)))
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+// ============================================================
+//  DuelEventRecorder
+// ------------------------------------------------------------
+/// Keep turn and phase state while reading game messages in order.
+// ============================================================
+#[derive(Debug, Default)]
+pub struct DuelEventRecorder {
+    turn: u32,
+    phase: Option<Phase>,
+    turn_player: Option<Netplayer>,
+    info: Option<DuelInfo>,
+    events: Vec<DuelEvent>
+}
+
+impl DuelEventRecorder {
+    pub fn new() -> DuelEventRecorder {
+        DuelEventRecorder::default()
+    }
+
+    pub fn events(&self) -> &[DuelEvent] {
+        &self.events
+    }
+
+    /// `info` overrides the one read from a replay frame in stream, if any.
+    pub fn into_log(self, info: Option<DuelInfo>) -> DuelLog {
+        DuelLog { info: info.or(self.info), events: self.events }
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  record
+    // ----------------------------------------------------------------------------------------------------
+    /// Record a game message.
+    ///
+    /// #### Arguments
+    /// * `kind`: type of the message.
+    /// * `message`: the child struct of [GameMessage](gm::GameMessage), must match `kind`.
+    ///
+    /// #### Return
+    /// The event recorded, `None` if the message is skipped.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn record(&mut self, kind: gm::MessageType, message: &dyn Struct) -> Option<&DuelEvent> {
+        let (actor, event) = self.translate(kind, message)?;
+        self.events.push(DuelEvent { turn: self.turn, phase: self.phase, actor, event });
+        self.events.last()
+    }
+
+    pub fn record_game_message(&mut self, message: &gm::GameMessage) -> Option<&DuelEvent> {
+        self.record(message.kind, &*message.message)
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  record_stream
+    // ----------------------------------------------------------------------------------------------------
+    /// Record all game messages in concatenated STOC frames, such as what an observer receives.
+    ///
+    /// [DuelInfo] is taken from the first [stoc::Replay] frame.
+    /// Other STOC messages, and messages failed to deserialize, are skipped.
+    /// A truncated frame in the tail is ignored.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn record_stream(&mut self, data: &[u8]) {
+        let mut position = 0;
+        while data.len() - position >= 3 {
+            let length = u16::from_le_bytes([data[position], data[position + 1]]) as usize;
+            if length == 0 || data.len() - position < 2 + length { break; }
+            let frame = &data[position + 2..position + 2 + length];
+            position += 2 + length;
+            if frame[0] == u8::from(stoc::MessageType::Replay) {
+                if self.info.is_none() { self.info = Replay::from_bytes(&frame[1..]).ok().map(|replay| DuelInfo::from_replay(&replay)); }
+                continue;
+            }
+            if frame[0] != u8::from(stoc::MessageType::GameMessage) { continue; }
+            let message = deserialize_struct_by_type(MessageType::STOC(stoc::MessageType::GameMessage), &frame[1..]);
+            if let Some(message) = message.as_ref().and_then(|message| message.downcast_ref::<gm::GameMessage>()) {
+                self.record_game_message(message);
+            }
+        }
+    }
+
+    fn translate(&mut self, kind: gm::MessageType, message: &dyn Struct) -> Option<(Option<Netplayer>, Event)> {
+        Some(match kind {
+            gm::MessageType::Start => {
+                let message = message.downcast_ref::<gm::Start>()?;
+                // Each duel of a match starts over.
+                self.turn = 0;
+                self.phase = None;
+                self.turn_player = None;
+                (None, Event::Start { life_points: message.life_points })
+            }
+            gm::MessageType::NewTurn => {
+                let message = message.downcast_ref::<gm::NewTurn>()?;
+                self.turn += 1;
+                self.phase = None;
+                self.turn_player = Some(message.player);
+                (Some(message.player), Event::NewTurn)
+            }
+            gm::MessageType::NewPhase => {
+                let message = message.downcast_ref::<gm::NewPhase>()?;
+                self.phase = Phase::try_from(message.phase as u32).ok();
+                (self.turn_player, Event::NewPhase)
+            }
+            gm::MessageType::Draw => {
+                let message = message.downcast_ref::<gm::Draw>()?;
+                (Some(message.player), Event::Draw { cards: message.cards.iter().map(|code| reveal_code(*code)).collect() })
+            }
+            gm::MessageType::Summoning => {
+                let message = message.downcast_ref::<gm::Summoning>()?;
+                (Some(message.location.controller), Event::Summon { card: reveal_code(message.card), location: message.location })
+            }
+            gm::MessageType::Spsummoning => {
+                let message = message.downcast_ref::<gm::Spsummoning>()?;
+                (Some(message.location.controller), Event::SpecialSummon { card: reveal_code(message.card), location: message.location })
+            }
+            gm::MessageType::Flipsummoning => {
+                let message = message.downcast_ref::<gm::Flipsummoning>()?;
+                (Some(message.location.controller), Event::FlipSummon { card: reveal_code(message.card), location: message.location })
+            }
+            gm::MessageType::Set => {
+                let message = message.downcast_ref::<gm::Set>()?;
+                (Some(message.location.controller), Event::Set { card: reveal_code(message.card), location: message.location })
+            }
+            gm::MessageType::Move => {
+                let message = message.downcast_ref::<gm::Move>()?;
+                (None, Event::Move { card: reveal_code(message.code), from: message.previous, to: message.current, reason: message.reason.bits() })
+            }
+            gm::MessageType::Chaining => {
+                let message = message.downcast_ref::<gm::Chaining>()?;
+                (Some(message.triggering_controller), Event::Chain { card: reveal_code(message.card), chain_link: message.chain_count as u32, location: message.location, desc: message.desc })
+            }
+            gm::MessageType::ChainSolved => {
+                let message = message.downcast_ref::<gm::ChainSolved>()?;
+                (None, Event::ChainSolved { chain_link: message.chain_index as u32 })
+            }
+            gm::MessageType::ChainNegated => {
+                let message = message.downcast_ref::<gm::ChainNegated>()?;
+                (None, Event::ChainNegated { chain_link: message.chain_index as u32 })
+            }
+            gm::MessageType::ChainDisabled => {
+                let message = message.downcast_ref::<gm::ChainDisabled>()?;
+                (None, Event::ChainDisabled { chain_link: message.chain_index as u32 })
+            }
+            gm::MessageType::ChainEnd => (None, Event::ChainEnd),
+            gm::MessageType::Attack => {
+                let message = message.downcast_ref::<gm::Attack>()?;
+                let target = if message.target.location == 0 { None } else { Some(message.target) };
+                (Some(message.attacker.controller), Event::Attack { attacker: message.attacker, target })
+            }
+            gm::MessageType::Damage => {
+                let message = message.downcast_ref::<gm::Damage>()?;
+                (Some(message.player), Event::Damage { amount: message.value })
+            }
+            gm::MessageType::Recover => {
+                let message = message.downcast_ref::<gm::Recover>()?;
+                (Some(message.player), Event::Recover { amount: message.value })
+            }
+            gm::MessageType::PayLpcost => {
+                let message = message.downcast_ref::<gm::PayLpcost>()?;
+                (Some(message.player), Event::PayLpCost { amount: message.cost })
+            }
+            gm::MessageType::Win => {
+                let message = message.downcast_ref::<gm::Win>()?;
+                let winner = match message.winner {
+                    Netplayer::Player1 | Netplayer::Player2 => Some(message.winner),
+                    _ => None
+                };
+                (winner, Event::Win { winner, reason: message.reason })
+            }
+            _ => return None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ygopro::Location;
+    use crate::ygopro::Position;
+
+    fn frame(kind: gm::MessageType, body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 2) as u16).to_le_bytes().to_vec();
+        data.push(stoc::MessageType::GameMessage.into());
+        data.push(kind.into());
+        data.extend_from_slice(body);
+        data
+    }
+
+    // MSG_CHAINING body as ocgcore writes it: code, `get_info_location()`, triggering
+    // controller, location and sequence (u32), description and chain size.
+    const CHAINING: &[u8] = &[
+        0xb5, 0xf0, 0x01, 0x05, 0x00, 0x04, 0x00, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x51, 0x0b,
+        0x1f, 0x50, 0x02
+    ];
+
+    #[test]
+    fn record_turn_and_chain() {
+        let mut stream = Vec::new();
+        stream.extend(frame(gm::MessageType::NewTurn, &[0]));
+        stream.extend(frame(gm::MessageType::NewPhase, &4u16.to_le_bytes()));
+        stream.extend(frame(gm::MessageType::Chaining, CHAINING));
+        stream.extend(frame(gm::MessageType::ChainEnd, &[]));
+        let mut damage = vec![1];
+        damage.extend(1000i32.to_le_bytes());
+        stream.extend(frame(gm::MessageType::Damage, &damage));
+        // Truncated tail is ignored.
+        stream.extend([5, 0, 1]);
+
+        let mut recorder = DuelEventRecorder::new();
+        recorder.record_stream(&stream);
+        let events = recorder.events();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0], DuelEvent { turn: 1, phase: None, actor: Some(Netplayer::Player1), event: Event::NewTurn });
+        assert_eq!(events[2].event, Event::Chain { card: Some(84013237), chain_link: 2, location: LocationInfo { controller: Netplayer::Player1, location: 4, sequence: 0, position: 1 }, desc: 84013237 << 4 | 1 });
+        assert_eq!(events[4], DuelEvent { turn: 1, phase: Some(Phase::Main1), actor: Some(Netplayer::Player2), event: Event::Damage { amount: 1000 } });
+
+        let json = serde_json::to_value(&events[4]).unwrap();
+        assert_eq!(json, serde_json::json!({ "turn": 1, "phase": "Main1", "actor": 1, "type": "damage", "amount": 1000 }));
+    }
+
+    const REPLAY: &[u8] = include_bytes!("../../tests/fixtures/duel.yrp");
+    // What an observer receives in the duel of `duel.yrp`, ends with its replay.
+    const MESSAGES: &[u8] = include_bytes!("../../tests/fixtures/duel.stoc");
+
+    #[test]
+    fn log_from_replay() {
+        let replay = Replay::from_bytes(REPLAY).unwrap();
+        let log = DuelLog::from_replay(&replay, MESSAGES);
+        let info = log.info.as_ref().unwrap();
+        assert_eq!(info.players.iter().map(|player| player.name.as_str()).collect::<Vec<_>>(), ["Alice", "Bob"]);
+        assert_eq!(info.seed, 1234567);
+
+        let events: Vec<_> = log.events.iter().map(|event| &event.event).collect();
+        assert_eq!(events.len(), 12);
+        assert!(matches!(events[0], Event::Start { life_points: [8000, 8000] }));
+        let monster = LocationInfo { controller: Netplayer::Player1, location: Location::MZone as u8, sequence: 2, position: Position::FaceupAttack as u8 };
+        assert!(events.contains(&&Event::Summon { card: Some(89631139), location: monster }));
+        assert!(events.contains(&&Event::Chain { card: Some(89631139), chain_link: 1, location: monster, desc: 89631139 << 4 }));
+        assert!(events.contains(&&Event::ChainSolved { chain_link: 1 }));
+        assert_eq!(log.events[10], DuelEvent { turn: 1, phase: Some(Phase::BattleStart), actor: Some(Netplayer::Player2), event: Event::Damage { amount: 3000 } });
+        assert_eq!(*events[11], Event::Win { winner: Some(Netplayer::Player1), reason: 0 });
+
+        // Info is also read from the replay frame in stream.
+        let mut recorder = DuelEventRecorder::new();
+        recorder.record_stream(MESSAGES);
+        assert_eq!(recorder.into_log(None), log);
+    }
+}
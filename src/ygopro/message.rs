@@ -3,10 +3,12 @@ mod mapped_struct;
 mod constants;
 mod utils;
 mod greedy_vector;
+mod counted_vector;
 #[doc(inline)] pub use mapped_struct::*;
 #[doc(inline)] pub use constants::*;
 #[doc(inline)] pub use utils::*;
 pub use greedy_vector::*;
+pub use counted_vector::*;
 
 pub mod ctos;
 pub mod stoc;
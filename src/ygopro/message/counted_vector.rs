@@ -0,0 +1,135 @@
+use std::marker::PhantomData;
+use serde::ser::Error as SerializeError;
+use serde::ser::Serializer;
+use serde::ser::SerializeTuple;
+use serde::ser::Serialize;
+use serde::de::Error;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::de::Deserialize;
+use serde::de::Deserializer;
+
+// ----------------------------------------------------------------------------------------------------
+//  CountedVector
+// ----------------------------------------------------------------------------------------------------
+/// Vector with a leading count in type `C`, which is how ygopro writes most lists.
+///
+/// Different from [GreedyVector](super::GreedyVector), it can be put in middle of a struct.
+/// For human readable formats (json), the count is omitted, only a plain array is written.
+///
+/// #### Example
+/// ```
+/// #[derive(Serialize, Deserialize)]
+/// pub struct ShuffleHand {
+///     pub player: Netplayer,
+///     #[serde(with = "CountedVector::<u8>")]
+///     pub cards: Vec<u32>
+/// }
+/// ```
+// ----------------------------------------------------------------------------------------------------
+pub trait CountedVector<'a, C>: Sized {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer;
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a>;
+}
+
+macro_rules! counted_vector {
+    ($($count:ty),+) => {
+        $(
+            impl<'a, T> CountedVector<'a, $count> for Vec<T> where T: Serialize + Deserialize<'a> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+                    if serializer.is_human_readable() { return serializer.collect_seq(self.iter()); }
+                    let count = <$count>::try_from(self.len()).map_err(|_| S::Error::custom(concat!("too many elements for a ", stringify!($count), " count")))?;
+                    let mut seq = serializer.serialize_tuple(self.len() + 1)?;
+                    seq.serialize_element(&count)?;
+                    for elem in &self[..] {
+                        seq.serialize_element(elem)?;
+                    }
+                    seq.end()
+                }
+
+                fn deserialize<D>(deserializer: D) -> Result<Vec<T>, D::Error> where D: Deserializer<'a> {
+                    struct CountedVisitor<T> { element: PhantomData<T> }
+
+                    impl<'a, T> Visitor<'a> for CountedVisitor<T> where T: Deserialize<'a> {
+                        type Value = Vec<T>;
+                        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                            formatter.write_str(concat!("a ", stringify!($count), " count followed by values"))
+                        }
+
+                        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<T>, A::Error> where A: SeqAccess<'a> {
+                            let count: $count = seq.next_element()?.ok_or_else(|| Error::invalid_length(0, &self))?;
+                            // Don't trust count to allocate.
+                            let mut arr = Vec::with_capacity((count as usize).min(256));
+                            for index in 0..count as usize {
+                                arr.push(seq.next_element()?.ok_or_else(|| Error::invalid_length(index + 1, &self))?);
+                            }
+                            Ok(arr)
+                        }
+                    }
+
+                    let visitor = CountedVisitor { element: PhantomData };
+                    deserializer.deserialize_tuple(<$count>::MAX as usize + 1, visitor)
+                }
+            }
+        )+
+    }
+}
+
+counted_vector! { u8, u16, u32 }
+
+// ----------------------------------------------------------------------------------------------------
+//  CountedString
+// ----------------------------------------------------------------------------------------------------
+/// UTF-8 string with a leading byte length in type `C`, and a `\0` in the end.
+///
+/// The length don't count the tailing `\0`.
+// ----------------------------------------------------------------------------------------------------
+pub trait CountedString<'a, C>: Sized {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer;
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a>;
+}
+
+macro_rules! counted_string {
+    ($($count:ty),+) => {
+        $(
+            impl<'a> CountedString<'a, $count> for String {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+                    if serializer.is_human_readable() { return serializer.serialize_str(self); }
+                    let count = <$count>::try_from(self.len()).map_err(|_| S::Error::custom(concat!("string too long for a ", stringify!($count), " length")))?;
+                    let mut seq = serializer.serialize_tuple(self.len() + 2)?;
+                    seq.serialize_element(&count)?;
+                    for byte in self.as_bytes() {
+                        seq.serialize_element(byte)?;
+                    }
+                    seq.serialize_element(&0u8)?;
+                    seq.end()
+                }
+
+                fn deserialize<D>(deserializer: D) -> Result<String, D::Error> where D: Deserializer<'a> {
+                    struct CountedStringVisitor;
+
+                    impl<'a> Visitor<'a> for CountedStringVisitor {
+                        type Value = String;
+                        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                            formatter.write_str(concat!("a ", stringify!($count), " length followed by a c string"))
+                        }
+
+                        fn visit_seq<A>(self, mut seq: A) -> Result<String, A::Error> where A: SeqAccess<'a> {
+                            let count: $count = seq.next_element()?.ok_or_else(|| Error::invalid_length(0, &self))?;
+                            let mut bytes = Vec::with_capacity((count as usize).min(256));
+                            for index in 0..count as usize {
+                                bytes.push(seq.next_element::<u8>()?.ok_or_else(|| Error::invalid_length(index + 1, &self))?);
+                            }
+                            seq.next_element::<u8>()?.ok_or_else(|| Error::invalid_length(count as usize + 1, &self))?;
+                            String::from_utf8(bytes).map_err(Error::custom)
+                        }
+                    }
+
+                    deserializer.deserialize_tuple(<$count>::MAX as usize + 2, CountedStringVisitor)
+                }
+            }
+        )+
+    }
+}
+
+counted_string! { u8, u16 }
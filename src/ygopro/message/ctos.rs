@@ -10,7 +10,11 @@ use serde::Deserialize;
 use num_enum::TryFromPrimitive;
 use num_enum::IntoPrimitive;
 
+use crate::ygopro::Race;
+use crate::ygopro::Position;
+use crate::ygopro::Attribute;
 use crate::ygopro::Netplayer;
+use crate::ygopro::message::gm;
 use crate::ygopro::message::HostInfo;
 use crate::ygopro::message::GreedyVector;
 
@@ -37,6 +41,173 @@ pub enum MessageType {
     RequestField = 48
 }
 
+// ----------------------------------------------------------------------------------------------------
+//  Response
+// ----------------------------------------------------------------------------------------------------
+/// Answer of a duelist to a select game message.
+/// 
+/// Layout of `data` depends on which select message it answers, 
+/// use [interpret](Response#method.interpret) to read it.
+// ----------------------------------------------------------------------------------------------------
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Response {
+    #[serde(with = "GreedyVector::<255>")]
+    pub data: Vec<u8>
+}
+
+/// Command chosen in [SelectIdlecmd](gm::SelectIdlecmd).
+#[derive(Copy, Clone, TryFromPrimitive, IntoPrimitive, Eq, PartialEq, Debug, Serialize)]
+#[repr(u8)]
+pub enum IdleCommand {
+    Summon = 0,
+    SpecialSummon = 1,
+    Reposition = 2,
+    MonsterSet = 3,
+    SpellSet = 4,
+    Activate = 5,
+    ToBattlePhase = 6,
+    ToEndPhase = 7,
+    Shuffle = 8
+}
+
+/// Command chosen in [SelectBattlecmd](gm::SelectBattlecmd).
+#[derive(Copy, Clone, TryFromPrimitive, IntoPrimitive, Eq, PartialEq, Debug, Serialize)]
+#[repr(u8)]
+pub enum BattleCommand {
+    Activate = 0,
+    Attack = 1,
+    ToMain2 = 2,
+    ToEndPhase = 3
+}
+
+/// A place chosen in [SelectPlace](gm::SelectPlace) or [SelectDisfield](gm::SelectDisfield).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct SelectedPlace {
+    pub player: u8,
+    pub location: u8,
+    pub sequence: u8
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  ResponseDecision
+// ----------------------------------------------------------------------------------------------------
+/// What a [Response] actually means.
+/// 
+/// Indexes point to the list in the select message it answers.
+// ----------------------------------------------------------------------------------------------------
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub enum ResponseDecision {
+    /// Answer to [SelectIdlecmd](gm::SelectIdlecmd). `index` is in the list of the `command`.
+    IdleCommand { command: IdleCommand, index: u16 },
+    /// Answer to [SelectBattlecmd](gm::SelectBattlecmd). `index` is in the list of the `command`.
+    BattleCommand { command: BattleCommand, index: u16 },
+    /// Answer to [SelectEffectyn](gm::SelectEffectyn) or [SelectYesno](gm::SelectYesno).
+    YesNo(bool),
+    /// Answer to [SelectOption](gm::SelectOption).
+    Option(u32),
+    /// Answer to [SelectCard](gm::SelectCard), [SelectTribute](gm::SelectTribute), 
+    /// [SelectSum](gm::SelectSum) or [SelectUnselectCard](gm::SelectUnselectCard).
+    Cards(Vec<u8>),
+    /// Cancel a card selection, or finish a [SelectUnselectCard](gm::SelectUnselectCard).
+    Cancel,
+    /// Answer to [SelectChain](gm::SelectChain). `None` for not chaining.
+    Chain(Option<u32>),
+    /// Answer to [SelectPlace](gm::SelectPlace) or [SelectDisfield](gm::SelectDisfield).
+    Places(Vec<SelectedPlace>),
+    /// Answer to [SelectPosition](gm::SelectPosition).
+    Position(Position),
+    /// Answer to [SelectCounter](gm::SelectCounter), counter count removed from each card.
+    Counters(Vec<u16>),
+    /// Answer to [SortCard](gm::SortCard) or [SortChain](gm::SortChain). `None` for default order.
+    Sort(Option<Vec<u8>>),
+    AnnounceRace(Race),
+    AnnounceAttribute(Attribute),
+    AnnounceCard(u32),
+    /// Index in [AnnounceNumber](gm::AnnounceNumber).
+    AnnounceNumber(u32),
+    /// Answer to [RockPaperScissors](gm::RockPaperScissors).
+    Hand(u8),
+    /// Response can't be recognized with the select message.
+    Unknown(Vec<u8>)
+}
+
+impl ResponseDecision {
+    /// Check if a game message asks receiver for a [Response].
+    pub fn is_select(kind: gm::MessageType) -> bool {
+        use gm::MessageType as GM;
+        matches!(kind, GM::SelectBattlecmd | GM::SelectIdlecmd | GM::SelectEffectyn | GM::SelectYesno | GM::SelectOption
+                     | GM::SelectCard | GM::SelectChain | GM::SelectPlace | GM::SelectPosition | GM::SelectTribute
+                     | GM::SortChain | GM::SelectCounter | GM::SelectSum | GM::SelectDisfield | GM::SortCard
+                     | GM::SelectUnselectCard | GM::RockPaperScissors | GM::AnnounceRace | GM::AnnounceAttrib
+                     | GM::AnnounceCard | GM::AnnounceNumber)
+    }
+}
+
+impl Response {
+    /// Read response as a single i32, which is how most simple selects answered.
+    fn as_i32(&self) -> Option<i32> {
+        if self.data.len() < 4 { return None; }
+        Some(i32::from_le_bytes([self.data[0], self.data[1], self.data[2], self.data[3]]))
+    }
+
+    /// `-1` is used for cancel on byte responses.
+    fn is_cancel(&self) -> bool {
+        self.data.len() == 4 && self.as_i32() == Some(-1)
+    }
+
+    // ----------------------------------------------------------------------------------------------------
+    //  interpret
+    // ----------------------------------------------------------------------------------------------------
+    /// Interpret this response as answer of a select message.
+    /// 
+    /// #### Arguments
+    /// * `pending`: type of last select message sent to the duelist. 
+    /// 
+    /// #### Return
+    /// [ResponseDecision::Unknown] if data can't fit the select message.
+    // ----------------------------------------------------------------------------------------------------
+    pub fn interpret(&self, pending: gm::MessageType) -> ResponseDecision {
+        use gm::MessageType as GM;
+        let value = self.as_i32();
+        let decision = match pending {
+            GM::SelectIdlecmd => value.and_then(|value| IdleCommand::try_from((value & 0xffff) as u8).ok()
+                .map(|command| ResponseDecision::IdleCommand { command, index: (value >> 16) as u16 })),
+            GM::SelectBattlecmd => value.and_then(|value| BattleCommand::try_from((value & 0xffff) as u8).ok()
+                .map(|command| ResponseDecision::BattleCommand { command, index: (value >> 16) as u16 })),
+            GM::SelectEffectyn | GM::SelectYesno => value.map(|value| ResponseDecision::YesNo(value != 0)),
+            GM::SelectOption => value.map(|value| ResponseDecision::Option(value as u32)),
+            GM::SelectCard | GM::SelectTribute | GM::SelectSum | GM::SelectUnselectCard => {
+                if self.is_cancel() { Some(ResponseDecision::Cancel) }
+                else {
+                    self.data.split_first()
+                        .filter(|(count, indexes)| indexes.len() >= **count as usize)
+                        .map(|(count, indexes)| ResponseDecision::Cards(indexes[..*count as usize].to_vec()))
+                }
+            },
+            GM::SelectChain => value.map(|value| ResponseDecision::Chain(if value < 0 { None } else { Some(value as u32) })),
+            GM::SelectPlace | GM::SelectDisfield => {
+                if self.data.len() < 3 { None }
+                else {
+                    Some(ResponseDecision::Places(self.data.chunks_exact(3).map(|place| SelectedPlace { player: place[0], location: place[1], sequence: place[2] }).collect()))
+                }
+            },
+            GM::SelectPosition => value.and_then(|value| Position::try_from(value as u8).ok()).map(ResponseDecision::Position),
+            GM::SelectCounter => Some(ResponseDecision::Counters(self.data.chunks_exact(2).map(|count| u16::from_le_bytes([count[0], count[1]])).collect())),
+            GM::SortCard | GM::SortChain => {
+                if self.is_cancel() { Some(ResponseDecision::Sort(None)) }
+                else { Some(ResponseDecision::Sort(Some(self.data.clone()))) }
+            },
+            GM::AnnounceRace => value.map(|value| ResponseDecision::AnnounceRace(Race::from_bits_truncate(value as u32))),
+            GM::AnnounceAttrib => value.map(|value| ResponseDecision::AnnounceAttribute(Attribute::from_bits_truncate(value as u32))),
+            GM::AnnounceCard => value.map(|value| ResponseDecision::AnnounceCard(value as u32)),
+            GM::AnnounceNumber => value.map(|value| ResponseDecision::AnnounceNumber(value as u32)),
+            GM::RockPaperScissors => value.map(|value| ResponseDecision::Hand(value as u8)),
+            _ => None
+        };
+        decision.unwrap_or_else(|| ResponseDecision::Unknown(self.data.clone()))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct UpdateDeck {
     pub deck: crate::ygopro::data::Deck
@@ -113,3 +284,52 @@ pub struct RequestField;
 pub fn generate_message_type(_type: MessageType) -> crate::ygopro::message::MessageType {
     crate::ygopro::message::MessageType::CTOS(_type)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ygopro::message::deserialize_struct_by_type;
+
+    /// Read a [Response] from a whole ctos packet, as sent by ygopro client.
+    fn response(packet: &[u8]) -> Response {
+        assert_eq!(u16::from_le_bytes([packet[0], packet[1]]) as usize, packet.len() - 2);
+        assert_eq!(packet[2], MessageType::Response as u8);
+        let message = deserialize_struct_by_type(crate::ygopro::message::MessageType::CTOS(MessageType::Response), &packet[3..]).unwrap();
+        let response = message.downcast_ref::<Response>().unwrap();
+        Response { data: response.data.clone() }
+    }
+
+    #[test]
+    fn interpret_commands() {
+        use gm::MessageType as GM;
+        // Normal summon the 3rd summonable card.
+        let summon = response(&[0x05, 0x00, 0x01, 0x00, 0x00, 0x02, 0x00]);
+        assert_eq!(summon.interpret(GM::SelectIdlecmd), ResponseDecision::IdleCommand { command: IdleCommand::Summon, index: 2 });
+        // Go to end phase.
+        let end_phase = response(&[0x05, 0x00, 0x01, 0x07, 0x00, 0x00, 0x00]);
+        assert_eq!(end_phase.interpret(GM::SelectIdlecmd), ResponseDecision::IdleCommand { command: IdleCommand::ToEndPhase, index: 0 });
+        // Attack with the 2nd attacker.
+        let attack = response(&[0x05, 0x00, 0x01, 0x01, 0x00, 0x01, 0x00]);
+        assert_eq!(attack.interpret(GM::SelectBattlecmd), ResponseDecision::BattleCommand { command: BattleCommand::Attack, index: 1 });
+        assert_eq!(attack.interpret(GM::SelectYesno), ResponseDecision::YesNo(true));
+    }
+
+    #[test]
+    fn interpret_selections() {
+        use gm::MessageType as GM;
+        let cards = response(&[0x04, 0x00, 0x01, 0x02, 0x00, 0x03]);
+        assert_eq!(cards.interpret(GM::SelectCard), ResponseDecision::Cards(vec![0, 3]));
+        let cancel = response(&[0x05, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(cancel.interpret(GM::SelectCard), ResponseDecision::Cancel);
+        assert_eq!(cancel.interpret(GM::SelectChain), ResponseDecision::Chain(None));
+        assert_eq!(cancel.interpret(GM::SortCard), ResponseDecision::Sort(None));
+        let place = response(&[0x04, 0x00, 0x01, 0x00, 0x04, 0x02]);
+        assert_eq!(place.interpret(GM::SelectPlace), ResponseDecision::Places(vec![SelectedPlace { player: 0, location: 4, sequence: 2 }]));
+        let position = response(&[0x05, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00]);
+        assert_eq!(position.interpret(GM::SelectPosition), ResponseDecision::Position(Position::FacedownDefense));
+        // Count claims more cards than sent.
+        let broken = response(&[0x03, 0x00, 0x01, 0x03, 0x00]);
+        assert_eq!(broken.interpret(GM::SelectCard), ResponseDecision::Unknown(vec![3, 0]));
+        assert_eq!(position.interpret(GM::Draw), ResponseDecision::Unknown(vec![8, 0, 0, 0]));
+    }
+}
@@ -12,13 +12,19 @@ use serde::de::Deserializer;
 use serde::de::Visitor;
 use serde::de::SeqAccess;
 
-use crate::ygopro::Location;
+use byteorder::LittleEndian;
+use byteorder::ReadBytesExt;
+
 use crate::ygopro::Netplayer;
-use crate::ygopro::Position;
+use crate::ygopro::Query;
+use crate::ygopro::Race;
+use crate::ygopro::Reason;
+use crate::ygopro::Attribute;
 use crate::ygopro::message::Struct;
 use crate::ygopro::message::MappedStruct;
-use crate::ygopro::message::Empty;
 use crate::ygopro::message::GreedyVector;
+use crate::ygopro::message::CountedVector;
+use crate::ygopro::message::CountedString;
 
 pub type MessageType = crate::ygopro::GameMessage;
 
@@ -76,100 +82,99 @@ impl<'de> Deserialize<'de> for GameMessage {
             fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error> where V: SeqAccess<'de> {
                 let kind = seq.next_element()?.ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
                 let message = match kind {
-                       MessageType::ShuffleDeck        => self.deserialize_message::<ShuffleDeck,        _>(&mut seq)?,
-                    // MessageType::Retry              => self.deserialize_message::<Retry,              _>(&mut seq)?,
-                       MessageType::Hint               => self.deserialize_message::<Hint,               _>(&mut seq)?,
-                    // MessageType::Waiting            => self.deserialize_message::<Waiting,            _>(&mut seq)?,
-                       MessageType::Start              => self.deserialize_message::<Start,              _>(&mut seq)?,
-                       MessageType::Win                => self.deserialize_message::<Win,                _>(&mut seq)?,
-                    // MessageType::UpdateData         => self.deserialize_message::<UpdateData,         _>(&mut seq)?,
-                    // MessageType::UpdateCard         => self.deserialize_message::<UpdateCard,         _>(&mut seq)?,
-                    // MessageType::RequestDeck        => self.deserialize_message::<RequestDeck,        _>(&mut seq)?,
-                    // MessageType::SelectBattlecmd    => self.deserialize_message::<SelectBattlecmd,    _>(&mut seq)?,
-                    // MessageType::SelectIdlecmd      => self.deserialize_message::<SelectIdlecmd,      _>(&mut seq)?,
-                    // MessageType::SelectEffectyn     => self.deserialize_message::<SelectEffectyn,     _>(&mut seq)?,
-                    // MessageType::SelectYesno        => self.deserialize_message::<SelectYesno,        _>(&mut seq)?,
-                    // MessageType::SelectOption       => self.deserialize_message::<SelectOption,       _>(&mut seq)?,
-                    // MessageType::SelectCard         => self.deserialize_message::<SelectCard,         _>(&mut seq)?,
-                    // MessageType::SelectChain        => self.deserialize_message::<SelectChain,        _>(&mut seq)?,
-                    // MessageType::SelectPlace        => self.deserialize_message::<SelectPlace,        _>(&mut seq)?,
-                    // MessageType::SelectPosition     => self.deserialize_message::<SelectPosition,     _>(&mut seq)?,
-                    // MessageType::SelectTribute      => self.deserialize_message::<SelectTribute,      _>(&mut seq)?,
-                    // MessageType::SortChain          => self.deserialize_message::<SortChain,          _>(&mut seq)?,
-                    // MessageType::SelectCounter      => self.deserialize_message::<SelectCounter,      _>(&mut seq)?,
-                    // MessageType::SelectSum          => self.deserialize_message::<SelectSum,          _>(&mut seq)?,
-                    // MessageType::SelectDisfield     => self.deserialize_message::<SelectDisfield,     _>(&mut seq)?,
-                    // MessageType::SortCard           => self.deserialize_message::<SortCard,           _>(&mut seq)?,
-                    // MessageType::SelectUnselectCard => self.deserialize_message::<SelectUnselectCard, _>(&mut seq)?,
-                    // MessageType::ConfirmDecktop     => self.deserialize_message::<ConfirmDecktop,     _>(&mut seq)?,
-                       MessageType::ConfirmCards       => self.deserialize_message::<ConfirmCards,       _>(&mut seq)?,
-                    // MessageType::ShuffleHand        => self.deserialize_message::<ShuffleHand,        _>(&mut seq)?,
-                    // MessageType::RefreshDeck        => self.deserialize_message::<RefreshDeck,        _>(&mut seq)?,
-                    // MessageType::SwapGraveDeck      => self.deserialize_message::<SwapGraveDeck,      _>(&mut seq)?,
-                    // MessageType::ShuffleSetCard     => self.deserialize_message::<ShuffleSetCard,     _>(&mut seq)?,
-                    // MessageType::ReverseDeck        => self.deserialize_message::<ReverseDeck,        _>(&mut seq)?,
-                    // MessageType::DeckTop            => self.deserialize_message::<DeckTop,            _>(&mut seq)?,
-                    // MessageType::MsgShuffleExtra    => self.deserialize_message::<MsgShuffleExtra,    _>(&mut seq)?,
-                       MessageType::NewTurn            => self.deserialize_message::<NewTurn,            _>(&mut seq)?,
-                    // MessageType::NewPhase           => self.deserialize_message::<NewPhase,           _>(&mut seq)?,
-                    // MessageType::ConfirmExtratop    => self.deserialize_message::<ConfirmExtratop,    _>(&mut seq)?,
-                    // MessageType::Move               => self.deserialize_message::<Move,               _>(&mut seq)?,
-                       MessageType::PosChange          => self.deserialize_message::<PosChange,          _>(&mut seq)?,
-                    // MessageType::Set                => self.deserialize_message::<Set,                _>(&mut seq)?,
-                    // MessageType::Swap               => self.deserialize_message::<Swap,               _>(&mut seq)?,
-                    // MessageType::FieldDisabled      => self.deserialize_message::<FieldDisabled,      _>(&mut seq)?,
-                       MessageType::Summoning          => self.deserialize_message::<Summoning,          _>(&mut seq)?,
-                    // MessageType::Summoned           => self.deserialize_message::<Summoned,           _>(&mut seq)?,
-                       MessageType::Spsummoning        => self.deserialize_message::<Spsummoning,        _>(&mut seq)?,
-                    // MessageType::Spsummoned         => self.deserialize_message::<Spsummoned,         _>(&mut seq)?,
-                    // MessageType::Flipsummoning      => self.deserialize_message::<Flipsummoning,      _>(&mut seq)?,
-                    // MessageType::Flipsummoned       => self.deserialize_message::<Flipsummoned,       _>(&mut seq)?,
-                       MessageType::Chaining           => self.deserialize_message::<Chaining,           _>(&mut seq)?,
-                       MessageType::Chained            => self.deserialize_message::<Chained,            _>(&mut seq)?,
-                       MessageType::ChainSolving       => self.deserialize_message::<ChainSolving,       _>(&mut seq)?,
-                       MessageType::ChainSolved        => self.deserialize_message::<ChainSolved,        _>(&mut seq)?,
-                       MessageType::ChainEnd           => self.deserialize_message::<ChainEnd,           _>(&mut seq)?,
-                       MessageType::ChainNegated       => self.deserialize_message::<ChainNegated,       _>(&mut seq)?,
-                       MessageType::ChainDisabled      => self.deserialize_message::<ChainDisabled,      _>(&mut seq)?,
-                       MessageType::CardSelected       => self.deserialize_message::<CardSelected,       _>(&mut seq)?,
-                    // MessageType::RandomSelected     => self.deserialize_message::<RandomSelected,     _>(&mut seq)?,
-                    // MessageType::BecomeTarget       => self.deserialize_message::<BecomeTarget,       _>(&mut seq)?,
-                    // MessageType::Draw               => self.deserialize_message::<Draw,               _>(&mut seq)?,
-                       MessageType::Damage             => self.deserialize_message::<Damage,             _>(&mut seq)?,
-                       MessageType::Recover            => self.deserialize_message::<Recover,            _>(&mut seq)?,
-                    // MessageType::Equip              => self.deserialize_message::<Equip,              _>(&mut seq)?,
-                    // MessageType::Lpupdate           => self.deserialize_message::<Lpupdate,           _>(&mut seq)?,
-                    // MessageType::Unequip            => self.deserialize_message::<Unequip,            _>(&mut seq)?,
-                    // MessageType::CardTarget         => self.deserialize_message::<CardTarget,         _>(&mut seq)?,
-                    // MessageType::CancelTarget       => self.deserialize_message::<CancelTarget,       _>(&mut seq)?,
-                       MessageType::PayLpcost          => self.deserialize_message::<PayLpcost,          _>(&mut seq)?,
-                    // MessageType::AddCounter         => self.deserialize_message::<AddCounter,         _>(&mut seq)?,
-                    // MessageType::RemoveCounter      => self.deserialize_message::<RemoveCounter,      _>(&mut seq)?,
-                    // MessageType::Attack             => self.deserialize_message::<Attack,             _>(&mut seq)?,
-                    // MessageType::Battle             => self.deserialize_message::<Battle,             _>(&mut seq)?,
-                    // MessageType::AttackDisabled     => self.deserialize_message::<AttackDisabled,     _>(&mut seq)?,
-                    // MessageType::DamageStepStart    => self.deserialize_message::<DamageStepStart,    _>(&mut seq)?,
-                    // MessageType::DamageStepEnd      => self.deserialize_message::<DamageStepEnd,      _>(&mut seq)?,
-                    // MessageType::MissedEffect       => self.deserialize_message::<MissedEffect,       _>(&mut seq)?,
-                    // MessageType::BeChainTarget      => self.deserialize_message::<BeChainTarget,      _>(&mut seq)?,
-                    // MessageType::CreateRelation     => self.deserialize_message::<CreateRelation,     _>(&mut seq)?,
-                    // MessageType::ReleaseRelation    => self.deserialize_message::<ReleaseRelation,    _>(&mut seq)?,
-                    // MessageType::TossCoin           => self.deserialize_message::<TossCoin,           _>(&mut seq)?,
-                    // MessageType::TossDice           => self.deserialize_message::<TossDice,           _>(&mut seq)?,
-                    // MessageType::RockPaperScissors  => self.deserialize_message::<RockPaperScissors,  _>(&mut seq)?,
-                    // MessageType::HandRes            => self.deserialize_message::<HandRes,            _>(&mut seq)?,
-                    // MessageType::AnnounceRace       => self.deserialize_message::<AnnounceRace,       _>(&mut seq)?,
-                    // MessageType::AnnounceAttrib     => self.deserialize_message::<AnnounceAttrib,     _>(&mut seq)?,
-                    // MessageType::AnnounceCard       => self.deserialize_message::<AnnounceCard,       _>(&mut seq)?,
-                    // MessageType::AnnounceNumber     => self.deserialize_message::<AnnounceNumber,     _>(&mut seq)?,
-                    // MessageType::CardHint           => self.deserialize_message::<CardHint,           _>(&mut seq)?,
-                    // MessageType::TagSwap            => self.deserialize_message::<TagSwap,            _>(&mut seq)?,
-                    // MessageType::ReloadField        => self.deserialize_message::<ReloadField,        _>(&mut seq)?,
-                    // MessageType::AiName             => self.deserialize_message::<AiName,             _>(&mut seq)?,
-                    // MessageType::ShowHint           => self.deserialize_message::<ShowHint,           _>(&mut seq)?,
-                       MessageType::MatchKill          => self.deserialize_message::<MatchKill,          _>(&mut seq)?,
-                    // MessageType::CustomMsg          => self.deserialize_message::<CustomMsg,          _>(&mut seq)?,
-                    _                               => self.deserialize_message::<Empty,         _>(&mut seq)?,
+                    MessageType::ShuffleDeck        => self.deserialize_message::<ShuffleDeck,        _>(&mut seq)?,
+                    MessageType::Retry              => self.deserialize_message::<Retry,              _>(&mut seq)?,
+                    MessageType::Hint               => self.deserialize_message::<Hint,               _>(&mut seq)?,
+                    MessageType::Waiting            => self.deserialize_message::<Waiting,            _>(&mut seq)?,
+                    MessageType::Start              => self.deserialize_message::<Start,              _>(&mut seq)?,
+                    MessageType::Win                => self.deserialize_message::<Win,                _>(&mut seq)?,
+                    MessageType::UpdateData         => self.deserialize_message::<UpdateData,         _>(&mut seq)?,
+                    MessageType::UpdateCard         => self.deserialize_message::<UpdateCard,         _>(&mut seq)?,
+                    MessageType::RequestDeck        => self.deserialize_message::<RequestDeck,        _>(&mut seq)?,
+                    MessageType::SelectBattlecmd    => self.deserialize_message::<SelectBattlecmd,    _>(&mut seq)?,
+                    MessageType::SelectIdlecmd      => self.deserialize_message::<SelectIdlecmd,      _>(&mut seq)?,
+                    MessageType::SelectEffectyn     => self.deserialize_message::<SelectEffectyn,     _>(&mut seq)?,
+                    MessageType::SelectYesno        => self.deserialize_message::<SelectYesno,        _>(&mut seq)?,
+                    MessageType::SelectOption       => self.deserialize_message::<SelectOption,       _>(&mut seq)?,
+                    MessageType::SelectCard         => self.deserialize_message::<SelectCard,         _>(&mut seq)?,
+                    MessageType::SelectChain        => self.deserialize_message::<SelectChain,        _>(&mut seq)?,
+                    MessageType::SelectPlace        => self.deserialize_message::<SelectPlace,        _>(&mut seq)?,
+                    MessageType::SelectPosition     => self.deserialize_message::<SelectPosition,     _>(&mut seq)?,
+                    MessageType::SelectTribute      => self.deserialize_message::<SelectTribute,      _>(&mut seq)?,
+                    MessageType::SortChain          => self.deserialize_message::<SortChain,          _>(&mut seq)?,
+                    MessageType::SelectCounter      => self.deserialize_message::<SelectCounter,      _>(&mut seq)?,
+                    MessageType::SelectSum          => self.deserialize_message::<SelectSum,          _>(&mut seq)?,
+                    MessageType::SelectDisfield     => self.deserialize_message::<SelectDisfield,     _>(&mut seq)?,
+                    MessageType::SortCard           => self.deserialize_message::<SortCard,           _>(&mut seq)?,
+                    MessageType::SelectUnselectCard => self.deserialize_message::<SelectUnselectCard, _>(&mut seq)?,
+                    MessageType::ConfirmDecktop     => self.deserialize_message::<ConfirmDecktop,     _>(&mut seq)?,
+                    MessageType::ConfirmCards       => self.deserialize_message::<ConfirmCards,       _>(&mut seq)?,
+                    MessageType::ShuffleHand        => self.deserialize_message::<ShuffleHand,        _>(&mut seq)?,
+                    MessageType::RefreshDeck        => self.deserialize_message::<RefreshDeck,        _>(&mut seq)?,
+                    MessageType::SwapGraveDeck      => self.deserialize_message::<SwapGraveDeck,      _>(&mut seq)?,
+                    MessageType::ShuffleSetCard     => self.deserialize_message::<ShuffleSetCard,     _>(&mut seq)?,
+                    MessageType::ReverseDeck        => self.deserialize_message::<ReverseDeck,        _>(&mut seq)?,
+                    MessageType::DeckTop            => self.deserialize_message::<DeckTop,            _>(&mut seq)?,
+                    MessageType::MsgShuffleExtra    => self.deserialize_message::<MsgShuffleExtra,    _>(&mut seq)?,
+                    MessageType::NewTurn            => self.deserialize_message::<NewTurn,            _>(&mut seq)?,
+                    MessageType::NewPhase           => self.deserialize_message::<NewPhase,           _>(&mut seq)?,
+                    MessageType::ConfirmExtratop    => self.deserialize_message::<ConfirmExtratop,    _>(&mut seq)?,
+                    MessageType::Move               => self.deserialize_message::<Move,               _>(&mut seq)?,
+                    MessageType::PosChange          => self.deserialize_message::<PosChange,          _>(&mut seq)?,
+                    MessageType::Set                => self.deserialize_message::<Set,                _>(&mut seq)?,
+                    MessageType::Swap               => self.deserialize_message::<Swap,               _>(&mut seq)?,
+                    MessageType::FieldDisabled      => self.deserialize_message::<FieldDisabled,      _>(&mut seq)?,
+                    MessageType::Summoning          => self.deserialize_message::<Summoning,          _>(&mut seq)?,
+                    MessageType::Summoned           => self.deserialize_message::<Summoned,           _>(&mut seq)?,
+                    MessageType::Spsummoning        => self.deserialize_message::<Spsummoning,        _>(&mut seq)?,
+                    MessageType::Spsummoned         => self.deserialize_message::<Spsummoned,         _>(&mut seq)?,
+                    MessageType::Flipsummoning      => self.deserialize_message::<Flipsummoning,      _>(&mut seq)?,
+                    MessageType::Flipsummoned       => self.deserialize_message::<Flipsummoned,       _>(&mut seq)?,
+                    MessageType::Chaining           => self.deserialize_message::<Chaining,           _>(&mut seq)?,
+                    MessageType::Chained            => self.deserialize_message::<Chained,            _>(&mut seq)?,
+                    MessageType::ChainSolving       => self.deserialize_message::<ChainSolving,       _>(&mut seq)?,
+                    MessageType::ChainSolved        => self.deserialize_message::<ChainSolved,        _>(&mut seq)?,
+                    MessageType::ChainEnd           => self.deserialize_message::<ChainEnd,           _>(&mut seq)?,
+                    MessageType::ChainNegated       => self.deserialize_message::<ChainNegated,       _>(&mut seq)?,
+                    MessageType::ChainDisabled      => self.deserialize_message::<ChainDisabled,      _>(&mut seq)?,
+                    MessageType::CardSelected       => self.deserialize_message::<CardSelected,       _>(&mut seq)?,
+                    MessageType::RandomSelected     => self.deserialize_message::<RandomSelected,     _>(&mut seq)?,
+                    MessageType::BecomeTarget       => self.deserialize_message::<BecomeTarget,       _>(&mut seq)?,
+                    MessageType::Draw               => self.deserialize_message::<Draw,               _>(&mut seq)?,
+                    MessageType::Damage             => self.deserialize_message::<Damage,             _>(&mut seq)?,
+                    MessageType::Recover            => self.deserialize_message::<Recover,            _>(&mut seq)?,
+                    MessageType::Equip              => self.deserialize_message::<Equip,              _>(&mut seq)?,
+                    MessageType::Lpupdate           => self.deserialize_message::<Lpupdate,           _>(&mut seq)?,
+                    MessageType::Unequip            => self.deserialize_message::<Unequip,            _>(&mut seq)?,
+                    MessageType::CardTarget         => self.deserialize_message::<CardTarget,         _>(&mut seq)?,
+                    MessageType::CancelTarget       => self.deserialize_message::<CancelTarget,       _>(&mut seq)?,
+                    MessageType::PayLpcost          => self.deserialize_message::<PayLpcost,          _>(&mut seq)?,
+                    MessageType::AddCounter         => self.deserialize_message::<AddCounter,         _>(&mut seq)?,
+                    MessageType::RemoveCounter      => self.deserialize_message::<RemoveCounter,      _>(&mut seq)?,
+                    MessageType::Attack             => self.deserialize_message::<Attack,             _>(&mut seq)?,
+                    MessageType::Battle             => self.deserialize_message::<Battle,             _>(&mut seq)?,
+                    MessageType::AttackDisabled     => self.deserialize_message::<AttackDisabled,     _>(&mut seq)?,
+                    MessageType::DamageStepStart    => self.deserialize_message::<DamageStepStart,    _>(&mut seq)?,
+                    MessageType::DamageStepEnd      => self.deserialize_message::<DamageStepEnd,      _>(&mut seq)?,
+                    MessageType::MissedEffect       => self.deserialize_message::<MissedEffect,       _>(&mut seq)?,
+                    MessageType::BeChainTarget      => self.deserialize_message::<BeChainTarget,      _>(&mut seq)?,
+                    MessageType::CreateRelation     => self.deserialize_message::<CreateRelation,     _>(&mut seq)?,
+                    MessageType::ReleaseRelation    => self.deserialize_message::<ReleaseRelation,    _>(&mut seq)?,
+                    MessageType::TossCoin           => self.deserialize_message::<TossCoin,           _>(&mut seq)?,
+                    MessageType::TossDice           => self.deserialize_message::<TossDice,           _>(&mut seq)?,
+                    MessageType::RockPaperScissors  => self.deserialize_message::<RockPaperScissors,  _>(&mut seq)?,
+                    MessageType::HandRes            => self.deserialize_message::<HandRes,            _>(&mut seq)?,
+                    MessageType::AnnounceRace       => self.deserialize_message::<AnnounceRace,       _>(&mut seq)?,
+                    MessageType::AnnounceAttrib     => self.deserialize_message::<AnnounceAttrib,     _>(&mut seq)?,
+                    MessageType::AnnounceCard       => self.deserialize_message::<AnnounceCard,       _>(&mut seq)?,
+                    MessageType::AnnounceNumber     => self.deserialize_message::<AnnounceNumber,     _>(&mut seq)?,
+                    MessageType::CardHint           => self.deserialize_message::<CardHint,           _>(&mut seq)?,
+                    MessageType::TagSwap            => self.deserialize_message::<TagSwap,            _>(&mut seq)?,
+                    MessageType::ReloadField        => self.deserialize_message::<ReloadField,        _>(&mut seq)?,
+                    MessageType::AiName             => self.deserialize_message::<AiName,             _>(&mut seq)?,
+                    MessageType::ShowHint           => self.deserialize_message::<ShowHint,           _>(&mut seq)?,
+                    MessageType::MatchKill          => self.deserialize_message::<MatchKill,          _>(&mut seq)?,
+                    MessageType::CustomMsg          => self.deserialize_message::<CustomMsg,          _>(&mut seq)?,
                 }.ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
                 Ok(GameMessage { kind, message })
             }
@@ -178,11 +183,271 @@ impl<'de> Deserialize<'de> for GameMessage {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Struct)]
-// #[gm]
-pub struct ShuffleDeck {
-    pub player: crate::ygopro::Netplayer 
+// ----------------------------------------------------------------------------------------------------
+//  LocationInfo
+// ----------------------------------------------------------------------------------------------------
+/// Where a card is, as ygopro core writes in most messages. 
+/// 
+/// `location` and `position` are raw value, as they can't always be mapped to [Location](crate::ygopro::Location) and [Position](crate::ygopro::Position):
+/// * For a material, `location` is combined with [Location::Overlay](crate::ygopro::Location::Overlay), and `position` is index of the material.
+/// * For an empty place (e.g. direct attack target), all fields are `0`.
+// ----------------------------------------------------------------------------------------------------
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocationInfo {
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub position: u8
+}
+impl Struct for LocationInfo {}
+
+/// A card with its code and [LocationInfo].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocatedCard {
+    pub code: u32,
+    pub location: LocationInfo
+}
+impl Struct for LocatedCard {}
+
+/// A card with its code and place, without position.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CardInfo {
+    pub code: u32,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8
 }
+impl Struct for CardInfo {}
+
+/// Card code with this bit is public to all players, e.g. a drawn card revealed by effect.
+pub const PUBLIC_CODE_FLAG: u32 = 0x80000000;
+
+// ----------------------------------------------------------------------------------------------------
+//  reveal_code
+// ----------------------------------------------------------------------------------------------------
+/// Get actual card code in per-player messages like [Draw], [ShuffleHand] or [TagSwap].
+/// 
+/// ygopro server send these messages to each player seperately, and erase the codes 
+/// the receiver should not see to `0`.
+/// 
+/// #### Return
+/// * `None`: the card is hidden to receiver.
+/// * `Some(code)`: card code, with [PUBLIC_CODE_FLAG] removed.
+// ----------------------------------------------------------------------------------------------------
+pub fn reveal_code(code: u32) -> Option<u32> {
+    match code & !PUBLIC_CODE_FLAG {
+        0 => None,
+        code => Some(code)
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  CardQuery
+// ----------------------------------------------------------------------------------------------------
+/// Card information queried from ygopro core, used by [UpdateData] and [UpdateCard].
+/// 
+/// Each field only exists if its [Query] bit is set in `flags`. \
+/// ygopro server erase the query of a face-down card before sending it to the opponent,
+/// leaving `flags` to `0` and the payload all zero, see [is_hidden](CardQuery#method.is_hidden).
+// ----------------------------------------------------------------------------------------------------
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct CardQuery {
+    /// Raw [Query] flags. `None` if there is no card in that place.
+    pub flags: Option<u32>,
+    pub code: Option<u32>,
+    /// Packed as `controller | location << 8 | sequence << 16 | position << 24`.
+    pub position: Option<u32>,
+    pub alias: Option<u32>,
+    pub _type: Option<u32>,
+    pub level: Option<u32>,
+    pub rank: Option<u32>,
+    pub attribute: Option<u32>,
+    pub race: Option<u32>,
+    pub attack: Option<i32>,
+    pub defense: Option<i32>,
+    pub base_attack: Option<i32>,
+    pub base_defense: Option<i32>,
+    pub reason: Option<u32>,
+    pub reason_card: Option<u32>,
+    pub equip_card: Option<u32>,
+    pub target_cards: Option<Vec<u32>>,
+    pub overlay_cards: Option<Vec<u32>>,
+    pub counters: Option<Vec<u32>>,
+    pub owner: Option<u32>,
+    pub status: Option<u32>,
+    pub lscale: Option<u32>,
+    pub rscale: Option<u32>,
+    /// Link rating and link markers.
+    pub link: Option<(u32, u32)>,
+    /// Data can't be recognized, include the erased payload of a hidden card.
+    pub residual: Vec<u8>
+}
+impl Struct for CardQuery {}
+
+impl CardQuery {
+    /// There is no card in that place.
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_none()
+    }
+
+    /// There is a card, but its information is hidden to receiver.
+    pub fn is_hidden(&self) -> bool {
+        self.flags == Some(0)
+    }
+
+    /// Known [Query] flags.
+    pub fn query(&self) -> Query {
+        Query::from_bits_truncate(self.flags.unwrap_or(0))
+    }
+
+    fn from_payload(payload: &[u8]) -> CardQuery {
+        let mut query = CardQuery::default();
+        if payload.is_empty() { return query; }
+        let mut reader = std::io::Cursor::new(payload);
+        let flags = match reader.read_u32::<LittleEndian>() {
+            Ok(flags) => flags,
+            Err(_) => { query.residual = payload.to_vec(); return query; }
+        };
+        query.flags = Some(flags);
+        // Stop at the first bit we don't know, as its data length is unknown.
+        let unknown = flags & !Query::all().bits();
+        let lowest_unknown = if unknown == 0 { u32::MAX } else { unknown & unknown.wrapping_neg() };
+        let parsed = (|| -> std::io::Result<()> {
+            macro_rules! read_field {
+                ($flag: expr, $field: ident, $read: ident) => {
+                    if flags & $flag.bits() != 0 {
+                        if $flag.bits() > lowest_unknown { return Ok(()); }
+                        query.$field = Some(reader.$read::<LittleEndian>()?);
+                    }
+                };
+            }
+            macro_rules! read_list {
+                ($flag: expr, $field: ident) => {
+                    if flags & $flag.bits() != 0 {
+                        if $flag.bits() > lowest_unknown { return Ok(()); }
+                        let count = reader.read_u32::<LittleEndian>()?;
+                        let mut list = Vec::new();
+                        for _ in 0..count { list.push(reader.read_u32::<LittleEndian>()?); }
+                        query.$field = Some(list);
+                    }
+                };
+            }
+            read_field!(Query::Code,        code,         read_u32);
+            read_field!(Query::Position,    position,     read_u32);
+            read_field!(Query::Alias,       alias,        read_u32);
+            read_field!(Query::Type,        _type,        read_u32);
+            read_field!(Query::Level,       level,        read_u32);
+            read_field!(Query::Rank,        rank,         read_u32);
+            read_field!(Query::Attribute,   attribute,    read_u32);
+            read_field!(Query::Race,        race,         read_u32);
+            read_field!(Query::Attack,      attack,       read_i32);
+            read_field!(Query::Defense,     defense,      read_i32);
+            read_field!(Query::BaseAttack,  base_attack,  read_i32);
+            read_field!(Query::BaseDefense, base_defense, read_i32);
+            read_field!(Query::Reason,      reason,       read_u32);
+            read_field!(Query::ReasonCard,  reason_card,  read_u32);
+            read_field!(Query::EquipCard,   equip_card,   read_u32);
+            read_list!(Query::TargetCard,   target_cards);
+            read_list!(Query::OverlayCard,  overlay_cards);
+            read_list!(Query::Counters,     counters);
+            read_field!(Query::Owner,       owner,        read_u32);
+            read_field!(Query::Status,      status,       read_u32);
+            read_field!(Query::Lscale,      lscale,       read_u32);
+            read_field!(Query::Rscale,      rscale,       read_u32);
+            if flags & Query::Link.bits() != 0 && Query::Link.bits() < lowest_unknown {
+                query.link = Some((reader.read_u32::<LittleEndian>()?, reader.read_u32::<LittleEndian>()?));
+            }
+            Ok(())
+        })();
+        if parsed.is_err() {
+            // Truncated data, keep it as it is.
+            return CardQuery { flags: Some(flags), residual: payload[4..].to_vec(), ..Default::default() };
+        }
+        query.residual = payload[reader.position() as usize..].to_vec();
+        query
+    }
+
+    fn to_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        let flags = match self.flags {
+            Some(flags) => flags,
+            None => return payload
+        };
+        payload.extend_from_slice(&flags.to_le_bytes());
+        let mut write = |value: u32| payload.extend_from_slice(&value.to_le_bytes());
+        for value in [self.code, self.position, self.alias, self._type, self.level, self.rank, self.attribute, self.race].iter().flatten() { write(*value); }
+        for value in [self.attack, self.defense, self.base_attack, self.base_defense].iter().flatten() { write(*value as u32); }
+        for value in [self.reason, self.reason_card, self.equip_card].iter().flatten() { write(*value); }
+        for list in [&self.target_cards, &self.overlay_cards, &self.counters].iter().copied().flatten() {
+            write(list.len() as u32);
+            for value in list.iter() { write(*value); }
+        }
+        for value in [self.owner, self.status, self.lscale, self.rscale].iter().flatten() { write(*value); }
+        if let Some((link, link_marker)) = self.link { write(link); write(link_marker); }
+        payload.extend_from_slice(&self.residual);
+        payload
+    }
+}
+
+#[doc(hidden)]
+mod card_query_serde {
+    use super::*;
+    use serde::ser::SerializeTuple;
+
+    /// Binary form of [CardQuery]: `[length:u32][payload]`, length includes itself.
+    pub struct BinaryCardQuery<'a>(pub &'a CardQuery);
+
+    impl<'a> Serialize for BinaryCardQuery<'a> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+            let payload = self.0.to_payload();
+            let mut seq = serializer.serialize_tuple(payload.len() + 1)?;
+            seq.serialize_element(&(payload.len() as u32 + 4))?;
+            for byte in payload.iter() { seq.serialize_element(byte)?; }
+            seq.end()
+        }
+    }
+
+    pub struct CardQueryVisitor;
+
+    impl<'de> Visitor<'de> for CardQueryVisitor {
+        type Value = CardQuery;
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a length leading card query")
+        }
+
+        fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error> where V: SeqAccess<'de> {
+            let length: u32 = seq.next_element()?.ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+            let size = (length as usize).saturating_sub(4);
+            let mut payload = Vec::with_capacity(size.min(1024));
+            for index in 0..size {
+                payload.push(seq.next_element::<u8>()?.ok_or_else(|| serde::de::Error::invalid_length(index + 1, &self))?);
+            }
+            Ok(CardQuery::from_payload(&payload))
+        }
+    }
+}
+
+impl Serialize for CardQuery {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        if !serializer.is_human_readable() { return card_query_serde::BinaryCardQuery(self).serialize(serializer); }
+        let mut state = serializer.serialize_struct("CardQuery", 25)?;
+        macro_rules! serialize_fields {
+            ($($field: ident),*) => { $(state.serialize_field(stringify!($field), &self.$field)?;)* };
+        }
+        serialize_fields!(flags, code, position, alias, _type, level, rank, attribute, race, attack, defense, base_attack, base_defense,
+                          reason, reason_card, equip_card, target_cards, overlay_cards, counters, owner, status, lscale, rscale, link, residual);
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for CardQuery {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_tuple(usize::MAX, card_query_serde::CardQueryVisitor)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Retry;
 
 #[derive(Serialize, Deserialize, Debug, Struct, Clone)]
 pub struct Hint {
@@ -191,6 +456,21 @@ pub struct Hint {
     pub data: i32
 }
 
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Waiting;
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+// #[gm]
+pub struct Start {
+    /// Lowest 4 bits: if receiver go first. `0x10` for observer.
+    pub _type: u8,
+    pub life_points: [i32; 2],
+    pub player1_deck_count: u16,
+    pub player1_extra_count: u16,
+    pub player2_deck_count: u16,
+    pub player2_extra_count: u16
+}
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 // #[gm]
 pub struct Win {
@@ -198,18 +478,259 @@ pub struct Win {
     pub reason: u8
 }
 
+/// Refresh all cards in a location. Face-down cards of opponent are [hidden](CardQuery#method.is_hidden).
 #[derive(Serialize, Deserialize, Debug, Struct)]
-// #[gm]
-pub struct Start {
-    pub _type: u8
+pub struct UpdateData {
+    pub player: Netplayer,
+    pub location: u8,
+    #[serde(with = "GreedyVector::<65536>")]
+    pub cards: Vec<CardQuery>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct UpdateCard {
+    pub player: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub query: CardQuery
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct RequestDeck;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActivatableCard {
+    pub code: u32,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub desc: i32
+}
+impl Struct for ActivatableCard {}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AttackableCard {
+    pub code: u32,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub direct_attackable: bool
+}
+impl Struct for AttackableCard {}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectBattlecmd {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub activatable: Vec<ActivatableCard>,
+    #[serde(with = "CountedVector::<u8>")]
+    pub attackable: Vec<AttackableCard>,
+    pub can_main2: bool,
+    pub can_end_phase: bool
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectIdlecmd {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub summonable: Vec<CardInfo>,
+    #[serde(with = "CountedVector::<u8>")]
+    pub special_summonable: Vec<CardInfo>,
+    #[serde(with = "CountedVector::<u8>")]
+    pub repositionable: Vec<CardInfo>,
+    #[serde(with = "CountedVector::<u8>")]
+    pub monster_setable: Vec<CardInfo>,
+    #[serde(with = "CountedVector::<u8>")]
+    pub spell_setable: Vec<CardInfo>,
+    #[serde(with = "CountedVector::<u8>")]
+    pub activatable: Vec<ActivatableCard>,
+    pub can_battle_phase: bool,
+    pub can_end_phase: bool,
+    pub can_shuffle: bool
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectEffectyn {
+    pub player: Netplayer,
+    pub code: u32,
+    pub location: LocationInfo,
+    pub desc: i32
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectYesno {
+    pub player: Netplayer,
+    pub desc: i32
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectOption {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub options: Vec<i32>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectCard {
+    pub player: Netplayer,
+    pub cancelable: bool,
+    pub min: u8,
+    pub max: u8,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<LocatedCard>
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChainableCard {
+    /// EDESC flag of the effect.
+    pub flag: u8,
+    pub forced: bool,
+    pub code: u32,
+    pub location: LocationInfo,
+    pub desc: i32
+}
+impl Struct for ChainableCard {}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectChain {
+    pub player: Netplayer,
+    pub count: u8,
+    pub special_count: u8,
+    pub forced: bool,
+    pub hint_timing: u32,
+    pub other_timing: u32,
+    #[serde(with = "GreedyVector::<255>")]
+    pub chains: Vec<ChainableCard>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectPlace {
+    pub player: Netplayer,
+    pub count: u8,
+    /// Bit mask of zones **can't** be selected.
+    pub field: u32
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectPosition {
+    pub player: Netplayer,
+    pub code: u32,
+    /// Combined [Position](crate::ygopro::Position) can be selected.
+    pub positions: u8
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TributeCard {
+    pub code: u32,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub release_param: u8
+}
+impl Struct for TributeCard {}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectTribute {
+    pub player: Netplayer,
+    pub cancelable: bool,
+    pub min: u8,
+    pub max: u8,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<TributeCard>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SortChain {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<CardInfo>
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterCard {
+    pub code: u32,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub counter_count: u16
+}
+impl Struct for CounterCard {}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectCounter {
+    pub player: Netplayer,
+    pub counter_type: u16,
+    pub count: u16,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<CounterCard>
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SumCard {
+    pub code: u32,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub param: i32
+}
+impl Struct for SumCard {}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectSum {
+    /// `0` for equal, `1` for greater.
+    pub mode: u8,
+    pub player: Netplayer,
+    pub sum: i32,
+    pub min: u8,
+    pub max: u8,
+    #[serde(with = "CountedVector::<u8>")]
+    pub must_select: Vec<SumCard>,
+    #[serde(with = "CountedVector::<u8>")]
+    pub selectable: Vec<SumCard>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectDisfield {
+    pub player: Netplayer,
+    pub count: u8,
+    /// Bit mask of zones **can't** be selected.
+    pub field: u32
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SortCard {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<CardInfo>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SelectUnselectCard {
+    pub player: Netplayer,
+    pub finishable: bool,
+    pub cancelable: bool,
+    pub min: u8,
+    pub max: u8,
+    #[serde(with = "CountedVector::<u8>")]
+    pub selectable: Vec<LocatedCard>,
+    #[serde(with = "CountedVector::<u8>")]
+    pub unselectable: Vec<LocatedCard>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct ConfirmDecktop {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<ConfirmCard>
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct ConfirmCard {
     pub code: i32,
     pub controller: Netplayer,
-    pub location: Location,
-    pub sequence: i8
+    /// Raw value as in [LocationInfo].
+    pub location: u8,
+    pub sequence: u8
 }
 impl Struct for ConfirmCard {}
 
@@ -221,51 +742,167 @@ pub struct ConfirmCards {
     pub cards: Vec<ConfirmCard>
 }
 
+#[derive(Serialize, Deserialize, Debug, Struct)]
+// #[gm]
+pub struct ShuffleDeck {
+    pub player: crate::ygopro::Netplayer 
+}
+
+/// Codes are [hidden](reveal_code) to opponent.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct ShuffleHand {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<u32>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct RefreshDeck {
+    pub player: Netplayer
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct SwapGraveDeck {
+    pub player: Netplayer
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct ShuffleSetCard {
+    pub location: u8,
+    pub count: u8,
+    /// Places before shuffle, then places after shuffle, `count` for each.
+    #[serde(with = "GreedyVector::<255>")]
+    pub cards: Vec<LocationInfo>
+}
+
+impl ShuffleSetCard {
+    /// Places before shuffle.
+    pub fn previous(&self) -> &[LocationInfo] {
+        &self.cards[..(self.count as usize).min(self.cards.len())]
+    }
+
+    /// Places after shuffle.
+    pub fn current(&self) -> &[LocationInfo] {
+        &self.cards[(self.count as usize).min(self.cards.len())..]
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct ReverseDeck;
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct DeckTop {
+    pub player: Netplayer,
+    pub sequence: u8,
+    /// Contains [PUBLIC_CODE_FLAG] if the card is face-up.
+    pub code: u32
+}
+
+/// Codes are [hidden](reveal_code) to opponent.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct MsgShuffleExtra {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<u32>
+}
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct NewTurn {
     pub player: Netplayer
 }
 
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct NewPhase {
+    /// Value of [Phase](crate::ygopro::Phase).
+    pub phase: u16
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct ConfirmExtratop {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<ConfirmCard>
+}
+
+/// Code is `0` if the card moves to a place receiver can't see.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Move {
+    pub code: u32,
+    pub previous: LocationInfo,
+    pub current: LocationInfo,
+    pub reason: Reason
+}
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct PosChange {
     pub card: u32,
     pub controller: Netplayer,
-    pub location: Location,
-    pub sequence: i8,
-    pub previous_position: Position,
-    pub current_position: Position
+    /// Raw value as in [LocationInfo].
+    pub location: u8,
+    pub sequence: u8,
+    /// Raw values as in [LocationInfo].
+    pub previous_position: u8,
+    pub current_position: u8
+}
+
+/// Code is always `0`, ygopro server hides set card from everyone.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Set {
+    pub card: u32,
+    pub location: LocationInfo
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Swap {
+    pub first: LocatedCard,
+    pub second: LocatedCard
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct FieldDisabled {
+    /// Bit mask of disabled zones.
+    pub field: u32
 }
 
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct Summoning {
     pub card: u32,
-    pub controller: Netplayer,
-    pub location: Location,
-    pub sequence: i8,
-    pub position: Position
+    pub location: LocationInfo
 }
 
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Summoned;
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct Spsummoning {
     pub card: u32,
-    pub controller: Netplayer,
-    pub location: Location,
-    pub sequence: i8,
-    pub position: Position
+    pub location: LocationInfo
 }
 
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Spsummoned;
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Flipsummoning {
+    pub card: u32,
+    pub location: LocationInfo
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Flipsummoned;
+
+/// `triggering_*` is where the effect is triggered, which may differ
+/// from current `location` of the card.
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct Chaining {
     pub card: u32,
-    pub previous_controller: Netplayer,
-    pub previous_location: Location,
-    pub previous_sequence: i8,
-    pub sub_sequence: i8,
-    pub controller: Netplayer,
-    pub location: Location,
-    pub sequence: i8,
-    pub desc: i32,
-    pub target: i8
+    pub location: LocationInfo,
+    pub triggering_controller: Netplayer,
+    pub triggering_location: u8,
+    pub triggering_sequence: u32,
+    pub desc: u32,
+    /// Size of the chain with this one, so its chain link.
+    pub chain_count: u8
 }
 
 #[derive(Serialize, Deserialize, Debug, Struct)]
@@ -297,7 +934,32 @@ pub struct ChainDisabled {
 }
 
 #[derive(Serialize, Deserialize, Debug, Struct)]
-pub struct CardSelected;
+pub struct CardSelected {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<LocationInfo>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct RandomSelected {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<LocationInfo>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct BecomeTarget {
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<LocationInfo>
+}
+
+/// Codes are [hidden](reveal_code) to opponent, unless the card is public.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Draw {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub cards: Vec<u32>
+}
 
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct Damage {
@@ -311,23 +973,553 @@ pub struct Recover {
     pub value: i32
 }
 
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Equip {
+    pub card: LocationInfo,
+    pub target: LocationInfo
+}
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct Lpupdate {
     pub player: Netplayer,
     pub lp: i32
 }
 
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Unequip {
+    pub card: LocationInfo
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct CardTarget {
+    pub card: LocationInfo,
+    pub target: LocationInfo
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct CancelTarget {
+    pub card: LocationInfo,
+    pub target: LocationInfo
+}
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct PayLpcost {
     pub player: Netplayer,
     pub cost: i32
 }
 
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct AddCounter {
+    pub counter_type: u16,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub count: u16
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct RemoveCounter {
+    pub counter_type: u16,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub count: u16
+}
+
+/// `target` is all zero for a direct attack.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Attack {
+    pub attacker: LocationInfo,
+    pub target: LocationInfo
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BattleCard {
+    pub location: LocationInfo,
+    pub attack: i32,
+    pub defense: i32,
+    pub damage_flag: u8
+}
+impl Struct for BattleCard {}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct Battle {
+    pub attacker: BattleCard,
+    pub target: BattleCard
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct AttackDisabled;
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct DamageStepStart;
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct DamageStepEnd;
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct MissedEffect {
+    pub location: LocationInfo,
+    pub code: u32
+}
+
+/// Reserved by ygopro, never sent by core.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct BeChainTarget;
+
+/// Reserved by ygopro, never sent by core.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct CreateRelation;
+
+/// Reserved by ygopro, never sent by core.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct ReleaseRelation;
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct TossCoin {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub results: Vec<u8>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct TossDice {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub results: Vec<u8>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct RockPaperScissors {
+    pub player: Netplayer
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct HandRes {
+    /// Lower 2 bits for player 1, higher 2 bits for player 2.
+    pub result: u8
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct AnnounceRace {
+    pub player: Netplayer,
+    pub count: u8,
+    pub available: Race
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct AnnounceAttrib {
+    pub player: Netplayer,
+    pub count: u8,
+    pub available: Attribute
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct AnnounceCard {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub opcodes: Vec<u32>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct AnnounceNumber {
+    pub player: Netplayer,
+    #[serde(with = "CountedVector::<u8>")]
+    pub numbers: Vec<u32>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct CardHint {
+    pub location: LocationInfo,
+    pub _type: u8,
+    pub value: u32
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct TagSwap {
+    pub player: Netplayer,
+    pub main_count: u8,
+    pub extra_count: u8,
+    pub extra_faceup_count: u8,
+    pub hand_count: u8,
+    /// Contains [PUBLIC_CODE_FLAG] if the card is face-up.
+    pub top_code: u32,
+    /// Hand codes, then extra deck codes.
+    #[serde(with = "GreedyVector::<65536>")]
+    pub cards: Vec<u32>
+}
+
+impl TagSwap {
+    /// Codes of cards in hand.
+    pub fn hand(&self) -> &[u32] {
+        &self.cards[..(self.hand_count as usize).min(self.cards.len())]
+    }
+
+    /// Codes of cards in extra deck.
+    pub fn extra(&self) -> &[u32] {
+        &self.cards[(self.hand_count as usize).min(self.cards.len())..]
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReloadFieldMonster {
+    pub position: u8,
+    pub overlay_count: u8
+}
+impl Struct for ReloadFieldMonster {}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReloadFieldSpell {
+    pub position: u8
+}
+impl Struct for ReloadFieldSpell {}
+
+/// Field of a player. Zone with no card is `None`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReloadFieldPlayer {
+    pub lp: i32,
+    pub monster_zones: [Option<ReloadFieldMonster>; 7],
+    pub spell_zones: [Option<ReloadFieldSpell>; 8],
+    pub deck_count: u8,
+    pub hand_count: u8,
+    pub grave_count: u8,
+    pub removed_count: u8,
+    pub extra_count: u8,
+    pub extra_faceup_count: u8
+}
+impl Struct for ReloadFieldPlayer {}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReloadFieldChain {
+    pub code: u32,
+    pub previous_controller: Netplayer,
+    pub previous_location: u8,
+    pub previous_sequence: u8,
+    pub sub_sequence: u8,
+    pub controller: Netplayer,
+    pub location: u8,
+    pub sequence: u8,
+    pub desc: i32
+}
+impl Struct for ReloadFieldChain {}
+
+/// Sent on reconnect or observing a running duel.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct ReloadField {
+    pub duel_rule: u8,
+    pub players: [ReloadFieldPlayer; 2],
+    #[serde(with = "CountedVector::<u8>")]
+    pub chains: Vec<ReloadFieldChain>
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct AiName {
+    #[serde(with = "CountedString::<u16>")]
+    pub name: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct ShowHint {
+    #[serde(with = "CountedString::<u16>")]
+    pub message: String
+}
+
 #[derive(Serialize, Deserialize, Debug, Struct)]
 pub struct MatchKill {
     pub reason: i32
 }
 
+/// Custom message for extended ygopro, content unknown.
+#[derive(Serialize, Deserialize, Debug, Struct)]
+pub struct CustomMsg;
+
 pub fn generate_message_type(_type: MessageType) -> crate::ygopro::message::MessageType {
     crate::ygopro::message::MessageType::GM(_type)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ygopro::Location;
+    use crate::ygopro::Position;
+
+    // Game message bodies, after the stoc header. First byte is the game message type.
+    // Bytes follow ocgcore `write_buffer*` calls of each message, field by field,
+    // e.g. a place is `get_info_location()`, controller, location, sequence and position.
+    const MOVE: &[u8] = &[
+        0x32, 0xa3, 0xa9, 0x57, 0x05, 0x00, 0x02, 0x01, 0x0a, 0x00, 0x04, 0x02, 0x01, 0x20, 0x00, 0x00,
+        0x00
+    ];
+
+    const DRAW: &[u8] = &[
+        0x5a, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x4a, 0x70, 0x49, 0x83
+    ];
+
+    const ATTACK: &[u8] = &[
+        0x6e, 0x00, 0x04, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00
+    ];
+
+    const NEW_PHASE: &[u8] = &[
+        0x29, 0x04, 0x00
+    ];
+
+    const LPUPDATE: &[u8] = &[
+        0x5e, 0x01, 0xa0, 0x0f, 0x00, 0x00
+    ];
+
+    const TOSS_COIN: &[u8] = &[
+        0x82, 0x00, 0x03, 0x01, 0x00, 0x01
+    ];
+
+    const SELECT_IDLECMD: &[u8] = &[
+        0x0b, 0x00, 0x01, 0xa3, 0xa9, 0x57, 0x05, 0x00, 0x02, 0x00, 0x00, 0x01, 0xae, 0xf4, 0xcc, 0x02,
+        0x00, 0x04, 0x00, 0x01, 0xa3, 0xa9, 0x57, 0x05, 0x00, 0x02, 0x00, 0x02, 0xef, 0x27, 0x51, 0x00,
+        0x00, 0x02, 0x03, 0x12, 0xd9, 0xa0, 0x02, 0x00, 0x02, 0x04, 0x01, 0xef, 0x27, 0x51, 0x00, 0x00,
+        0x02, 0x03, 0xf0, 0x7e, 0x12, 0x05, 0x01, 0x01, 0x00
+    ];
+
+    const SELECT_CARD: &[u8] = &[
+        0x0f, 0x00, 0x01, 0x01, 0x02, 0x03, 0xa3, 0xa9, 0x57, 0x05, 0x00, 0x02, 0x00, 0x0a, 0xae, 0xf4,
+        0xcc, 0x02, 0x00, 0x10, 0x01, 0x05, 0x00, 0x00, 0x00, 0x00, 0x01, 0x04, 0x00, 0x0a
+    ];
+
+    const SELECT_CHAIN: &[u8] = &[
+        0x10, 0x01, 0x01, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xb2,
+        0x32, 0xcc, 0x05, 0x01, 0x02, 0x00, 0x0a, 0x20, 0x1b, 0xc3, 0x5c
+    ];
+
+    const SELECT_SUM: &[u8] = &[
+        0x17, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x01, 0x03, 0x01, 0x44, 0x81, 0x77, 0x02, 0x00, 0x04,
+        0x00, 0x03, 0x00, 0x03, 0x00, 0x02, 0xa3, 0xa9, 0x57, 0x05, 0x00, 0x02, 0x01, 0x08, 0x00, 0x00,
+        0x00, 0xae, 0xf4, 0xcc, 0x02, 0x00, 0x02, 0x02, 0x07, 0x00, 0x00, 0x00
+    ];
+
+    const UPDATE_DATA: &[u8] = &[
+        0x06, 0x00, 0x04, 0x04, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0xa3,
+        0xa9, 0x57, 0x05, 0x00, 0x04, 0x02, 0x01, 0xb8, 0x0b, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+    ];
+
+    const UPDATE_CARD: &[u8] = &[
+        0x07, 0x00, 0x04, 0x01, 0x20, 0x00, 0x00, 0x00, 0x01, 0x00, 0x81, 0x00, 0xb5, 0xf0, 0x01, 0x05,
+        0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x28, 0x00, 0x00, 0x00
+    ];
+
+    const RELOAD_FIELD: &[u8] = &[
+        0xa2, 0x05, 0x40, 0x1f, 0x00, 0x00, 0x01, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1e, 0x05, 0x02, 0x01, 0x0f, 0x00, 0x20, 0x1c,
+        0x00, 0x00, 0x01, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x0a, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x1e, 0x05, 0x02, 0x01, 0x0f, 0x00, 0x01, 0xb2, 0x32, 0xcc, 0x05, 0x01,
+        0x02, 0x00, 0x00, 0x01, 0x10, 0x00, 0x20, 0x1b, 0xc3, 0x5c
+    ];
+
+    const TAG_SWAP: &[u8] = &[
+        0xa1, 0x00, 0x23, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0xa3, 0xa9, 0x57, 0x05, 0x00, 0x00,
+        0x00, 0x00, 0xbe, 0x23, 0xa7, 0x82
+    ];
+
+    const AI_NAME: &[u8] = &[
+        0xa3, 0x07, 0x00, 0x57, 0x69, 0x6e, 0x64, 0x42, 0x6f, 0x74, 0x00
+    ];
+
+    const START: &[u8] = &[
+        0x04, 0x00, 0x40, 0x1f, 0x00, 0x00, 0x40, 0x1f, 0x00, 0x00, 0x23, 0x00, 0x0f, 0x00, 0x28, 0x00,
+        0x0c, 0x00
+    ];
+
+    const SHUFFLE_SET_CARD: &[u8] = &[
+        0x24, 0x04, 0x02, 0x00, 0x04, 0x00, 0x0a, 0x00, 0x04, 0x01, 0x0a, 0x00, 0x04, 0x01, 0x0a, 0x00,
+        0x04, 0x00, 0x0a
+    ];
+
+    const BATTLE: &[u8] = &[
+        0x6f, 0x00, 0x04, 0x02, 0x01, 0xb8, 0x0b, 0x00, 0x00, 0xc4, 0x09, 0x00, 0x00, 0x00, 0x01, 0x04,
+        0x00, 0x04, 0x08, 0x07, 0x00, 0x00, 0xd0, 0x07, 0x00, 0x00, 0x00
+    ];
+
+    // Number 39: Utopia in monster zone 0, chain link 1.
+    const CHAINING: &[u8] = &[
+        0x46, 0xb5, 0xf0, 0x01, 0x05, 0x00, 0x04, 0x00, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x50,
+        0x0b, 0x1f, 0x50, 0x01
+    ];
+
+    // A material chains from overlay: location is `MZone | Overlay`, position is material index.
+    const CHAINING_OVERLAY: &[u8] = &[
+        0x46, 0xa3, 0xa9, 0x57, 0x05, 0x01, 0x84, 0x03, 0x01, 0x01, 0x80, 0x01, 0x00, 0x00, 0x00, 0x30,
+        0x9a, 0x7a, 0x55, 0x02
+    ];
+
+    const POS_CHANGE: &[u8] = &[
+        0x35, 0xa3, 0xa9, 0x57, 0x05, 0x00, 0x04, 0x02, 0x01, 0x04
+    ];
+
+    const SPSUMMONING: &[u8] = &[
+        0x3e, 0xb5, 0xf0, 0x01, 0x05, 0x01, 0x04, 0x05, 0x08
+    ];
+
+    fn round_trip(fixture: &[u8]) -> GameMessage {
+        let message: GameMessage = bincode::deserialize(fixture).expect("Failed to deserialize fixture");
+        assert_eq!(bincode::serialize(&message).expect("Failed to serialize message"), fixture, "{:?} changed after round trip", message.kind);
+        message
+    }
+
+    fn round_trip_as<S: Struct>(fixture: &[u8], kind: MessageType) -> Box<S> {
+        let message = round_trip(fixture);
+        assert_eq!(message.kind, kind);
+        message.message.downcast::<S>().unwrap_or_else(|_| panic!("{:?} is not decoded to its struct", kind))
+    }
+
+    #[test]
+    fn move_message() {
+        let message = round_trip_as::<Move>(MOVE, MessageType::Move);
+        assert_eq!(message.code, 89631139);
+        assert_eq!(message.previous.location, Location::Hand as u8);
+        assert_eq!(message.current, LocationInfo { controller: Netplayer::Player1, location: Location::MZone as u8, sequence: 2, position: Position::FaceupAttack as u8 });
+    }
+
+    #[test]
+    fn draw_with_hidden_card() {
+        let message = round_trip_as::<Draw>(DRAW, MessageType::Draw);
+        assert_eq!(message.player, Netplayer::Player2);
+        let codes: Vec<Option<u32>> = message.cards.iter().map(|code| reveal_code(*code)).collect();
+        assert_eq!(codes, vec![None, Some(55144522)]);
+    }
+
+    #[test]
+    fn direct_attack() {
+        let message = round_trip_as::<Attack>(ATTACK, MessageType::Attack);
+        assert_eq!(message.attacker.sequence, 2);
+        assert_eq!((message.target.location, message.target.sequence), (0, 0));
+    }
+
+    #[test]
+    fn new_phase_and_lp_update() {
+        assert_eq!(round_trip_as::<NewPhase>(NEW_PHASE, MessageType::NewPhase).phase, crate::ygopro::Phase::Main1 as u16);
+        assert_eq!(round_trip_as::<Lpupdate>(LPUPDATE, MessageType::Lpupdate).lp, 4000);
+    }
+
+    #[test]
+    fn toss_coin() {
+        assert_eq!(round_trip_as::<TossCoin>(TOSS_COIN, MessageType::TossCoin).results, vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn select_idle_command() {
+        let message = round_trip_as::<SelectIdlecmd>(SELECT_IDLECMD, MessageType::SelectIdlecmd);
+        assert_eq!(message.summonable.len(), 1);
+        assert!(message.special_summonable.is_empty());
+        assert_eq!(message.spell_setable.len(), 2);
+        assert_eq!(message.activatable[0].desc, 85098224);
+        assert!(message.can_battle_phase && message.can_end_phase && !message.can_shuffle);
+    }
+
+    #[test]
+    fn select_card() {
+        let message = round_trip_as::<SelectCard>(SELECT_CARD, MessageType::SelectCard);
+        assert!(message.cancelable);
+        assert_eq!((message.min, message.max), (1, 2));
+        assert_eq!(message.cards.len(), 3);
+        assert_eq!(message.cards[2].location.controller, Netplayer::Player2);
+    }
+
+    #[test]
+    fn select_chain() {
+        let message = round_trip_as::<SelectChain>(SELECT_CHAIN, MessageType::SelectChain);
+        assert_eq!(message.chains.len(), message.count as usize);
+        assert_eq!(message.chains[0].code, 97268402);
+    }
+
+    #[test]
+    fn select_sum() {
+        let message = round_trip_as::<SelectSum>(SELECT_SUM, MessageType::SelectSum);
+        assert_eq!(message.sum, 8);
+        assert_eq!(message.must_select.len(), 1);
+        assert_eq!(message.selectable.len(), 2);
+    }
+
+    #[test]
+    fn update_data_with_hidden_card() {
+        let message = round_trip_as::<UpdateData>(UPDATE_DATA, MessageType::UpdateData);
+        assert_eq!(message.cards.len(), 3);
+        assert!(message.cards[0].is_empty());
+        assert_eq!(message.cards[1].code, Some(89631139));
+        assert_eq!(message.cards[1].attack, Some(3000));
+        assert_eq!(message.cards[1].defense, None);
+        assert!(message.cards[2].is_hidden());
+    }
+
+    #[test]
+    fn update_card_with_lists() {
+        let message = round_trip_as::<UpdateCard>(UPDATE_CARD, MessageType::UpdateCard);
+        assert_eq!(message.query.overlay_cards, Some(vec![1, 2]));
+        assert_eq!(message.query.link, Some((2, 0x28)));
+        assert!(message.query.residual.is_empty());
+    }
+
+    #[test]
+    fn reload_field() {
+        let message = round_trip_as::<ReloadField>(RELOAD_FIELD, MessageType::ReloadField);
+        assert_eq!(message.players[1].lp, 7200);
+        assert_eq!(message.players[0].monster_zones[0], Some(ReloadFieldMonster { position: 1, overlay_count: 2 }));
+        assert_eq!(message.players[0].monster_zones[1], None);
+        assert_eq!(message.chains.len(), 1);
+    }
+
+    #[test]
+    fn tag_swap() {
+        let message = round_trip_as::<TagSwap>(TAG_SWAP, MessageType::TagSwap);
+        assert_eq!(message.hand(), &[89631139]);
+        assert_eq!(message.extra().len(), 2);
+    }
+
+    #[test]
+    fn ai_name() {
+        assert_eq!(round_trip_as::<AiName>(AI_NAME, MessageType::AiName).name, "WindBot");
+    }
+
+    #[test]
+    fn start() {
+        let message = round_trip_as::<Start>(START, MessageType::Start);
+        assert_eq!(message.life_points, [8000, 8000]);
+        assert_eq!(message.player2_extra_count, 12);
+    }
+
+    #[test]
+    fn shuffle_set_card() {
+        let message = round_trip_as::<ShuffleSetCard>(SHUFFLE_SET_CARD, MessageType::ShuffleSetCard);
+        assert_eq!(message.previous()[0].sequence, 0);
+        assert_eq!(message.current()[0].sequence, 1);
+    }
+
+    #[test]
+    fn battle() {
+        let message = round_trip_as::<Battle>(BATTLE, MessageType::Battle);
+        assert_eq!(message.attacker.attack, 3000);
+        assert_eq!(message.target.defense, 2000);
+    }
+
+    #[test]
+    fn chaining() {
+        let message = round_trip_as::<Chaining>(CHAINING, MessageType::Chaining);
+        assert_eq!(message.card, 84013237);
+        assert_eq!(message.location, LocationInfo { controller: Netplayer::Player1, location: Location::MZone as u8, sequence: 0, position: Position::FaceupAttack as u8 });
+        assert_eq!((message.triggering_location, message.triggering_sequence), (Location::MZone as u8, 0));
+        assert_eq!(message.desc, 84013237 << 4);
+        assert_eq!(message.chain_count, 1);
+
+        let message = round_trip_as::<Chaining>(CHAINING_OVERLAY, MessageType::Chaining);
+        assert_eq!(message.location.location, Location::MZone as u8 | Location::Overlay as u8);
+        assert_eq!(message.location.position, 1);
+        assert_eq!(message.triggering_controller, Netplayer::Player2);
+        assert_eq!(message.chain_count, 2);
+    }
+
+    #[test]
+    fn position_change_and_special_summon() {
+        let message = round_trip_as::<PosChange>(POS_CHANGE, MessageType::PosChange);
+        assert_eq!((message.previous_position, message.current_position), (Position::FaceupAttack as u8, Position::FaceupDefense as u8));
+        let message = round_trip_as::<Spsummoning>(SPSUMMONING, MessageType::Spsummoning);
+        assert_eq!(message.location, LocationInfo { controller: Netplayer::Player2, location: Location::MZone as u8, sequence: 5, position: Position::FacedownDefense as u8 });
+    }
+}
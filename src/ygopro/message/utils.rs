@@ -38,7 +38,7 @@ pub fn deserialize_struct_by_type(message_type: MessageType, data: &[u8]) -> Opt
     match message_type {
         MessageType::CTOS(ctos_type) => {
             match ctos_type {
-                // ctos::MessageType::Response     => deserialize_struct::<ctos::Response>(data),
+                ctos::MessageType::Response     => deserialize_struct::<ctos::Response>(data),
                 ctos::MessageType::UpdateDeck   => deserialize_struct::<ctos::UpdateDeck>(data),
                 ctos::MessageType::HandResult   => deserialize_struct::<ctos::HandResult>(data),
                 ctos::MessageType::TpResult     => deserialize_struct::<ctos::TpResult>(data),
@@ -56,7 +56,6 @@ pub fn deserialize_struct_by_type(message_type: MessageType, data: &[u8]) -> Opt
                 ctos::MessageType::HsKick       => deserialize_struct::<ctos::HsKick>(data),
                 ctos::MessageType::HsStart      => deserialize_struct::<ctos::HsStart>(data),
                 ctos::MessageType::RequestField => deserialize_struct::<ctos::RequestField>(data),
-                _ => Option::None
             }
         }
         MessageType::STOC(stoc_type) => {
@@ -0,0 +1,201 @@
+// ============================================================
+// common
+// ------------------------------------------------------------
+//! Integration test harness.
+//!
+//! [boot] starts a srvpru [Server](srvpru::srvpru::Server) in
+//! this process, with `mock_ygopro` as ygopro binary, and
+//! [YgoClient] plays as a ygopro client against it.
+//!
+//! Configurations are static, so a test binary (a file under
+//! `tests/`) can only boot once, with one set of plugins.
+// ============================================================
+#![allow(dead_code)]
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use futures_util::Stream;
+use futures_util::StreamExt;
+use once_cell::sync::OnceCell;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::time::Duration;
+
+use srvpru::srvpru::MessageFramer;
+use srvpru::ygopro::client::YgoClient;
+use srvpru::ygopro::message::Direction;
+use srvpru::ygopro::message::MappedStruct;
+use srvpru::ygopro::message::stoc;
+use srvpru::ygopro::message::string::cast_to_string;
+use srvpru::ygopro::message::try_get_message_type;
+
+/// How long to wait for an expected message.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+static SERVER_ADDR: OnceCell<SocketAddr> = OnceCell::new();
+static API_PORT: OnceCell<u16> = OnceCell::new();
+
+pub fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0").and_then(|listener| listener.local_addr()).expect("No free port").port()
+}
+
+fn prepare_configuration(port: u16, plugins: &[&str], extra: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("srvpru-test-{}-{}", std::process::id(), port));
+    std::fs::create_dir_all(&directory).unwrap();
+    for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/config")).unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, directory.join(path.file_name().unwrap())).unwrap();
+    }
+    let plugins: Vec<String> = plugins.iter().map(|plugin| format!("- {}", plugin)).collect();
+    let configuration = format!("port: {}\nygopro:\n  cwd: {}\n  binary: {}\n  address: 127.0.0.1\nplugins:\n{}\n{}",
+        port, directory.display(), env!("CARGO_BIN_EXE_mock_ygopro"), plugins.join("\n"), extra);
+    std::fs::write(directory.join("srvpru.yaml"), configuration).unwrap();
+    std::fs::write(directory.join("api.yaml"), format!("port: {}\n", api_port())).unwrap();
+    for (name, content) in files {
+        std::fs::write(directory.join(name), content).unwrap();
+    }
+    directory
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  boot
+// ----------------------------------------------------------------------------------------------------
+/// Start srvpru with given plugins, only once for each test binary.
+///
+/// Server runs on its own runtime thread, so it outlives each `#[tokio::test]`.
+///
+/// #### Return
+/// Address of srvpru.
+// ----------------------------------------------------------------------------------------------------
+pub fn boot(plugins: &[&str]) -> SocketAddr {
+    boot_with_script(plugins, serde_json::json!([]))
+}
+
+/// Like [boot], and `mock_ygopro` replies by `script`. See `src/bin/mock_ygopro.rs` for format.
+pub fn boot_with_script(plugins: &[&str], script: serde_json::Value) -> SocketAddr {
+    boot_with(plugins, script, "", &[])
+}
+
+/// Like [boot], with `extra` lines appended to `srvpru.yaml`.
+pub fn boot_with_configuration(plugins: &[&str], extra: &str) -> SocketAddr {
+    boot_with(plugins, serde_json::json!([]), extra, &[])
+}
+
+/// Like [boot_with_configuration], with plugin configuration `files` as `(name, content)`.
+pub fn boot_with_files(plugins: &[&str], extra: &str, files: &[(&str, &str)]) -> SocketAddr {
+    boot_with(plugins, serde_json::json!([]), extra, files)
+}
+
+/// Like [boot_with_script], with plugin configuration `files` as `(name, content)`.
+pub fn boot_with_script_and_files(plugins: &[&str], script: serde_json::Value, files: &[(&str, &str)]) -> SocketAddr {
+    boot_with(plugins, script, "", files)
+}
+
+fn boot_with(plugins: &[&str], script: serde_json::Value, extra: &str, files: &[(&str, &str)]) -> SocketAddr {
+    *SERVER_ADDR.get_or_init(|| {
+        pretty_env_logger::try_init().ok();
+        let port = free_port();
+        let directory = prepare_configuration(port, plugins, extra, files);
+        std::fs::write(directory.join("mock_ygopro.json"), script.to_string()).unwrap();
+        std::env::set_var("MOCK_YGOPRO_SCRIPT", directory.join("mock_ygopro.json"));
+        std::env::set_var("SRVPRU_CONFIG_PATH", &directory);
+        std::env::set_var("SRVPRO_CONFIG_PATH", directory.join("srvpro"));
+        std::thread::spawn(|| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                srvpru::init().await;
+                srvpru::register();
+                srvpru::start().await;
+            });
+        });
+        let addr: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+        let deadline = std::time::Instant::now() + TIMEOUT;
+        while std::net::TcpStream::connect(addr).is_err() {
+            assert!(std::time::Instant::now() < deadline, "srvpru didn't start");
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        addr
+    })
+}
+
+/// Port of api server, if `api` plugin is booted.
+pub fn api_port() -> u16 {
+    *API_PORT.get_or_init(free_port)
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  expect
+// ----------------------------------------------------------------------------------------------------
+/// [expect](YgoClient::expect) in [TIMEOUT], panic if not received.
+// ----------------------------------------------------------------------------------------------------
+pub async fn expect<T, F>(client: &mut YgoClient, filter: F) -> T
+    where T: MappedStruct, F: Fn(&T) -> bool {
+    match tokio::time::timeout(TIMEOUT, client.expect(filter)).await {
+        Ok(Some(message)) => message,
+        _ => panic!("Expected {:?} not received.", T::message())
+    }
+}
+
+/// Join `pass` as `name`.
+pub async fn join(addr: SocketAddr, name: &str, pass: &str) -> YgoClient {
+    YgoClient::connect(addr).player_info(name).join(pass).await.unwrap()
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  retry_connect
+// ----------------------------------------------------------------------------------------------------
+/// Call `connect` until it succeeds in [TIMEOUT], for listeners starting a bit later than srvpru, 
+/// such as api server.
+///
+/// #### Arguments
+/// * `listener`: what is connected, for the panic message.
+// ----------------------------------------------------------------------------------------------------
+pub async fn retry_connect<T, E, F, R>(listener: &str, mut connect: F) -> T
+    where F: FnMut() -> R, R: Future<Output = Result<T, E>> {
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    loop {
+        if let Ok(connection) = connect().await { return connection; }
+        assert!(tokio::time::Instant::now() < deadline, "{} didn't start", listener);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Data read from `reader`, ending when it's closed.
+pub fn read_chunks<R: AsyncRead + Unpin>(reader: &mut R) -> impl Stream<Item = Vec<u8>> + Unpin + '_ {
+    Box::pin(futures_util::stream::unfold(reader, |reader| async move {
+        let mut buf = [0; 10240];
+        match reader.read(&mut buf).await {
+            Ok(n) if n > 0 => Some((buf[..n].to_vec(), reader)),
+            _ => None
+        }
+    }))
+}
+
+// ----------------------------------------------------------------------------------------------------
+//  expect_enter
+// ----------------------------------------------------------------------------------------------------
+/// Wait for a `HsPlayerEnter` of `name` in [TIMEOUT], panic if not received.
+///
+/// For connections not made by [YgoClient], `data` is what they receive, in any chunks.
+// ----------------------------------------------------------------------------------------------------
+pub async fn expect_enter<S: Stream<Item = Vec<u8>> + Unpin>(data: &mut S, name: &str) {
+    let wait = async {
+        let mut framer = MessageFramer::new();
+        while let Some(chunk) = data.next().await {
+            let frames = match framer.feed(&chunk).unwrap() { Some(frames) => frames, None => continue };
+            let mut rest = &frames[..];
+            while rest.len() >= 3 {
+                let length = 2 + u16::from_le_bytes([rest[0], rest[1]]) as usize;
+                if try_get_message_type(Direction::STOC, rest[2]) == Some(stoc::HsPlayerEnter::message()) {
+                    let enter: stoc::HsPlayerEnter = bincode::deserialize(&rest[3..length]).unwrap();
+                    if cast_to_string(&enter.name).as_deref() == Some(name) { return; }
+                }
+                rest = &rest[length..];
+            }
+        }
+        panic!("Connection closed before {} entered.", name);
+    };
+    tokio::time::timeout(TIMEOUT, wait).await.expect("HsPlayerEnter not received");
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use srvpru::ygopro::Attribute;
+use srvpru::ygopro::ErrorMessage;
+use srvpru::ygopro::Linkmarkers;
+use srvpru::ygopro::Netplayer;
+use srvpru::ygopro::PlayerChange;
+use srvpru::ygopro::Race;
+use srvpru::ygopro::Type;
+use srvpru::ygopro::data::CARD_AVAILABLE_OCG;
+use srvpru::ygopro::data::CARD_AVAILABLE_TCG;
+use srvpru::ygopro::data::Card;
+use srvpru::ygopro::data::CardText;
+use srvpru::ygopro::data::Deck;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::stoc;
+
+const PLUGINS: &[&str] = &["player", "room", "deck_validator", "lock_deck"];
+
+fn test_card(code: u32) -> Card {
+    Card {
+        code, alias: 0, ot: CARD_AVAILABLE_OCG | CARD_AVAILABLE_TCG, setcode: 0, _type: Type::Trap, level: 0,
+        attribute: Attribute::empty(), race: Race::empty(),
+        attack: 0, defense: 0, left_scale: 0, right_scale: 0, link_marker: Linkmarkers::empty(),
+        text: CardText::default()
+    }
+}
+
+#[tokio::test]
+async fn offered_deck_is_validated() {
+    let codes = 900000010..900000024;
+    let registered = Deck { main: codes.clone().flat_map(|code| [code; 3]).take(40).collect(), side: vec![], ex: vec![] };
+    let directory = std::env::temp_dir().join(format!("srvpru-lock-deck-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("alice.ydk"), registered.to_ydk("registered")).unwrap();
+    let configuration = format!("deck_directory: {}\nlock_level: offer_deck\n", directory.display());
+    let addr = common::boot_with_files(PLUGINS, "", &[("lock_deck.yaml", &configuration)]);
+    Card::add_cards("lock_deck_test", codes.map(test_card).collect());
+
+    // No lflist.conf here, so the room has no banlist.
+    // Offered deck replaces this illegal one before deck_validator sees it.
+    let mut alice = common::join(addr, "alice", "NF#lock_deck_room").await;
+    common::expect::<stoc::JoinGame, _>(&mut alice, |_| true).await;
+    alice.send(&ctos::UpdateDeck { deck: Deck { main: vec![900000010; 4], side: vec![], ex: vec![] } }).await.unwrap();
+    alice.send(&ctos::HsReady).await.unwrap();
+    common::expect::<stoc::HsPlayerChange, _>(&mut alice, |change| change.status == PlayerChange::Ready(Netplayer::Player1)).await;
+
+    let mut bob = common::join(addr, "bob", "NF#lock_deck_room").await;
+    common::expect::<stoc::JoinGame, _>(&mut bob, |_| true).await;
+    bob.send(&ctos::UpdateDeck { deck: registered }).await.unwrap();
+    common::expect::<stoc::ErrorMessage, _>(&mut bob, |error| error.msg == ErrorMessage::Deckerror).await;
+    std::fs::remove_dir_all(&directory).ok();
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use srvpru::ygopro::message::stoc;
+
+const PLUGINS: &[&str] = &["player", "room", "api", "metrics"];
+
+async fn scrape() -> String {
+    let url = format!("http://127.0.0.1:{}/metrics", common::api_port());
+    common::retry_connect("Metrics api", || reqwest::get(&url)).await.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn rooms_players_and_handlers_exported() {
+    let addr = common::boot(PLUGINS);
+    let mut alice = common::join(addr, "alice", "M#metrics_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+    let metrics = scrape().await;
+    assert!(metrics.contains("srvpru_rooms{mode=\"Match\",stage=\"Void\"} 1"), "{}", metrics);
+    assert!(metrics.contains("srvpru_players 1"), "{}", metrics);
+    assert!(metrics.contains("srvpru_handler_calls_total{handler=\"room_producer\"} 1"), "{}", metrics);
+    assert!(metrics.contains("srvpru_handler_duration_seconds_count{handler=\"room_producer\"} 1"), "{}", metrics);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use reqwest::StatusCode;
+use serde_json::Value;
+use serde_json::json;
+
+use srvpru::ygopro::Colors;
+use srvpru::ygopro::ErrorMessage;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::stoc;
+use srvpru::ygopro::message::string::cast_to_c_array;
+use srvpru::ygopro::message::string::cast_to_string;
+
+const PLUGINS: &[&str] = &["player", "room", "api", "chat_command", "miniluv"];
+
+fn url(path: &str, key: &str) -> String {
+    format!("http://127.0.0.1:{}/miniluv{}?key={}", common::api_port(), path, key)
+}
+
+async fn list_penalties() -> Value {
+    let url = url("/penalties", "secret");
+    common::retry_connect("Miniluv api", || reqwest::get(&url)).await.json().await.unwrap()
+}
+
+async fn add_penalty(penalty: Value) -> i64 {
+    let response = reqwest::Client::new().post(url("/penalties", "secret")).json(&penalty).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+async fn delete_penalty(id: i64) -> StatusCode {
+    reqwest::Client::new().delete(url(&format!("/penalties/{}", id), "secret")).send().await.unwrap().status()
+}
+
+#[tokio::test]
+async fn ban_and_mute_players() {
+    let database = std::env::temp_dir().join(format!("srvpru-miniluv-{}.db", std::process::id()));
+    let configuration = format!("database: {}\naccess_key: secret\n", database.display());
+    let addr = common::boot_with_files(PLUGINS, "", &[("miniluv.yaml", &configuration)]);
+    assert_eq!(list_penalties().await, json!([]));
+    assert_eq!(reqwest::get(url("/penalties", "wrong")).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+    let ban = add_penalty(json!({ "kind": "ban", "name": "banned", "reason": "cheating" })).await;
+    add_penalty(json!({ "kind": "mute", "name": "muted" })).await;
+    // Expired penalties are neither listed nor enforced.
+    add_penalty(json!({ "kind": "ban", "name": "forgiven", "duration": -60 })).await;
+    let penalties = list_penalties().await;
+    let names: Vec<_> = penalties.as_array().unwrap().iter().map(|penalty| (penalty["kind"].as_str().unwrap(), penalty["name"].as_str().unwrap())).collect();
+    assert_eq!(names, [("ban", "banned"), ("mute", "muted")]);
+
+    let mut banned = common::join(addr, "banned", "miniluv_room").await;
+    common::expect::<stoc::ErrorMessage, _>(&mut banned, |error| error.msg == ErrorMessage::Joinerror).await;
+    let mut forgiven = common::join(addr, "forgiven", "miniluv_room").await;
+    common::expect::<stoc::JoinGame, _>(&mut forgiven, |_| true).await;
+
+    let mut muted = common::join(addr, "muted", "miniluv_room").await;
+    common::expect::<stoc::JoinGame, _>(&mut muted, |_| true).await;
+    muted.send(&ctos::Chat { msg: cast_to_c_array("hello") }).await.unwrap();
+    common::expect::<stoc::Chat, _>(&mut muted, |chat| chat.name == Colors::Red as u16).await;
+    forgiven.send(&ctos::Chat { msg: cast_to_c_array("hi") }).await.unwrap();
+    let chat = common::expect::<stoc::Chat, _>(&mut muted, |chat| chat.name < Colors::Lightblue as u16).await;
+    assert_eq!(cast_to_string(&chat.msg).as_deref(), Some("hi"));
+
+    assert_eq!(delete_penalty(ban).await, StatusCode::NO_CONTENT);
+    assert_eq!(delete_penalty(ban).await, StatusCode::NOT_FOUND);
+    let mut unbanned = common::join(addr, "banned", "miniluv_room").await;
+    common::expect::<stoc::JoinGame, _>(&mut unbanned, |_| true).await;
+    std::fs::remove_file(&database).ok();
+}
@@ -0,0 +1,17 @@
+mod common;
+
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::generate::wrap_mapped_struct;
+use srvpru::ygopro::message::stoc;
+
+#[tokio::test]
+async fn scripted_reply() {
+    let ready: u8 = ctos::MessageType::HsReady.into();
+    let script = serde_json::json!([{ "ctos": ready, "stoc": [wrap_mapped_struct(&stoc::SelectHand)] }]);
+    let addr = common::boot_with_script(&["player", "room"], script);
+    let mut alice = common::join(addr, "alice", "scripted_room").await;
+    common::expect::<stoc::JoinGame, _>(&mut alice, |_| true).await;
+    alice.send(&ctos::HsReady).await.unwrap();
+    common::expect::<stoc::HsPlayerChange, _>(&mut alice, |_| true).await;
+    common::expect::<stoc::SelectHand, _>(&mut alice, |_| true).await;
+}
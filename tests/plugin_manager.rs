@@ -0,0 +1,60 @@
+mod common;
+
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use srvpru::srvpru::get_server;
+use srvpru::ygopro::Colors;
+use srvpru::ygopro::message::stoc;
+
+const PLUGINS: &[&str] = &["player", "room", "api", "plugin_manager"];
+
+fn url(path: &str, key: &str) -> String {
+    format!("http://127.0.0.1:{}{}?key={}", common::api_port(), path, key)
+}
+
+async fn list_plugins() -> Value {
+    let url = url("/plugins", "secret");
+    common::retry_connect("Plugin manager api", || reqwest::get(&url)).await.json().await.unwrap()
+}
+
+async fn post(path: &str, key: &str) -> StatusCode {
+    reqwest::Client::new().post(url(path, key)).send().await.unwrap().status()
+}
+
+fn is_welcome(chat: &stoc::Chat) -> bool {
+    chat.name == Colors::Green as u16
+}
+
+#[tokio::test]
+async fn toggle_plugins_while_rooms_running() {
+    let addr = common::boot_with_files(PLUGINS, "", &[("plugin_manager.yaml", "access_key: secret\n")]);
+    let plugins = list_plugins().await;
+    assert_eq!(plugins["enabled"], serde_json::json!(PLUGINS));
+    assert!(plugins["available"].as_array().unwrap().contains(&Value::from("welcome")));
+
+    let mut alice = common::join(addr, "alice", "M#toggled_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+
+    assert_eq!(post("/plugins/welcome/enable", "secret").await, StatusCode::OK);
+    assert!(get_server().ctos_processor().contains_handler("welcome"));
+    let mut bob = common::join(addr, "bob", "M#toggled_room").await;
+    common::expect::<stoc::Chat, _>(&mut bob, is_welcome).await;
+    // Room created before is still served.
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+
+    assert_eq!(post("/plugins/welcome/enable", "wrong").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(post("/plugins/nonexistent/enable", "secret").await, StatusCode::NOT_FOUND);
+    assert_eq!(post("/plugins/room/disable", "secret").await, StatusCode::FORBIDDEN);
+    // Api of plugin_manager is registered once, it can't follow.
+    assert_eq!(post("/plugins/plugin_manager/disable", "secret").await, StatusCode::FORBIDDEN);
+
+    // srvpru.yaml doesn't list welcome.
+    assert_eq!(post("/plugins/reload", "secret").await, StatusCode::OK);
+    let deadline = tokio::time::Instant::now() + common::TIMEOUT;
+    while get_server().ctos_processor().contains_handler("welcome") {
+        assert!(tokio::time::Instant::now() < deadline, "Plugins are not reloaded");
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(list_plugins().await["enabled"], serde_json::json!(PLUGINS));
+}
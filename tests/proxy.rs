@@ -0,0 +1,68 @@
+mod common;
+
+use srvpru::srvpru::Room;
+use srvpru::ygopro::Netplayer;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::stoc;
+use srvpru::ygopro::message::string::cast_to_c_array;
+use srvpru::ygopro::message::string::cast_to_string;
+
+const PLUGINS: &[&str] = &["player", "room", "welcome"];
+
+fn name_of(name: &[u16]) -> String {
+    cast_to_string(name).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn join_room() {
+    let addr = common::boot(PLUGINS);
+    let mut alice = common::join(addr, "alice", "join_room").await;
+    common::expect::<stoc::JoinGame, _>(&mut alice, |_| true).await;
+    let type_change = common::expect::<stoc::TypeChange, _>(&mut alice, |_| true).await;
+    assert_eq!(type_change._type, 0x10);
+    let enter = common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+    assert_eq!(name_of(&enter.name), "alice");
+    // Sent by welcome plugin, not by ygopro.
+    let welcome = common::expect::<stoc::Chat, _>(&mut alice, |_| true).await;
+    assert!(name_of(&welcome.msg).contains("Srvpru Server"));
+    assert!(Room::get_room("join_room").is_some());
+}
+
+#[tokio::test]
+async fn players_share_room() {
+    let addr = common::boot(PLUGINS);
+    let mut alice = common::join(addr, "alice", "share_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |enter| name_of(&enter.name) == "alice").await;
+    let mut bob = common::join(addr, "bob", "share_room").await;
+    let enter = common::expect::<stoc::HsPlayerEnter, _>(&mut bob, |enter| name_of(&enter.name) == "alice").await;
+    assert_eq!(enter.pos, Netplayer::Player1);
+    let enter = common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |enter| name_of(&enter.name) == "bob").await;
+    assert_eq!(enter.pos, Netplayer::Player2);
+    assert_eq!(Room::get_room("share_room").unwrap().lock().players.len(), 2);
+}
+
+#[tokio::test]
+async fn chat_is_forwarded() {
+    let addr = common::boot(PLUGINS);
+    let mut alice = common::join(addr, "alice", "chat_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |enter| name_of(&enter.name) == "alice").await;
+    let mut bob = common::join(addr, "bob", "chat_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut bob, |enter| name_of(&enter.name) == "bob").await;
+    alice.send(&ctos::Chat { msg: cast_to_c_array("hello") }).await.unwrap();
+    let chat = common::expect::<stoc::Chat, _>(&mut bob, |chat| name_of(&chat.msg) == "hello").await;
+    assert_eq!(chat.name, Netplayer::Player1 as u16);
+}
+
+#[tokio::test]
+async fn room_destroyed_after_leave() {
+    let addr = common::boot(PLUGINS);
+    let mut alice = common::join(addr, "alice", "leave_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+    assert!(Room::get_room("leave_room").is_some());
+    drop(alice);
+    let deadline = tokio::time::Instant::now() + common::TIMEOUT;
+    while Room::get_room("leave_room").is_some() {
+        assert!(tokio::time::Instant::now() < deadline, "Room is not destroyed");
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+    }
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use srvpru::srvpru::Player;
+use srvpru::srvpru::Room;
+use srvpru::ygopro::client::YgoClient;
+use srvpru::ygopro::client::DEFAULT_VERSION;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::stoc;
+use srvpru::ygopro::message::string::cast_to_fix_length_array;
+
+const PLUGINS: &[&str] = &["player", "room"];
+
+fn boot() -> std::net::SocketAddr {
+    common::boot_with_configuration(PLUGINS, "proxy_protocol: true\n")
+}
+
+async fn join_after(addr: std::net::SocketAddr, header: &[u8], name: &str, pass: &str) -> YgoClient {
+    let mut client = YgoClient::open(addr).await.unwrap();
+    client.send_raw(header).await.unwrap();
+    client.send(&ctos::PlayerInfo { name: cast_to_fix_length_array(name) }).await.unwrap();
+    client.send(&ctos::JoinGame { version: DEFAULT_VERSION, align: 0, gameid: 0, pass: cast_to_fix_length_array(pass) }).await.unwrap();
+    client
+}
+
+#[tokio::test]
+async fn v1_source_is_player_address() {
+    let addr = boot();
+    let mut alice = join_after(addr, b"PROXY TCP4 203.0.113.7 10.0.0.1 40000 7911\r\n", "alice", "proxy_v1").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+    let source = "203.0.113.7:40000".parse().unwrap();
+    assert!(Player::get_player(source).is_some());
+    assert_eq!(Room::get_room_by_client_addr(source).unwrap().lock().name, "proxy_v1");
+}
+
+#[tokio::test]
+async fn v2_source_is_player_address() {
+    let addr = boot();
+    let mut header = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+    header.extend([0x21, 0x11, 0x00, 0x0C, 198, 51, 100, 9, 10, 0, 0, 1, 0x9C, 0x41, 0x1E, 0xE7]);
+    let mut bob = join_after(addr, &header, "bob", "proxy_v2").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut bob, |_| true).await;
+    assert!(Player::get_player("198.51.100.9:40001".parse().unwrap()).is_some());
+}
+
+#[tokio::test]
+async fn missing_header_is_refused() {
+    let addr = boot();
+    let mut carol = common::join(addr, "carol", "proxy_none").await;
+    assert!(tokio::time::timeout(common::TIMEOUT, carol.recv()).await.unwrap().is_none());
+    assert!(Room::get_room("proxy_none").is_none());
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use reqwest::StatusCode;
+
+use srvpru::ygopro::Colors;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::generate::wrap_mapped_struct;
+use srvpru::ygopro::message::string::cast_to_string;
+use srvpru::ygopro::message::stoc;
+
+const PLUGINS: &[&str] = &["player", "room", "api", "replay_archive"];
+const REPLAY: &[u8] = include_bytes!("fixtures/duel.yrp");
+
+fn replay_url(id: &str) -> String {
+    format!("http://127.0.0.1:{}/replay/{}", common::api_port(), id)
+}
+
+#[tokio::test]
+async fn archive_and_download_replays() {
+    let directory = std::env::temp_dir().join(format!("srvpru-replays-{}", std::process::id()));
+    let configuration = format!("storage: directory\npath: {}\n", directory.display());
+    // Each duelist receives the same replay, which is stored once.
+    let ready: u8 = ctos::MessageType::HsReady.into();
+    let replay = wrap_mapped_struct(&stoc::Replay { data: REPLAY.to_vec() });
+    let script = serde_json::json!([{ "ctos": ready, "stoc": [replay, replay, wrap_mapped_struct(&stoc::DuelEnd)] }]);
+    let addr = common::boot_with_script_and_files(PLUGINS, script, &[("replay_archive.yaml", &configuration)]);
+
+    let mut alice = common::join(addr, "alice", "replay_room").await;
+    common::expect::<stoc::JoinGame, _>(&mut alice, |_| true).await;
+    alice.send(&ctos::HsReady).await.unwrap();
+    let hint = common::expect::<stoc::Chat, _>(&mut alice, |chat| chat.name == Colors::Babyblue as u16).await;
+    let hint = cast_to_string(&hint.msg).unwrap();
+    let id = hint.rsplit(' ').next().unwrap();
+    assert_eq!(id.len(), 10, "{}", hint);
+    assert_eq!(std::fs::read(directory.join(format!("{}.yrp", id))).unwrap(), REPLAY);
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+
+    let response = common::retry_connect("Replay api", || reqwest::get(replay_url(id))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(&response.bytes().await.unwrap()[..], REPLAY);
+    assert_eq!(reqwest::get(replay_url("nonexistent")).await.unwrap().status(), StatusCode::NOT_FOUND);
+    assert_eq!(reqwest::get(replay_url("..%2Fsrvpru")).await.unwrap().status(), StatusCode::NOT_FOUND);
+    std::fs::remove_dir_all(&directory).ok();
+}
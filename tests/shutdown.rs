@@ -0,0 +1,37 @@
+mod common;
+
+use srvpru::srvpru::Room;
+use srvpru::srvpru::is_draining;
+use srvpru::srvpru::request_shutdown;
+use srvpru::ygopro::Colors;
+use srvpru::ygopro::message::stoc;
+
+const PLUGINS: &[&str] = &["player", "room"];
+
+fn is_warning(chat: &stoc::Chat) -> bool {
+    chat.name == Colors::Red as u16
+}
+
+#[tokio::test]
+async fn drain_then_kill_rooms() {
+    let addr = common::boot_with_configuration(PLUGINS, "drain_timeout: 2\n");
+    let mut alice = common::join(addr, "alice", "drain_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+
+    request_shutdown();
+    common::expect::<stoc::Chat, _>(&mut alice, is_warning).await;
+    assert!(is_draining());
+
+    // New games are refused while draining.
+    let mut bob = common::join(addr, "bob", "another_room").await;
+    common::expect::<stoc::Chat, _>(&mut bob, is_warning).await;
+    common::expect::<stoc::ErrorMessage, _>(&mut bob, |_| true).await;
+    assert!(Room::get_room("another_room").is_none());
+
+    // Lobby never ends by itself, so it's killed after drain_timeout.
+    let deadline = tokio::time::Instant::now() + common::TIMEOUT;
+    while Room::get_room("drain_room").is_some() {
+        assert!(tokio::time::Instant::now() < deadline, "Room is not killed");
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+    }
+}
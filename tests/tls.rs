@@ -0,0 +1,67 @@
+mod common;
+
+use std::sync::Arc;
+
+use once_cell::sync::OnceCell;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::webpki::DNSNameRef;
+
+use srvpru::ygopro::client::DEFAULT_VERSION;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::stoc;
+use srvpru::ygopro::message::generate::wrap_mapped_struct;
+use srvpru::ygopro::message::string::cast_to_fix_length_array;
+use srvpru::ygopro::message::string::cast_to_string;
+
+const PLUGINS: &[&str] = &["player", "room", "api", "metrics"];
+const CERTIFICATE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/localhost.crt");
+const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/localhost.key");
+
+static TLS_PORTS: OnceCell<(u16, u16)> = OnceCell::new();
+
+/// Boot with tls listeners beside plain ones, returning plain address and tls ports of game and api.
+fn boot() -> (std::net::SocketAddr, u16, u16) {
+    let (port, api_port) = *TLS_PORTS.get_or_init(|| (common::free_port(), common::free_port()));
+    let configuration = format!("tls:\n  certificate: {}\n  key: {}\n  port: {}\n  api_port: {}\n", CERTIFICATE, KEY, port, api_port);
+    (common::boot_with_configuration(PLUGINS, &configuration), port, api_port)
+}
+
+/// Api server starts a bit later than srvpru.
+async fn connect(port: u16) -> TlsStream<TcpStream> {
+    let mut configuration = ClientConfig::new();
+    configuration.root_store.add_pem_file(&mut &std::fs::read(CERTIFICATE).unwrap()[..]).unwrap();
+    let connector = TlsConnector::from(Arc::new(configuration));
+    let socket = common::retry_connect("Tls listener", || TcpStream::connect(("127.0.0.1", port))).await;
+    connector.connect(DNSNameRef::try_from_ascii_str("localhost").unwrap(), socket).await.unwrap()
+}
+
+#[tokio::test]
+async fn tls_and_plain_players_share_room() {
+    let (addr, port, _) = boot();
+    let mut alice = common::join(addr, "alice", "tls_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+
+    let mut bob = connect(port).await;
+    let mut data = wrap_mapped_struct(&ctos::PlayerInfo { name: cast_to_fix_length_array("bob") });
+    data.extend(wrap_mapped_struct(&ctos::JoinGame { version: DEFAULT_VERSION, align: 0, gameid: 0, pass: cast_to_fix_length_array("tls_room") }));
+    bob.write_all(&data).await.unwrap();
+
+    common::expect_enter(&mut common::read_chunks(&mut bob), "alice").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |enter| cast_to_string(&enter.name).as_deref() == Some("bob")).await;
+}
+
+#[tokio::test]
+async fn api_served_over_tls() {
+    let (_, _, api_port) = boot();
+    let mut stream = connect(api_port).await;
+    stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+    let mut response = String::new();
+    tokio::time::timeout(common::TIMEOUT, stream.read_to_string(&mut response)).await.unwrap().ok();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.contains("srvpru_players"), "{}", response);
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use futures_util::SinkExt;
+use futures_util::StreamExt;
+use tokio_tungstenite::tungstenite::Message;
+
+use srvpru::ygopro::client::DEFAULT_VERSION;
+use srvpru::ygopro::message::ctos;
+use srvpru::ygopro::message::stoc;
+use srvpru::ygopro::message::generate::wrap_mapped_struct;
+use srvpru::ygopro::message::string::cast_to_fix_length_array;
+use srvpru::ygopro::message::string::cast_to_string;
+
+const PLUGINS: &[&str] = &["player", "room", "api", "websocket"];
+
+type WebSocket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn connect() -> WebSocket {
+    let url = format!("ws://127.0.0.1:{}/ygopro", common::api_port());
+    let (socket, _) = common::retry_connect("Websocket api", || tokio_tungstenite::connect_async(&url)).await;
+    socket
+}
+
+#[tokio::test]
+async fn web_and_desktop_players_share_room() {
+    let addr = common::boot(PLUGINS);
+    let mut alice = common::join(addr, "alice", "web_room").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |_| true).await;
+
+    let mut bob = connect().await;
+    let mut data = wrap_mapped_struct(&ctos::PlayerInfo { name: cast_to_fix_length_array("bob") });
+    data.extend(wrap_mapped_struct(&ctos::JoinGame { version: DEFAULT_VERSION, align: 0, gameid: 0, pass: cast_to_fix_length_array("web_room") }));
+    // Frames can be split across messages.
+    let (head, tail) = data.split_at(5);
+    bob.send(Message::Binary(head.to_vec())).await.unwrap();
+    bob.send(Message::Binary(tail.to_vec())).await.unwrap();
+
+    let mut data = bob.filter_map(|message| async move { match message { Ok(Message::Binary(data)) => Some(data), _ => None } }).boxed();
+    common::expect_enter(&mut data, "alice").await;
+    common::expect::<stoc::HsPlayerEnter, _>(&mut alice, |enter| cast_to_string(&enter.name).as_deref() == Some("bob")).await;
+}